@@ -0,0 +1,39 @@
+use crate::TempMongo;
+
+/// A value that a web application needs to connect to the database, like an axum `State` or actix `Data`.
+///
+/// Get the value from a server with [`TempMongo::app_state()`], and run the application with [`TempMongo::run_app()`]
+/// to shut it down before the server is removed.
+/// Implement this trait for the state of an application, to create it in one call.
+pub trait FromTempMongo {
+	/// Create the value for a server.
+	fn from_temp_mongo(mongo: &TempMongo) -> Self;
+}
+
+/// A client connected to the server, which shares its connection pool with [`TempMongo::client()`].
+impl FromTempMongo for mongodb::Client {
+	fn from_temp_mongo(mongo: &TempMongo) -> Self {
+		mongo.client().clone()
+	}
+}
+
+/// The `test` database of the server.
+impl FromTempMongo for mongodb::Database {
+	fn from_temp_mongo(mongo: &TempMongo) -> Self {
+		mongo.client().database("test")
+	}
+}
+
+/// The options of the client of the server, to create a client with its own connection pool.
+impl FromTempMongo for mongodb::options::ClientOptions {
+	fn from_temp_mongo(mongo: &TempMongo) -> Self {
+		mongo.client_options().clone()
+	}
+}
+
+/// The connection string of the server, see [`TempMongo::uri()`].
+impl FromTempMongo for String {
+	fn from_temp_mongo(mongo: &TempMongo) -> Self {
+		mongo.uri()
+	}
+}
@@ -0,0 +1,163 @@
+//! Assertion helpers for the contents of collections.
+//!
+//! The assertions panic with a readable message if they fail, including pretty-printed documents.
+//! They also panic if a MongoDB operation fails while checking the assertion.
+//!
+//! ```no_run
+//! # #[cfg_attr(feature = "tokio-runtime", tokio::main)]
+//! # #[cfg_attr(feature = "async-std-runtime", async_std::main)]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use temp_mongo::TempMongo;
+//! use temp_mongo::assertions::{assert_collection_eq, assert_contains, assert_doc_count};
+//! use mongodb::bson::{doc, Document};
+//!
+//! let mongo = TempMongo::new().await?;
+//! let collection = mongo.client().database("test").collection::<Document>("animals");
+//! collection.insert_one(doc! { "species": "dog", "cute": "yes" }, None).await?;
+//!
+//! assert_doc_count(&collection, None, 1).await;
+//! assert_contains(&collection, doc! { "species": "dog" }).await;
+//! assert_collection_eq(&collection, &[doc! { "species": "dog", "cute": "yes" }]).await;
+//! # Ok(())
+//! # }
+//! ```
+
+use futures_util::stream::TryStreamExt;
+use mongodb::bson::{Bson, Document};
+use mongodb::options::FindOptions;
+use mongodb::{Collection, Database};
+use std::path::Path;
+
+use crate::util::{golden_diff, render_golden, CollectionDiff, DiffOptions};
+
+/// The environment variable that makes [`assert_matches_golden()`] update the golden files instead of comparing them.
+pub const UPDATE_GOLDEN_ENV: &str = "TEMP_MONGO_UPDATE_GOLDEN";
+
+/// The maximum number of documents to print when an assertion fails.
+const MAX_PRINTED_DOCUMENTS: i64 = 10;
+
+/// Assert that a collection contains exactly `expected` documents matching `filter`.
+pub async fn assert_doc_count(collection: &Collection<Document>, filter: impl Into<Option<Document>>, expected: u64) {
+	let filter = filter.into();
+	let count = match collection.count_documents(filter.clone(), None).await {
+		Ok(count) => count,
+		Err(e) => panic!("failed to count documents in {}: {e}", collection.namespace()),
+	};
+	if count != expected {
+		let filter = filter.unwrap_or_default();
+		panic!(
+			"assertion failed: expected {expected} documents in {} matching {}, but found {count}",
+			collection.namespace(),
+			pretty(&filter),
+		);
+	}
+}
+
+/// Assert that a collection contains at least one document that has all fields of `document`.
+pub async fn assert_contains(collection: &Collection<Document>, document: Document) {
+	let count = match collection.count_documents(document.clone(), None).await {
+		Ok(count) => count,
+		Err(e) => panic!("failed to count documents in {}: {e}", collection.namespace()),
+	};
+	if count == 0 {
+		let options = FindOptions::builder().limit(MAX_PRINTED_DOCUMENTS).build();
+		let found = find(collection, options).await;
+		panic!(
+			"assertion failed: no document in {} matches\n{}\nfirst documents in the collection:\n{}",
+			collection.namespace(),
+			pretty(&document),
+			pretty_all(&found),
+		);
+	}
+}
+
+/// Assert that a collection contains exactly the `expected` documents, in any order.
+///
+/// The `_id` field is ignored for both the expected and the actual documents.
+pub async fn assert_collection_eq(collection: &Collection<Document>, expected: &[Document]) {
+	let strip = |mut document: Document| {
+		document.remove("_id");
+		document
+	};
+	let actual = find(collection, None).await.into_iter().map(strip).collect();
+	let expected = expected.iter().cloned().map(strip).collect();
+	let diff = CollectionDiff::compute(collection.name(), expected, actual);
+	if !diff.is_empty() {
+		let mut message = format!("assertion failed: contents of {} differ from the expected documents\n", collection.namespace());
+		for document in &diff.missing {
+			message += &format!("missing document:\n{}\n", pretty(document));
+		}
+		for document in &diff.extra {
+			message += &format!("unexpected document:\n{}\n", pretty(document));
+		}
+		for mismatch in &diff.mismatched {
+			message += &format!(
+				"document differs in fields {:?}:\nexpected:\n{}\nactual:\n{}\n",
+				mismatch.fields,
+				pretty(&mismatch.expected),
+				pretty(&mismatch.actual),
+			);
+		}
+		panic!("{message}");
+	}
+}
+
+/// Assert that collections of a database match a golden file, ignoring the `_id` field.
+///
+/// The collections are rendered as canonical JSON with sorted keys and documents, see [`render_golden()`](crate::render_golden).
+/// If they differ from the file, the assertion fails with a line diff.
+///
+/// If the environment variable [`UPDATE_GOLDEN_ENV`] is set to `1`, the golden file is written instead,
+/// so the expected state can be recorded or updated by running the tests once.
+pub async fn assert_matches_golden(database: &Database, collections: &[&str], path: impl AsRef<Path>) {
+	let path = path.as_ref();
+	let actual = match render_golden(database, collections, &DiffOptions::new()).await {
+		Ok(actual) => actual,
+		Err(e) => panic!("{e}"),
+	};
+	if std::env::var_os(UPDATE_GOLDEN_ENV).is_some_and(|value| value == "1") {
+		if let Err(e) = std::fs::write(path, &actual) {
+			panic!("failed to write golden file {}: {e}", path.display());
+		}
+		return;
+	}
+	let expected = match std::fs::read_to_string(path) {
+		Ok(expected) => expected,
+		Err(e) => panic!("failed to read golden file {}: {e}
+run the test with {UPDATE_GOLDEN_ENV}=1 to create it", path.display()),
+	};
+	if let Some(diff) = golden_diff(&expected, &actual) {
+		panic!(
+			"assertion failed: contents of {} differ from golden file {}
+{diff}run the test with {UPDATE_GOLDEN_ENV}=1 to update it",
+			database.name(),
+			path.display(),
+		);
+	}
+}
+
+/// Find all documents in a collection, panicking on errors.
+async fn find(collection: &Collection<Document>, options: impl Into<Option<FindOptions>>) -> Vec<Document> {
+	let result = match collection.find(None, options).await {
+		Ok(cursor) => cursor.try_collect().await,
+		Err(e) => Err(e),
+	};
+	match result {
+		Ok(documents) => documents,
+		Err(e) => panic!("failed to read documents from {}: {e}", collection.namespace()),
+	}
+}
+
+/// Pretty-print a document as relaxed extended JSON.
+fn pretty(document: &Document) -> String {
+	let json = Bson::Document(document.clone()).into_relaxed_extjson();
+	serde_json::to_string_pretty(&json).unwrap_or_else(|_| document.to_string())
+}
+
+/// Pretty-print a list of documents.
+fn pretty_all(documents: &[Document]) -> String {
+	if documents.is_empty() {
+		return "(none)".to_string();
+	}
+	documents.iter().map(pretty).collect::<Vec<_>>().join("\n")
+}
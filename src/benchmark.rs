@@ -0,0 +1,96 @@
+use crate::error::ErrorInner;
+use crate::{Error, TempMongo, TempMongoBuilder};
+use std::future::Future;
+
+/// A temporary MongoDB instance for benchmarks, like with [`criterion`](https://crates.io/crates/criterion).
+///
+/// Unlike [`TempMongo`], this is created synchronously and owns the runtime that spawned the server,
+/// so it works in the synchronous benchmark functions of criterion.
+/// Operations are run synchronously with [`Self::block_on()`] or [`Self::run()`].
+///
+/// The synchronous API of the driver is not used, since enabling it makes
+/// [`ClientOptions::parse()`](mongodb::options::ClientOptions::parse) synchronous for the whole build.
+///
+/// The server is killed and its temporary directory removed when this object is dropped.
+pub struct BenchmarkMongo {
+	/// The server, which is only `None` while this object is dropped.
+	mongo: Option<TempMongo>,
+
+	/// The runtime that spawned the server, and that drives its client.
+	runtime: tokio::runtime::Runtime,
+}
+
+impl std::fmt::Debug for BenchmarkMongo {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("BenchmarkMongo")
+			.field("mongo", &self.mongo)
+			.finish_non_exhaustive()
+	}
+}
+
+impl BenchmarkMongo {
+	/// Spawn a server tuned for benchmarks, with the fast startup profile, see [`TempMongoBuilder::fast_startup()`].
+	pub fn new() -> Result<Self, Error> {
+		Self::with_builder(&TempMongo::builder().fast_startup(true))
+	}
+
+	/// Spawn a server with the options of a builder.
+	pub fn with_builder(builder: &TempMongoBuilder) -> Result<Self, Error> {
+		let runtime = tokio::runtime::Builder::new_multi_thread()
+			.thread_name("temp-mongo-benchmark")
+			.enable_all()
+			.build()
+			.map_err(ErrorInner::StartRuntime)?;
+		let mongo = runtime.block_on(builder.spawn())?;
+		Ok(Self {
+			mongo: Some(mongo),
+			runtime,
+		})
+	}
+
+	/// Get the server.
+	pub fn mongo(&self) -> &TempMongo {
+		self.mongo.as_ref().expect("server is not dropped yet")
+	}
+
+	/// Get the client connected to the server.
+	///
+	/// The client must be used in [`Self::block_on()`], or from another task of the runtime of the server.
+	pub fn client(&self) -> &mongodb::Client {
+		self.mongo().client()
+	}
+
+	/// Get the runtime of the server, for example for the `to_async()` adapter of criterion.
+	pub fn runtime(&self) -> &tokio::runtime::Runtime {
+		&self.runtime
+	}
+
+	/// Run a future to completion on the runtime of the server.
+	pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+		self.runtime.block_on(future)
+	}
+
+	/// Run an operation with the client synchronously, like `mongo.run(|client| async move { ... })`.
+	pub fn run<F, Fut>(&self, operation: F) -> Fut::Output
+	where
+		F: FnOnce(mongodb::Client) -> Fut,
+		Fut: Future,
+	{
+		self.block_on(operation(self.client().clone()))
+	}
+
+	/// Remove all data from the server between benchmark iterations, see [`TempMongo::reset()`].
+	///
+	/// Use this in the setup routine of `iter_batched()`, so the reset is not included in the measurement.
+	pub fn reset_between_iterations(&self) -> Result<(), Error> {
+		self.block_on(self.mongo().reset())
+	}
+}
+
+impl Drop for BenchmarkMongo {
+	fn drop(&mut self) {
+		if let Some(mongo) = self.mongo.take() {
+			self.runtime.block_on(mongo.kill_and_clean()).ok();
+		}
+	}
+}
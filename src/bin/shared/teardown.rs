@@ -0,0 +1,46 @@
+//! Wait for the signal to tear down a server that is served from a binary.
+
+/// Block the teardown signals and return a future that completes when `SIGTERM`, `SIGINT` or `SIGHUP` is received.
+///
+/// The signals must be blocked before other threads are started, so they are only received by `sigwait()`.
+#[cfg(unix)]
+pub fn prepare() -> impl std::future::Future<Output = ()> {
+	// SAFETY: The signal set is initialized by `sigemptyset()` before it is used.
+	let signals = unsafe {
+		let mut signals = std::mem::zeroed();
+		libc::sigemptyset(&mut signals);
+		for signal in [libc::SIGTERM, libc::SIGINT, libc::SIGHUP] {
+			libc::sigaddset(&mut signals, signal);
+		}
+		libc::pthread_sigmask(libc::SIG_BLOCK, &signals, std::ptr::null_mut());
+		signals
+	};
+
+	let (sender, receiver) = tokio::sync::oneshot::channel();
+	std::thread::spawn(move || {
+		let mut signal = 0;
+		// SAFETY: The signal set is initialized and the signal is written to a valid integer.
+		unsafe {
+			libc::sigwait(&signals, &mut signal);
+		}
+		sender.send(()).ok();
+	});
+	async move {
+		receiver.await.ok();
+	}
+}
+
+/// Return a future that completes when standard input is closed.
+#[cfg(not(unix))]
+pub fn prepare() -> impl std::future::Future<Output = ()> {
+	use std::io::Read;
+
+	let (sender, receiver) = tokio::sync::oneshot::channel();
+	std::thread::spawn(move || {
+		std::io::stdin().lock().read_to_end(&mut Vec::new()).ok();
+		sender.send(()).ok();
+	});
+	async move {
+		receiver.await.ok();
+	}
+}
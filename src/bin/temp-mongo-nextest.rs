@@ -0,0 +1,79 @@
+//! Spawn a temporary MongoDB server for all tests of a `cargo-nextest` run.
+//!
+//! Use this as a setup script in `.config/nextest.toml`:
+//!
+//! ```toml
+//! [script.mongodb]
+//! command = "temp-mongo-nextest --detach"
+//! ```
+//!
+//! With `--detach`, the server is kept running by a background process, and the command exits once the server is ready,
+//! so nextest can start the tests with `MONGODB_URI` set to the connection string of the server.
+//! Without `--detach`, the command keeps running in the foreground.
+//!
+//! The server is cleaned up when the serving process receives `SIGTERM`, `SIGINT` or `SIGHUP`,
+//! for example with `kill $TEMP_MONGO_PID` in a wrapper script after the test run.
+//! On other platforms than Unix, the server is cleaned up when standard input is closed.
+
+#[path = "shared/teardown.rs"]
+mod teardown;
+
+use std::io::BufRead;
+use std::process::{Command, ExitCode, Stdio};
+
+fn main() -> ExitCode {
+	let mut detach = false;
+	for arg in std::env::args().skip(1) {
+		match arg.as_str() {
+			"--detach" => detach = true,
+			_ => {
+				eprintln!("Usage: temp-mongo-nextest [--detach]");
+				return ExitCode::FAILURE;
+			},
+		}
+	}
+
+	let result = if detach { detach_server() } else { serve() };
+	match result {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(e) => {
+			eprintln!("temp-mongo-nextest: {e}");
+			ExitCode::FAILURE
+		},
+	}
+}
+
+/// Serve the server in the foreground until teardown is signaled.
+fn serve() -> Result<(), Box<dyn std::error::Error>> {
+	let teardown = teardown::prepare();
+	let runtime = tokio::runtime::Runtime::new()?;
+	runtime.block_on(temp_mongo::serve_for_nextest(&temp_mongo::TempMongo::builder(), teardown))?;
+	Ok(())
+}
+
+/// Serve the server from a background process, and exit once the server is ready.
+///
+/// The environment variables of the background process are forwarded to standard output.
+fn detach_server() -> Result<(), Box<dyn std::error::Error>> {
+	let mut command = Command::new(std::env::current_exe()?);
+	command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::null());
+	#[cfg(unix)]
+	{
+		use std::os::unix::process::CommandExt;
+		// A new process group, so the server is not interrupted together with the process that started it.
+		command.process_group(0);
+	}
+	let mut child = command.spawn()?;
+
+	let stdout = child.stdout.take().ok_or("failed to read output of background process")?;
+	let mut variables = 0;
+	for line in std::io::BufReader::new(stdout).lines() {
+		println!("{}", line?);
+		variables += 1;
+		if variables == 2 {
+			return Ok(());
+		}
+	}
+	let status = child.wait()?;
+	Err(format!("background process exited before the server was ready: {status}").into())
+}
@@ -0,0 +1,186 @@
+//! Manage temporary MongoDB servers from the command line, for manual debugging and for test harnesses in other languages.
+//!
+//! * `temp-mongo up` spawns a server in a background process, and prints its ID and connection string.
+//! * `temp-mongo list` prints the ID and connection string of all running servers.
+//! * `temp-mongo down <id>` stops a server and removes its temporary directory.
+//! * `temp-mongo clean` forgets servers whose background process is gone, and `temp-mongo clean --all` also stops all running servers.
+//!
+//! The ID of a server is the PID of its background process.
+//! The running servers are tracked in the `temp-mongo-instances` directory in the temporary directory of the system.
+//! Only available on Unix, with the `cli` feature.
+
+#[cfg(unix)]
+#[path = "shared/teardown.rs"]
+mod teardown;
+
+use std::process::ExitCode;
+
+/// The usage of the command.
+const USAGE: &str = "Usage: temp-mongo up | list | down <id> | clean [--all]";
+
+fn main() -> ExitCode {
+	let args: Vec<String> = std::env::args().skip(1).collect();
+	let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+	#[cfg(unix)]
+	let result = unix::run(&args);
+	#[cfg(not(unix))]
+	let result: Result<(), Box<dyn std::error::Error>> = Err("only supported on Unix".into());
+
+	match result {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(e) => {
+			eprintln!("temp-mongo: {e}");
+			ExitCode::FAILURE
+		},
+	}
+}
+
+#[cfg(unix)]
+mod unix {
+	use super::{teardown, USAGE};
+	use std::io::BufRead;
+	use std::path::PathBuf;
+	use std::process::{Command, Stdio};
+	use std::time::{Duration, Instant};
+
+	/// The result of a subcommand.
+	type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+	/// The time to wait for a server to be cleaned up by `down`.
+	const DOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+	/// Run a subcommand.
+	pub fn run(args: &[&str]) -> Result<()> {
+		match args {
+			["up"] => up(),
+			["list"] => list(),
+			["down", id] => down(id.parse().map_err(|_| format!("invalid ID: {id}"))?),
+			["clean"] => clean(false),
+			["clean", "--all"] => clean(true),
+			// Run by `up` in the background process.
+			["serve"] => serve(),
+			_ => Err(USAGE.into()),
+		}
+	}
+
+	/// Get the directory in which the running servers are tracked.
+	fn registry() -> PathBuf {
+		std::env::temp_dir().join("temp-mongo-instances")
+	}
+
+	/// Get the IDs and connection strings of all tracked servers, sorted by ID.
+	fn tracked() -> Result<Vec<(u32, String)>> {
+		let entries = match std::fs::read_dir(registry()) {
+			Ok(entries) => entries,
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+			Err(e) => return Err(e.into()),
+		};
+		let mut servers = Vec::new();
+		for entry in entries {
+			let entry = entry?;
+			let Some(id) = entry.file_name().to_str().and_then(|name| name.parse().ok()) else {
+				continue;
+			};
+			let uri = std::fs::read_to_string(entry.path())?;
+			servers.push((id, uri.trim().to_owned()));
+		}
+		servers.sort();
+		Ok(servers)
+	}
+
+	/// Check if the background process of a server is still running.
+	fn is_running(id: u32) -> bool {
+		// SAFETY: Signal 0 only checks if the process exists.
+		unsafe { libc::kill(id as libc::pid_t, 0) == 0 }
+	}
+
+	/// Spawn a server in a background process, and print its ID and connection string once it is ready.
+	fn up() -> Result<()> {
+		use std::os::unix::process::CommandExt;
+
+		let mut child = Command::new(std::env::current_exe()?)
+			.arg("serve")
+			.stdin(Stdio::null())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::null())
+			// A new process group, so the server is not interrupted together with the terminal.
+			.process_group(0)
+			.spawn()?;
+
+		let stdout = child.stdout.take().ok_or("failed to read output of background process")?;
+		for line in std::io::BufReader::new(stdout).lines() {
+			if let Some(uri) = line?.strip_prefix("MONGODB_URI=") {
+				println!("{}\t{uri}", child.id());
+				return Ok(());
+			}
+		}
+		let status = child.wait()?;
+		Err(format!("background process exited before the server was ready: {status}").into())
+	}
+
+	/// Serve a server until teardown is signaled, and track it in the registry meanwhile.
+	fn serve() -> Result<()> {
+		let teardown = teardown::prepare();
+		let id = std::process::id();
+		let path = registry().join(id.to_string());
+		let runtime = tokio::runtime::Runtime::new()?;
+		runtime.block_on(async {
+			let mongo = temp_mongo::TempMongo::new().await?;
+			std::fs::create_dir_all(registry())?;
+			std::fs::write(&path, mongo.uri())?;
+			println!("MONGODB_URI={}", mongo.uri());
+
+			teardown.await;
+			let result = mongo.kill_and_clean().await;
+			std::fs::remove_file(&path).ok();
+			result?;
+			Ok(())
+		})
+	}
+
+	/// Print the ID and connection string of all running servers.
+	fn list() -> Result<()> {
+		for (id, uri) in tracked()? {
+			if is_running(id) {
+				println!("{id}\t{uri}");
+			}
+		}
+		Ok(())
+	}
+
+	/// Stop a server, and wait until it is cleaned up.
+	fn down(id: u32) -> Result<()> {
+		let path = registry().join(id.to_string());
+		if !path.exists() || !is_running(id) {
+			return Err(format!("no running server with ID {id}").into());
+		}
+		// SAFETY: Sending a signal has no memory safety requirements.
+		unsafe {
+			libc::kill(id as libc::pid_t, libc::SIGTERM);
+		}
+		let start = Instant::now();
+		while is_running(id) {
+			if start.elapsed() > DOWN_TIMEOUT {
+				return Err(format!("server with ID {id} did not stop within {DOWN_TIMEOUT:?}").into());
+			}
+			std::thread::sleep(Duration::from_millis(50));
+		}
+		std::fs::remove_file(&path).ok();
+		Ok(())
+	}
+
+	/// Forget servers whose background process is gone, and stop all running servers if `all` is set.
+	fn clean(all: bool) -> Result<()> {
+		for (id, _uri) in tracked()? {
+			if is_running(id) {
+				if all {
+					down(id)?;
+				}
+			} else {
+				std::fs::remove_file(registry().join(id.to_string()))?;
+			}
+		}
+		Ok(())
+	}
+}
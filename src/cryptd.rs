@@ -0,0 +1,276 @@
+use crate::error::ErrorInner;
+use crate::util::{event, server_output_tail, spawn_on_free_port, KillOnDrop, ResourceLimits, TempDir, DEFAULT_PORT_RETRIES};
+use crate::Error;
+use mongodb::bson::doc;
+use mongodb::options::{ClientOptions, ServerAddress};
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// The component that analyzes queries for automatic client-side field level encryption.
+///
+/// See [`TempMongoBuilder::with_csfle()`][crate::TempMongoBuilder::with_csfle].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryAnalysis {
+	/// Spawn a temporary `mongocryptd` process next to the server.
+	///
+	/// The `mongocryptd` command can be set with the `TEMP_MONGO_MONGOCRYPTD` environment variable.
+	Mongocryptd,
+
+	/// Load the `crypt_shared` library from the given path in the client.
+	CryptShared(PathBuf),
+}
+
+/// A temporary `mongocryptd` process, for testing client-side field level encryption.
+///
+/// The process listens on a free TCP port on the loopback adapter.
+/// Its log and PID files are stored in a temporary directory.
+/// Unless disabled, the temporary directory is deleted when this object is dropped.
+pub struct TempMongoCryptd {
+	port: u16,
+	log_path: PathBuf,
+	client: mongodb::Client,
+	server: KillOnDrop,
+
+	/// The temporary state directory.
+	///
+	/// Declared last, so it is only removed after the process is killed.
+	tempdir: TempDir,
+}
+
+impl std::fmt::Debug for TempMongoCryptd {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("TempMongoCryptd")
+			.field("tempdir", &self.tempdir.path())
+			.field("uri", &self.uri())
+			.field("server_pid", &self.server.id())
+			.finish_non_exhaustive()
+	}
+}
+
+impl TempMongoCryptd {
+	/// Spawn a new `mongocryptd` process with the default options.
+	pub async fn new() -> Result<Self, Error> {
+		Self::from_builder(&TempMongoCryptdBuilder::new()).await
+	}
+
+	/// Create a builder to customize your [`TempMongoCryptd`].
+	pub fn builder() -> TempMongoCryptdBuilder {
+		TempMongoCryptdBuilder::new()
+	}
+
+	/// Get the PID of the `mongocryptd` process.
+	pub fn process_id(&self) -> u32 {
+		self.server.id()
+	}
+
+	/// Get the path of the temporary state directory.
+	pub fn directory(&self) -> &Path {
+		self.tempdir.path()
+	}
+
+	/// Get the TCP port `mongocryptd` is listening on.
+	pub fn port(&self) -> u16 {
+		self.port
+	}
+
+	/// Get the connection string for `mongocryptd`.
+	///
+	/// Pass this as `mongocryptdURI` in the extra options of the auto encryption options of a client.
+	pub fn uri(&self) -> String {
+		format!("mongodb://127.0.0.1:{}", self.port)
+	}
+
+	/// Get the path of the log file of `mongocryptd`.
+	pub fn log_path(&self) -> &Path {
+		&self.log_path
+	}
+
+	/// Get a client that is directly connected to `mongocryptd`.
+	pub fn client(&self) -> &mongodb::Client {
+		&self.client
+	}
+
+	/// Enable or disable clean-up of the temporary directory when this object is dropped.
+	pub fn set_clean_on_drop(&mut self, clean_on_drop: bool) {
+		self.tempdir.set_clean_on_drop(clean_on_drop);
+	}
+
+	/// Kill the process and remove the temporary state directory on the filesystem.
+	///
+	/// This function ignores the value of `clean_on_drop`.
+	/// It also allows for better error handling compared to just dropping the object.
+	pub async fn kill_and_clean(mut self) -> Result<(), Error> {
+		self.client.clone().shutdown_immediate().await;
+		self.server.kill().map_err(ErrorInner::KillServer)?;
+		self.server.wait().map_err(ErrorInner::KillServer)?;
+
+		let path = self.tempdir.path().to_owned();
+		self.tempdir
+			.close()
+			.map_err(|e| ErrorInner::CleanDir(path, e))?;
+		Ok(())
+	}
+
+	/// Create a temporary directory, spawn `mongocryptd` and wait until it accepts connections.
+	async fn from_builder(builder: &TempMongoCryptdBuilder) -> Result<Self, Error> {
+		let tempdir = builder.make_temp_dir().map_err(ErrorInner::MakeTempDir)?;
+		let log_path = tempdir.path().join("mongocryptd.log");
+		let output_path = tempdir.path().join("mongocryptd.out");
+
+		let make_args = |port: u16| {
+			let mut args: Vec<OsString> = vec![
+				"--bind_ip".into(),
+				"127.0.0.1".into(),
+				"--port".into(),
+				port.to_string().into(),
+				"--logpath".into(),
+				log_path.clone().into(),
+				"--pidfilepath".into(),
+				tempdir.path().join("mongocryptd.pid").into(),
+			];
+			if let Some(timeout) = builder.idle_shutdown_timeout {
+				args.extend(["--idleShutdownTimeoutSecs".into(), timeout.as_secs().to_string().into()]);
+			}
+			args
+		};
+		let (port, mut server) = spawn_on_free_port(builder.get_command(), &output_path, &log_path, &ResourceLimits::new(), builder.port_retries, make_args).await?;
+
+		let address = format!("127.0.0.1:{port}");
+		let client_options = ClientOptions::builder()
+			.hosts(vec![ServerAddress::Tcp {
+				host: "127.0.0.1".into(),
+				port: Some(port),
+			}])
+			.connect_timeout(Duration::from_millis(100))
+			.direct_connection(true)
+			.build();
+		let client = mongodb::Client::with_options(client_options)
+			.map_err(|e| ErrorInner::Connect(address.clone(), e))?;
+
+		// Retry a few times, since the process may still be starting.
+		let mut attempt = 0;
+		loop {
+			match client.database("admin").run_command(doc! { "hello": 1 }, None).await {
+				Ok(_) => {
+					event!(debug, target: "temp_mongo::spawn", "mongocryptd at {address} is ready");
+					break;
+				},
+				Err(_) if attempt < 50 => attempt += 1,
+				Err(e) => {
+					let output = server_output_tail(&output_path, &log_path);
+					return Err(Error::from(ErrorInner::Connect(address, e)).with_server_output(output, server.try_wait().ok().flatten()));
+				},
+			}
+			sleep(Duration::from_millis(100)).await;
+		}
+
+		Ok(Self {
+			port,
+			log_path,
+			client,
+			server,
+			tempdir,
+		})
+	}
+}
+
+/// Builder for customizing your [`TempMongoCryptd`] object.
+///
+/// After configuring the desired options, run [`TempMongoCryptdBuilder::spawn()`].
+#[derive(Debug, Clone)]
+pub struct TempMongoCryptdBuilder {
+	/// The parent directory for the temporary directory.
+	///
+	/// Use the system default if set to `None`.
+	parent_directory: Option<PathBuf>,
+
+	/// Clean up the temprorary directory when the [`TempMongoCryptd`] object is dropped.
+	clean_on_drop: bool,
+
+	/// The `mongocryptd` command to execute.
+	command: Option<OsString>,
+
+	/// The time without connections after which `mongocryptd` exits by itself.
+	///
+	/// Use the `mongocryptd` default if set to `None`.
+	idle_shutdown_timeout: Option<Duration>,
+
+	/// The number of times to restart `mongocryptd` on a new port if its port was taken.
+	port_retries: u32,
+}
+
+impl TempMongoCryptdBuilder {
+	/// Create a new builder.
+	pub fn new() -> Self {
+		Self {
+			parent_directory: None,
+			clean_on_drop: true,
+			command: std::env::var_os("TEMP_MONGO_MONGOCRYPTD").filter(|command| !command.is_empty()),
+			idle_shutdown_timeout: None,
+			port_retries: DEFAULT_PORT_RETRIES,
+		}
+	}
+
+	/// Spawn `mongocryptd` and connect to it.
+	pub async fn spawn(&self) -> Result<TempMongoCryptd, Error> {
+		TempMongoCryptd::from_builder(self).await
+	}
+
+	/// Enable or disable cleaning of the temporary state directory when the [`TempMongoCryptd`] object is dropped.
+	pub fn clean_on_drop(mut self, clean_on_drop: bool) -> Self {
+		self.clean_on_drop = clean_on_drop;
+		self
+	}
+
+	/// Overwrite the `mongocryptd` command to run.
+	///
+	/// Can be used to run a `mongocryptd` binary from an alternative location.
+	/// Defaults to the value of the `TEMP_MONGO_MONGOCRYPTD` environment variable if it is set, or `mongocryptd` otherwise.
+	pub fn mongocryptd_command(mut self, command: impl Into<OsString>) -> Self {
+		self.command = Some(command.into());
+		self
+	}
+
+	/// Set the time without connections after which `mongocryptd` exits by itself.
+	///
+	/// Note that the client held by the [`TempMongoCryptd`] object keeps a connection open.
+	pub fn idle_shutdown_timeout(mut self, timeout: Duration) -> Self {
+		self.idle_shutdown_timeout = Some(timeout);
+		self
+	}
+
+	/// Set the number of times to restart `mongocryptd` on a new port if its port was taken by another process.
+	///
+	/// A free port is selected before `mongocryptd` is started, so another process may bind to it first.
+	/// Defaults to 3.
+	pub fn port_retries(mut self, retries: u32) -> Self {
+		self.port_retries = retries;
+		self
+	}
+
+	/// Get the command to execute to run `mongocryptd`.
+	pub fn get_command(&self) -> &OsStr {
+		self.command.as_deref().unwrap_or("mongocryptd".as_ref())
+	}
+
+	/// Get the command to execute to run `mongocryptd` as a string, for diagnostic purposes.
+	pub fn get_command_string(&self) -> String {
+		self.get_command().to_string_lossy().into()
+	}
+
+	/// Create a temporary directory according to the configuration of the builder.
+	fn make_temp_dir(&self) -> std::io::Result<TempDir> {
+		match &self.parent_directory {
+			Some(dir) => TempDir::new_in(dir, self.clean_on_drop),
+			None => TempDir::new(self.clean_on_drop),
+		}
+	}
+}
+
+impl Default for TempMongoCryptdBuilder {
+	fn default() -> Self {
+		Self::new()
+	}
+}
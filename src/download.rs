@@ -0,0 +1,694 @@
+use crate::error::ErrorInner;
+use crate::util::{event, RetryPolicy};
+use crate::Error;
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The time between checks of the progress of a download.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Downloader for official MongoDB server binaries.
+///
+/// The downloaded binaries are extracted into a per-user cache directory and reused by later downloads of the same version.
+/// Downloading uses the `curl` and `tar` commands, which must be available in `PATH`.
+/// Before extracting, the archive is verified against the SHA-256 checksum published next to it.
+///
+/// Like `curl` itself, the downloader honors the `HTTPS_PROXY` and `NO_PROXY` environment variables,
+/// unless a proxy is configured explicitly with [`Self::proxy()`].
+///
+/// ```no_run
+/// # #[cfg_attr(feature = "tokio-runtime", tokio::main)]
+/// # #[cfg_attr(feature = "async-std-runtime", async_std::main)]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use temp_mongo::{MongoDownloader, TempMongo};
+///
+/// let binaries = MongoDownloader::new("7.0.5").download()?;
+/// let mongo = TempMongo::builder()
+///     .mongod_command(binaries.mongod())
+///     .spawn()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// To test features that are only available in MongoDB Enterprise, like the `inMemory` storage engine or auditing,
+/// download the enterprise edition with [`Self::edition()`].
+/// Percona Server for MongoDB is not downloaded by this type,
+/// but its `mongod` binary can be used with [`TempMongoBuilder::mongod_command()`][crate::TempMongoBuilder::mongod_command].
+#[derive(Debug, Clone)]
+pub struct MongoDownloader {
+	/// The MongoDB version to download.
+	version: String,
+
+	/// The platform to download binaries for, like `linux-x86_64-ubuntu2204`.
+	///
+	/// Detected automatically if set to `None`.
+	platform: Option<String>,
+
+	/// The edition of MongoDB to download.
+	edition: Edition,
+
+	/// The license of MongoDB Enterprise was accepted.
+	accept_enterprise_license: bool,
+
+	/// The root directory of the binary cache.
+	///
+	/// Use the platform cache directory if set to `None`.
+	cache_dir: Option<PathBuf>,
+
+	/// Verify the published SHA-256 checksum of the archive.
+	verify_checksum: bool,
+
+	/// Verify the published PGP signature of the archive.
+	verify_signature: bool,
+
+	/// The proxy to download through.
+	///
+	/// Use the proxy environment variables if set to `None`.
+	proxy: Option<String>,
+
+	/// The hosts to connect to without proxy.
+	///
+	/// Use the `NO_PROXY` environment variable if set to `None`.
+	no_proxy: Option<String>,
+
+	/// The callback to report the progress of downloads to.
+	progress: Option<ProgressCallback>,
+}
+
+/// The progress of a file that is being downloaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadProgress {
+	/// The URL of the file.
+	pub url: String,
+
+	/// The number of bytes downloaded so far.
+	pub downloaded: u64,
+
+	/// The size of the file in bytes, if the server reported it.
+	pub total: Option<u64>,
+}
+
+/// A callback for the progress of downloads.
+#[derive(Clone)]
+struct ProgressCallback(Arc<dyn Fn(&DownloadProgress) + Send + Sync>);
+
+impl std::fmt::Debug for ProgressCallback {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str("ProgressCallback")
+	}
+}
+
+/// The edition of the MongoDB server binaries to download.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Edition {
+	/// The MongoDB Community Server.
+	#[default]
+	Community,
+
+	/// MongoDB Enterprise Advanced.
+	///
+	/// Downloading it requires accepting its license,
+	/// see [`MongoDownloader::accept_enterprise_license()`].
+	Enterprise,
+}
+
+/// Cache of downloaded MongoDB binaries.
+///
+/// Each cached version is stored in a subdirectory of the cache root.
+#[derive(Debug, Clone)]
+pub struct BinaryCache {
+	/// The root directory of the cache.
+	root: PathBuf,
+}
+
+/// Downloaded MongoDB server binaries.
+#[derive(Debug, Clone)]
+pub struct MongoBinaries {
+	/// The directory containing the binaries.
+	bin_dir: PathBuf,
+}
+
+impl MongoDownloader {
+	/// Create a downloader for the given MongoDB version, like `"7.0.5"`.
+	pub fn new(version: impl Into<String>) -> Self {
+		Self {
+			version: version.into(),
+			platform: None,
+			edition: Edition::Community,
+			accept_enterprise_license: std::env::var_os("TEMP_MONGO_ACCEPT_ENTERPRISE_LICENSE").is_some_and(|value| value == "1"),
+			cache_dir: None,
+			verify_checksum: true,
+			verify_signature: false,
+			proxy: None,
+			no_proxy: None,
+			progress: None,
+		}
+	}
+
+	/// Set the platform to download binaries for, instead of detecting it automatically.
+	///
+	/// The platform is the part of the official archive name between `mongodb-` and the version,
+	/// for example `linux-x86_64-ubuntu2204`, `linux-aarch64-rhel90` or `macos-arm64`.
+	pub fn platform(mut self, platform: impl Into<String>) -> Self {
+		self.platform = Some(platform.into());
+		self
+	}
+
+	/// Set the edition of MongoDB to download.
+	///
+	/// Defaults to [`Edition::Community`].
+	/// The editions are cached separately.
+	pub fn edition(mut self, edition: Edition) -> Self {
+		self.edition = edition;
+		self
+	}
+
+	/// Accept or reject the license of MongoDB Enterprise.
+	///
+	/// Downloading [`Edition::Enterprise`] fails unless the license is accepted.
+	/// MongoDB Enterprise may be used for evaluation and development under the terms of the
+	/// [MongoDB Customer Agreement](https://www.mongodb.com/legal/customer-agreement).
+	///
+	/// Defaults to `true` if the `TEMP_MONGO_ACCEPT_ENTERPRISE_LICENSE` environment variable is set to `1`, or `false` otherwise.
+	pub fn accept_enterprise_license(mut self, accept: bool) -> Self {
+		self.accept_enterprise_license = accept;
+		self
+	}
+
+	/// Set the root directory of the binary cache.
+	///
+	/// Defaults to a `temp-mongo` directory in the platform cache directory,
+	/// like `~/.cache/temp-mongo` on Linux.
+	pub fn cache_dir(mut self, cache_dir: impl Into<PathBuf>) -> Self {
+		self.cache_dir = Some(cache_dir.into());
+		self
+	}
+
+	/// Enable or disable verification of the published SHA-256 checksum of the archive.
+	///
+	/// Enabled by default.
+	pub fn verify_checksum(mut self, verify_checksum: bool) -> Self {
+		self.verify_checksum = verify_checksum;
+		self
+	}
+
+	/// Enable or disable verification of the published PGP signature of the archive.
+	///
+	/// This uses the `gpg` command, which must be available in `PATH`,
+	/// and the MongoDB server release signing key must be imported in its keyring.
+	/// Disabled by default.
+	pub fn verify_signature(mut self, verify_signature: bool) -> Self {
+		self.verify_signature = verify_signature;
+		self
+	}
+
+	/// Download through the given proxy, like `http://proxy.example.com:3128`.
+	///
+	/// This overrides the `HTTPS_PROXY` environment variable.
+	pub fn proxy(mut self, proxy: impl Into<String>) -> Self {
+		self.proxy = Some(proxy.into());
+		self
+	}
+
+	/// Set a comma separated list of hosts to connect to without proxy, like `localhost,.example.com`.
+	///
+	/// This overrides the `NO_PROXY` environment variable.
+	/// Use `*` to disable the proxy for all hosts.
+	pub fn no_proxy(mut self, hosts: impl Into<String>) -> Self {
+		self.no_proxy = Some(hosts.into());
+		self
+	}
+
+	/// Report the progress of downloads to a callback.
+	///
+	/// The callback is called from the thread that runs [`Self::download()`], about ten times per second while a file is downloaded,
+	/// and once more when it is complete.
+	/// The size of the file is requested from the server before the download starts.
+	/// With the `tracing` or `log` feature, the progress is also emitted as events with target `temp_mongo::download`.
+	///
+	/// ```no_run
+	/// use temp_mongo::MongoDownloader;
+	///
+	/// let binaries = MongoDownloader::new("7.0.5")
+	///     .progress(|progress| eprintln!("{}: {} of {:?} bytes", progress.url, progress.downloaded, progress.total))
+	///     .download();
+	/// ```
+	pub fn progress(mut self, callback: impl Fn(&DownloadProgress) + Send + Sync + 'static) -> Self {
+		self.progress = Some(ProgressCallback(Arc::new(callback)));
+		self
+	}
+
+	/// Get the binary cache used by the downloader.
+	pub fn cache(&self) -> Result<BinaryCache, Error> {
+		match &self.cache_dir {
+			Some(root) => Ok(BinaryCache::in_dir(root)),
+			None => BinaryCache::new(),
+		}
+	}
+
+	/// Get the version to download.
+	pub fn get_version(&self) -> &str {
+		&self.version
+	}
+
+	/// Get the platform to download binaries for.
+	///
+	/// Returns an error if the platform was not set explicitly and it could not be detected.
+	pub fn get_platform(&self) -> Result<String, Error> {
+		match &self.platform {
+			Some(platform) => Ok(platform.clone()),
+			None => Ok(detect_platform()?),
+		}
+	}
+
+	/// Get the edition to download.
+	pub fn get_edition(&self) -> Edition {
+		self.edition
+	}
+
+	/// Get the platform as used in the archive name, which includes the edition.
+	///
+	/// The enterprise edition is inserted after the architecture, like `linux-x86_64-enterprise-ubuntu2204`.
+	fn archive_platform(&self) -> Result<String, Error> {
+		let platform = self.get_platform()?;
+		match self.edition {
+			Edition::Community => Ok(platform),
+			Edition::Enterprise => match platform.splitn(3, '-').collect::<Vec<_>>().as_slice() {
+				[os, arch, distro] => Ok(format!("{os}-{arch}-enterprise-{distro}")),
+				_ => Ok(format!("{platform}-enterprise")),
+			},
+		}
+	}
+
+	/// Get the URL of the archive to download.
+	pub fn get_url(&self) -> Result<String, Error> {
+		let platform = self.archive_platform()?;
+		let host = match self.edition {
+			Edition::Community => "fastdl.mongodb.org",
+			Edition::Enterprise => "downloads.mongodb.com",
+		};
+		let os = platform.split('-').next().unwrap_or_default();
+		let (directory, extension) = match os {
+			"macos" => ("osx", "tgz"),
+			"windows" => ("windows", "zip"),
+			_ => (os, "tgz"),
+		};
+		Ok(format!("https://{host}/{directory}/mongodb-{platform}-{}.{extension}", self.version))
+	}
+
+	/// Download and extract the binaries, unless they are already cached.
+	///
+	/// This blocks the current thread until the download is finished.
+	/// Returns an error when downloading [`Edition::Enterprise`] without accepting its license.
+	#[cfg_attr(feature = "tracing", tracing::instrument(target = "temp_mongo::download", skip_all, fields(version = %self.version, edition = ?self.edition)))]
+	pub fn download(&self) -> Result<MongoBinaries, Error> {
+		if self.edition == Edition::Enterprise && !self.accept_enterprise_license {
+			return Err(ErrorInner::EnterpriseLicenseNotAccepted.into());
+		}
+		let platform = self.archive_platform()?;
+		let url = self.get_url()?;
+		let cache_dir = self.cache()?.root;
+		let version_dir = cache_dir.join(&self.version).join(&platform);
+
+		let binaries = MongoBinaries {
+			bin_dir: version_dir.join("bin"),
+		};
+		if binaries.mongod().is_file() {
+			event!(debug, target: "temp_mongo::download", "using cached binaries in {}", version_dir.display());
+			return Ok(binaries);
+		}
+
+		event!(info, target: "temp_mongo::download", "downloading {url}");
+		std::fs::create_dir_all(&cache_dir).map_err(|e| ErrorInner::MakeCacheDir(cache_dir.clone(), e))?;
+		let staging = tempfile::Builder::new()
+			.prefix(".download-")
+			.tempdir_in(&cache_dir)
+			.map_err(|e| ErrorInner::MakeCacheDir(cache_dir.clone(), e))?;
+
+		let archive = staging.path().join(url.rsplit('/').next().unwrap_or("mongodb.tgz"));
+		self.fetch(&url, &archive)?;
+		if self.verify_checksum {
+			let checksum_path = path_with_suffix(&archive, ".sha256");
+			self.fetch(&format!("{url}.sha256"), &checksum_path)?;
+			event!(debug, target: "temp_mongo::download", "verifying checksum of {}", archive.display());
+			verify_checksum(&archive, &checksum_path)?;
+		}
+		if self.verify_signature {
+			let signature_path = path_with_suffix(&archive, ".sig");
+			self.fetch(&format!("{url}.sig"), &signature_path)?;
+			event!(debug, target: "temp_mongo::download", "verifying signature of {}", archive.display());
+			run(Command::new("gpg").arg("--batch").arg("--verify").arg(&signature_path).arg(&archive))
+				.map_err(|e| ErrorInner::VerifySignature(archive.clone(), e))?;
+		}
+		event!(debug, target: "temp_mongo::download", "extracting {} into {}", archive.display(), version_dir.display());
+		run(Command::new("tar").arg("-xf").arg(&archive).arg("-C").arg(staging.path()))
+			.map_err(|e| ErrorInner::ExtractArchive(archive.clone(), e))?;
+
+		let extracted = staging.path().join(format!("mongodb-{platform}-{}", self.version));
+		let parent = version_dir.parent().unwrap_or(&cache_dir);
+		std::fs::create_dir_all(parent).map_err(|e| ErrorInner::MakeCacheDir(parent.to_owned(), e))?;
+		if let Err(e) = std::fs::rename(&extracted, &version_dir) {
+			// Another process may have finished the same download first.
+			if !binaries.mongod().is_file() {
+				return Err(ErrorInner::ExtractArchive(archive, e).into());
+			}
+		}
+
+		Ok(binaries)
+	}
+
+	/// Download and extract the binaries like [`Self::download()`], retrying downloads that fail because of the network.
+	///
+	/// Only failures for which [`Error::is_infrastructure()`] returns `true` are retried, up to the number of attempts of the policy.
+	/// A version that does not exist fails on the first attempt.
+	///
+	/// This blocks the current thread until the download is finished, including the delays between attempts.
+	pub fn download_with_retries(&self, policy: RetryPolicy) -> Result<MongoBinaries, Error> {
+		let mut attempt = 1;
+		loop {
+			match self.download() {
+				Err(e) if e.is_infrastructure() && attempt < policy.attempts() => {
+					let delay = policy.delay_after(attempt);
+					event!(warn, target: "temp_mongo::download", "attempt {attempt} to download MongoDB {} failed, retrying in {delay:?}: {e}", self.version);
+					std::thread::sleep(delay);
+					attempt += 1;
+				},
+				result => return result,
+			}
+		}
+	}
+
+	/// Download a URL to a file, reporting the progress while the download runs.
+	fn fetch(&self, url: &str, path: &Path) -> Result<(), Error> {
+		let download_error = |e| ErrorInner::Download(url.to_owned(), e);
+		let total = match &self.progress {
+			Some(_) => self.content_length(url),
+			None => None,
+		};
+
+		let mut command = self.curl();
+		command
+			.arg("--output")
+			.arg(path)
+			.arg("--write-out")
+			.arg("%{http_code} %{http_connect}")
+			.arg(url)
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped());
+		let mut child = command.spawn().map_err(download_error)?;
+		let mut reported = None;
+		let status = loop {
+			let downloaded = std::fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+			if reported != Some(downloaded) {
+				self.report_progress(url, downloaded, total);
+				reported = Some(downloaded);
+			}
+			if let Some(status) = child.try_wait().map_err(download_error)? {
+				break status;
+			}
+			std::thread::sleep(PROGRESS_INTERVAL);
+		};
+
+		if !status.success() {
+			let mut stdout = String::new();
+			if let Some(mut pipe) = child.stdout.take() {
+				pipe.read_to_string(&mut stdout).ok();
+			}
+			let mut stderr = String::new();
+			if let Some(mut pipe) = child.stderr.take() {
+				pipe.read_to_string(&mut stderr).ok();
+			}
+			let kind = curl_error_kind(status.code(), &stdout);
+			return Err(download_error(std::io::Error::new(kind, format!("{status}: {}", stderr.trim()))).into());
+		}
+		let downloaded = std::fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+		if reported != Some(downloaded) {
+			self.report_progress(url, downloaded, total);
+		}
+		Ok(())
+	}
+
+	/// Request the size of a file from the server, without downloading it.
+	///
+	/// Returns `None` if the request fails or the server does not report the size.
+	fn content_length(&self, url: &str) -> Option<u64> {
+		let output = self.curl().arg("--head").arg(url).output().ok()?;
+		// With redirects, there is a header block per response: use the last one.
+		String::from_utf8_lossy(&output.stdout)
+			.lines()
+			.filter_map(|line| {
+				let (name, value) = line.split_once(':')?;
+				if !name.trim().eq_ignore_ascii_case("content-length") {
+					return None;
+				}
+				value.trim().parse().ok()
+			})
+			.next_back()
+	}
+
+	/// Create a `curl` command with the common arguments for all requests.
+	fn curl(&self) -> Command {
+		let mut command = Command::new("curl");
+		command.arg("--fail").arg("--silent").arg("--show-error").arg("--location");
+		if let Some(proxy) = &self.proxy {
+			command.arg("--proxy").arg(proxy);
+		}
+		if let Some(hosts) = &self.no_proxy {
+			command.arg("--noproxy").arg(hosts);
+		}
+		command
+	}
+
+	/// Report the progress of a download to the callback and as an event.
+	fn report_progress(&self, url: &str, downloaded: u64, total: Option<u64>) {
+		event!(debug, target: "temp_mongo::download", "downloaded {downloaded} of {total:?} bytes of {url}");
+		if let Some(ProgressCallback(callback)) = &self.progress {
+			callback(&DownloadProgress {
+				url: url.to_owned(),
+				downloaded,
+				total,
+			});
+		}
+	}
+}
+
+impl MongoBinaries {
+	/// Get the directory containing the binaries.
+	pub fn bin_dir(&self) -> &Path {
+		&self.bin_dir
+	}
+
+	/// Get the path of the `mongod` binary.
+	pub fn mongod(&self) -> PathBuf {
+		self.bin_dir.join(executable("mongod"))
+	}
+
+	/// Get the path of the `mongos` binary.
+	pub fn mongos(&self) -> PathBuf {
+		self.bin_dir.join(executable("mongos"))
+	}
+}
+
+impl BinaryCache {
+	/// Open the binary cache in the default location.
+	///
+	/// This is a `temp-mongo` directory in the platform cache directory, like `~/.cache/temp-mongo` on Linux.
+	pub fn new() -> Result<Self, Error> {
+		let root = default_cache_dir().ok_or(ErrorInner::NoCacheDir)?;
+		Ok(Self { root })
+	}
+
+	/// Open a binary cache with the given root directory.
+	pub fn in_dir(root: impl Into<PathBuf>) -> Self {
+		Self { root: root.into() }
+	}
+
+	/// Get the root directory of the cache.
+	pub fn root(&self) -> &Path {
+		&self.root
+	}
+
+	/// List the cached versions, sorted from oldest to newest.
+	///
+	/// Only directories with a name that starts with a digit are considered versions.
+	/// Returns an empty list if the cache directory does not exist yet.
+	pub fn list_cached_versions(&self) -> Result<Vec<String>, Error> {
+		let entries = match std::fs::read_dir(&self.root) {
+			Ok(entries) => entries,
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+			Err(e) => return Err(ErrorInner::ReadCacheDir(self.root.clone(), e).into()),
+		};
+
+		let mut versions = Vec::new();
+		for entry in entries {
+			let entry = entry.map_err(|e| ErrorInner::ReadCacheDir(self.root.clone(), e))?;
+			let Ok(name) = entry.file_name().into_string() else {
+				continue;
+			};
+			// Skip unfinished downloads, and other directories that are not named like a version.
+			if !name.starts_with(|c: char| c.is_ascii_digit()) || !entry.path().is_dir() {
+				continue;
+			}
+			versions.push(name);
+		}
+		versions.sort_by_cached_key(|version| version_key(version));
+		Ok(versions)
+	}
+
+	/// Remove all but the `keep` newest cached versions.
+	///
+	/// Returns the removed versions.
+	pub fn prune_cache(&self, keep: usize) -> Result<Vec<String>, Error> {
+		let mut versions = self.list_cached_versions()?;
+		let removed: Vec<_> = versions.drain(..versions.len().saturating_sub(keep)).collect();
+		for version in &removed {
+			let path = self.root.join(version);
+			std::fs::remove_dir_all(&path).map_err(|e| ErrorInner::RemoveCachedVersion(path, e))?;
+		}
+		Ok(removed)
+	}
+}
+
+/// Get a key to sort versions by, comparing numeric components as numbers.
+fn version_key(version: &str) -> Vec<(u64, String)> {
+	version
+		.split(['.', '-'])
+		.map(|part| match part.parse() {
+			Ok(number) => (number, String::new()),
+			Err(_) => (0, part.to_owned()),
+		})
+		.collect()
+}
+
+/// Get a path with a suffix appended to the file name.
+fn path_with_suffix(path: &Path, suffix: &str) -> PathBuf {
+	let mut path = path.as_os_str().to_owned();
+	path.push(suffix);
+	path.into()
+}
+
+/// Verify the SHA-256 checksum of a file against a checksum file in the format of `sha256sum`.
+fn verify_checksum(path: &Path, checksum_path: &Path) -> Result<(), Error> {
+	let checksum = std::fs::read_to_string(checksum_path).map_err(|e| ErrorInner::ComputeChecksum(checksum_path.to_owned(), e))?;
+	let expected = checksum.split_whitespace().next().unwrap_or_default().to_lowercase();
+
+	let mut file = std::fs::File::open(path).map_err(|e| ErrorInner::ComputeChecksum(path.to_owned(), e))?;
+	let mut hasher = Sha256::new();
+	std::io::copy(&mut file, &mut hasher).map_err(|e| ErrorInner::ComputeChecksum(path.to_owned(), e))?;
+	let actual = format!("{:x}", hasher.finalize());
+
+	if actual != expected {
+		return Err(ErrorInner::ChecksumMismatch(path.to_owned(), expected, actual).into());
+	}
+	Ok(())
+}
+
+/// Determine the kind of error of a failed `curl` command, from its exit code and the HTTP status codes it wrote.
+///
+/// Network failures and server errors map to the kinds that [`Error::is_infrastructure()`] considers transient.
+/// HTTP client errors, like a 404 for a version that does not exist, do not.
+fn curl_error_kind(exit_code: Option<i32>, write_out: &str) -> std::io::ErrorKind {
+	use std::io::ErrorKind;
+
+	// The status of the response, and of the response of the proxy to a `CONNECT` request.
+	let http_status = write_out
+		.split_whitespace()
+		.filter_map(|code| code.parse::<u16>().ok())
+		.find(|&code| code >= 400);
+	match (http_status, exit_code) {
+		(Some(404 | 410), _) => ErrorKind::NotFound,
+		(Some(401 | 403 | 407), _) => ErrorKind::PermissionDenied,
+		(Some(400..=499), _) => ErrorKind::InvalidInput,
+		// The server failed to handle the request, which is usually temporary.
+		(Some(_), _) => ErrorKind::ConnectionAborted,
+		// Could not resolve the proxy or host, or could not connect.
+		(None, Some(5..=7)) => ErrorKind::ConnectionRefused,
+		(None, Some(28)) => ErrorKind::TimedOut,
+		(None, Some(35)) => ErrorKind::ConnectionAborted,
+		// Partial file, empty reply, or failure sending or receiving data.
+		(None, Some(18 | 52 | 55 | 56)) => ErrorKind::ConnectionReset,
+		(None, None) => ErrorKind::Interrupted,
+		(None, Some(_)) => ErrorKind::Other,
+	}
+}
+
+/// Run a command to completion, turning a non-zero exit status into an error.
+pub(crate) fn run(command: &mut Command) -> std::io::Result<()> {
+	let output = command.output()?;
+	if output.status.success() {
+		Ok(())
+	} else {
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		Err(std::io::Error::other(format!("{}: {}", output.status, stderr.trim())))
+	}
+}
+
+/// Get the file name of an executable for the current platform.
+fn executable(name: &str) -> String {
+	format!("{name}{}", std::env::consts::EXE_SUFFIX)
+}
+
+/// Get the default directory to cache downloaded binaries in.
+pub(crate) fn default_cache_dir() -> Option<PathBuf> {
+	Some(platform_cache_dir()?.join("temp-mongo"))
+}
+
+/// Get the default directory for named persistent data directories.
+///
+/// This is a sibling of the binary cache, so pruning the cache never removes persistent data.
+pub(crate) fn default_data_dir() -> Option<PathBuf> {
+	Some(platform_cache_dir()?.join("temp-mongo-data"))
+}
+
+/// Get the cache directory of the platform, like `~/.cache` on Linux.
+fn platform_cache_dir() -> Option<PathBuf> {
+	let env = |name| std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+	let base = if cfg!(windows) {
+		env("LOCALAPPDATA")?
+	} else if cfg!(target_os = "macos") {
+		env("HOME")?.join("Library").join("Caches")
+	} else {
+		env("XDG_CACHE_HOME").or_else(|| Some(env("HOME")?.join(".cache")))?
+	};
+	Some(base)
+}
+
+/// Detect the download platform of the current system.
+fn detect_platform() -> Result<String, ErrorInner> {
+	let arch = std::env::consts::ARCH;
+	match std::env::consts::OS {
+		"macos" => match arch {
+			"aarch64" => Ok("macos-arm64".into()),
+			_ => Ok(format!("macos-{arch}")),
+		},
+		"windows" => Ok(format!("windows-{arch}")),
+		"linux" => {
+			let os_release = std::fs::read_to_string("/etc/os-release")
+				.map_err(|e| ErrorInner::UnsupportedPlatform(format!("failed to read /etc/os-release: {e}")))?;
+			let field = |key: &str| {
+				os_release
+					.lines()
+					.find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+					.map(|value| value.trim_matches('"').to_owned())
+					.unwrap_or_default()
+			};
+			let id = field("ID");
+			let version = field("VERSION_ID");
+			let major = version.split('.').next().unwrap_or_default();
+			let distro = match id.as_str() {
+				"ubuntu" => format!("ubuntu{}", version.replace('.', "")),
+				"debian" => format!("debian{major}"),
+				"rhel" | "centos" | "rocky" | "almalinux" | "ol" => format!("rhel{major}0"),
+				"amzn" => format!("amazon{major}"),
+				_ => return Err(ErrorInner::UnsupportedPlatform(format!("unsupported Linux distribution: {id} {version}"))),
+			};
+			Ok(format!("linux-{arch}-{distro}"))
+		},
+		os => Err(ErrorInner::UnsupportedPlatform(format!("unsupported operating system: {os}"))),
+	}
+}
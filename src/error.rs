@@ -1,4 +1,6 @@
+use crate::util::redact_uri;
 use std::path::PathBuf;
+use std::time::Duration;
 
 /// An error that can occur when creating or cleaning a MongDB instance.
 pub struct Error {
@@ -20,26 +22,213 @@ pub enum ErrorInner {
 	/// Failed to kill the server.
 	KillServer(std::io::Error),
 
+	/// Failed to check if the server has exited.
+	WaitServer(std::io::Error),
+
 	/// Failed to clean up the temporary directory.
 	CleanDir(PathBuf, std::io::Error),
 
+	/// Failed to copy the log of the server out of the temporary directory.
+	SaveLog(PathBuf, std::io::Error),
+
+	/// Failed to run the `serverStatus` command.
+	ServerStatus(mongodb::error::Error),
+
+	/// Failed to write a `serverStatus` snapshot to a file.
+	WriteServerStatus(PathBuf, std::io::Error),
+
+	/// Failed to read the operations recorded by the database profiler.
+	ReadProfiler(mongodb::error::Error),
+
+	/// Failed to run the `currentOp` command.
+	CurrentOp(mongodb::error::Error),
+
+	/// Failed to ping the server to measure the latency.
+	Ping(mongodb::error::Error),
+
+	/// Failed to run the `buildInfo` command to determine the server version.
+	BuildInfo(mongodb::error::Error),
+
+	/// Failed to list the databases of the server.
+	ListDatabases(mongodb::error::Error),
+
+	/// A database that should be unique to a test already exists.
+	DatabaseExists(String),
+
+	/// Failed to write a golden file.
+	WriteGolden(PathBuf, std::io::Error),
+
+	/// Failed to drop a database.
+	DropDatabase(String, mongodb::error::Error),
+
+	/// Failed to start the runtime of an instance pool or a benchmark instance.
+	StartRuntime(std::io::Error),
+
+	/// Failed to write the environment variables for `cargo-nextest` to a file.
+	WriteNextestEnv(PathBuf, std::io::Error),
+
+	/// Failed to write a failure report to a file.
+	WriteFailureReport(PathBuf, std::io::Error),
+
+	/// Failed to read the data directory to determine its size.
+	ReadDataDir(PathBuf, std::io::Error),
+
 	/// Failed to connect to the server.
 	Connect(String, mongodb::error::Error),
 
 	Port,
+
+	/// Failed to read a seed file.
+	ReadSeedFile(PathBuf, std::io::Error),
+
+	/// Failed to parse a seed file.
+	ParseSeedFile(PathBuf, serde_json::Error),
+
+	/// Failed to parse a BSON seed file.
+	ParseBsonSeedFile(PathBuf, mongodb::bson::de::Error),
+
+	/// Failed to read the documents of a collection.
+	ReadCollection(String, mongodb::error::Error),
+
+	/// Failed to write an exported collection.
+	WriteExport(PathBuf, std::io::Error),
+
+	/// The requested seed profile was not registered.
+	UnknownProfile(String),
+
+	/// Failed to seed the data of a seed profile.
+	SeedProfile(String, mongodb::error::Error),
+
+	/// A replica set was requested without any members.
+	NoMembers,
+
+	/// Member options were set for a data bearing member that the replica set does not have.
+	MemberOptionsIndex(usize, usize),
+
+	/// Failed to initiate the replica set.
+	InitiateReplicaSet(mongodb::error::Error),
+
+	/// The replica set did not become ready within the configured timeout.
+	ReplicaSetTimeout(Duration),
+
+	/// The replica set has no primary.
+	NoPrimary,
+
+	/// Failed to step down the primary of a replica set.
+	StepDown(mongodb::error::Error),
+
+	/// The member to remove from a replica set is the current primary.
+	RemovePrimary(usize),
+
+	/// Failed to reconfigure the replica set.
+	Reconfigure(mongodb::error::Error),
+
+	/// Failed to write the keyfile for internal authentication.
+	WriteKeyfile(PathBuf, std::io::Error),
+
+	/// Failed to create a user.
+	CreateUser(String, mongodb::error::Error),
+
+	/// Failed to update a user.
+	UpdateUser(String, mongodb::error::Error),
+
+	/// Failed to read the audit log.
+	ReadAuditLog(PathBuf, std::io::Error),
+
+	/// Failed to parse an event in the audit log.
+	ParseAuditLog(PathBuf, serde_json::Error),
+
+	/// A startup error, with the last output of the server.
+	ServerOutput(Box<ErrorInner>, String),
+
+	/// A startup error of a server that has exited, with its exit status and the last output of the server.
+	ServerExited(Box<ErrorInner>, std::process::ExitStatus, String),
+
+	/// Failed to write a `mongod` configuration file.
+	WriteConfig(PathBuf, std::io::Error),
+
+	/// Failed to read a topology description.
+	ReadTopology(PathBuf, std::io::Error),
+
+	/// Failed to parse a topology description.
+	ParseTopology(PathBuf, serde_json::Error),
+
+	/// Failed to determine the platform to download MongoDB binaries for.
+	UnsupportedPlatform(String),
+
+	/// Failed to determine the cache directory for downloaded binaries.
+	NoCacheDir,
+
+	/// Failed to create the cache directory for downloaded binaries.
+	MakeCacheDir(PathBuf, std::io::Error),
+
+	/// Failed to read the cache directory for downloaded binaries.
+	ReadCacheDir(PathBuf, std::io::Error),
+
+	/// Failed to remove a cached version of the MongoDB binaries.
+	RemoveCachedVersion(PathBuf, std::io::Error),
+
+	/// The license of MongoDB Enterprise was not accepted before downloading it.
+	EnterpriseLicenseNotAccepted,
+
+	/// Failed to download MongoDB binaries.
+	Download(String, std::io::Error),
+
+	/// Failed to extract downloaded MongoDB binaries.
+	ExtractArchive(PathBuf, std::io::Error),
+
+	/// The SHA-256 checksum of a downloaded archive does not match the published checksum.
+	///
+	/// Holds the path of the archive, the published checksum and the actual checksum.
+	ChecksumMismatch(PathBuf, String, String),
+
+	/// Failed to compute the checksum of a downloaded archive.
+	ComputeChecksum(PathBuf, std::io::Error),
+
+	/// Failed to verify the PGP signature of a downloaded archive.
+	VerifySignature(PathBuf, std::io::Error),
+
+	/// Failed to add a shard to a sharded cluster.
+	AddShard(String, mongodb::error::Error),
+
+	/// Failed to enable sharding on a database.
+	EnableSharding(String, mongodb::error::Error),
+
+	/// Failed to generate TLS certificates.
+	GenerateCertificate(PathBuf, std::io::Error),
+
+	/// Failed to set the feature compatibility version.
+	SetFeatureCompatibilityVersion(String, mongodb::error::Error),
+
+	/// Failed to create the key vault collection for client-side encryption.
+	#[cfg(feature = "csfle")]
+	CreateKeyVault(String, mongodb::error::Error),
+
+	/// Failed to create a data key for client-side encryption.
+	#[cfg(feature = "csfle")]
+	CreateDataKey(mongodb::error::Error),
+
+	/// Failed to start the mock KMS server.
+	#[cfg(feature = "mock-kms")]
+	StartMockKms(std::io::Error),
 }
 
 impl std::error::Error for Error {}
 
+// Connection strings in errors may contain passwords, so they are redacted in all output.
 impl std::fmt::Debug for Error {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		std::fmt::Debug::fmt(&self.inner, f)
+		let output = match f.alternate() {
+			true => format!("{:#?}", self.inner),
+			false => format!("{:?}", self.inner),
+		};
+		f.write_str(&redact_uri(&output))
 	}
 }
 
 impl std::fmt::Display for Error {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		std::fmt::Display::fmt(&self.inner, f)
+		f.write_str(&redact_uri(&self.inner.to_string()))
 	}
 }
 
@@ -52,17 +241,164 @@ impl std::fmt::Display for ErrorInner {
 			}
 			Self::SpawnServer(name, e) => write!(f, "Failed to run server command: {name}: {e}"),
 			Self::KillServer(e) => write!(f, "Failed to terminate spanwed server: {e}"),
+			Self::WaitServer(e) => write!(f, "Failed to check if the server has exited: {e}"),
 			Self::CleanDir(path, e) => write!(
 				f,
 				"Failed to clean up temporary state directory {}: {e}",
 				path.display()
 			),
+			Self::SaveLog(path, e) => write!(f, "Failed to save server log to {}: {e}", path.display()),
+			Self::ServerStatus(e) => write!(f, "Failed to get server status: {e}"),
+			Self::WriteServerStatus(path, e) => write!(f, "Failed to write server status to {}: {e}", path.display()),
+			Self::ReadProfiler(e) => write!(f, "Failed to read profiled operations: {e}"),
+			Self::CurrentOp(e) => write!(f, "Failed to get current operations: {e}"),
+			Self::Ping(e) => write!(f, "Failed to ping server: {e}"),
+			Self::BuildInfo(e) => write!(f, "Failed to get server version: {e}"),
+			Self::ListDatabases(e) => write!(f, "Failed to list databases: {e}"),
+			Self::DatabaseExists(name) => write!(f, "Database {name:?} already exists, is the run ID shared with another test run?"),
+			Self::WriteGolden(path, e) => write!(f, "Failed to write golden file {}: {e}", path.display()),
+			Self::DropDatabase(name, e) => write!(f, "Failed to drop database {name:?}: {e}"),
+			Self::StartRuntime(e) => write!(f, "Failed to start runtime: {e}"),
+			Self::WriteNextestEnv(path, e) => write!(f, "Failed to write nextest environment to {}: {e}", path.display()),
+			Self::WriteFailureReport(path, e) => write!(f, "Failed to write failure report to {}: {e}", path.display()),
+			Self::ReadDataDir(path, e) => write!(f, "Failed to read data directory {}: {e}", path.display()),
 			Self::Connect(address, e) => write!(f, "Failed to connect to server at {address}: {e}"),
 			Self::Port => write!(f, "Failed to select a free port by the os "),
+			Self::ReadSeedFile(path, e) => write!(f, "Failed to read seed file {}: {e}", path.display()),
+			Self::ParseSeedFile(path, e) => write!(f, "Failed to parse seed file {}: {e}", path.display()),
+			Self::ParseBsonSeedFile(path, e) => write!(f, "Failed to parse BSON seed file {}: {e}", path.display()),
+			Self::ReadCollection(namespace, e) => write!(f, "Failed to read documents from {namespace}: {e}"),
+			Self::WriteExport(path, e) => write!(f, "Failed to write exported collection to {}: {e}", path.display()),
+			Self::UnknownProfile(name) => write!(f, "Unknown seed profile: {name}"),
+			Self::SeedProfile(name, e) => write!(f, "Failed to seed profile {name}: {e}"),
+			Self::NoMembers => write!(f, "A replica set needs at least one member"),
+			Self::MemberOptionsIndex(index, members) => write!(f, "Member options were set for member {index}, but the replica set only has {members} data bearing members"),
+			Self::InitiateReplicaSet(e) => write!(f, "Failed to initiate replica set: {e}"),
+			Self::ReplicaSetTimeout(timeout) => write!(f, "Replica set did not elect a primary within {timeout:?}"),
+			Self::NoPrimary => write!(f, "Replica set has no primary"),
+			Self::StepDown(e) => write!(f, "Failed to step down primary: {e}"),
+			Self::RemovePrimary(index) => write!(f, "Can not remove member {index} of the replica set, it is the primary: step it down first"),
+			Self::Reconfigure(e) => write!(f, "Failed to reconfigure replica set: {e}"),
+			Self::WriteKeyfile(path, e) => write!(f, "Failed to write keyfile {}: {e}", path.display()),
+			Self::CreateUser(name, e) => write!(f, "Failed to create user {name}: {e}"),
+			Self::UpdateUser(name, e) => write!(f, "Failed to update user {name}: {e}"),
+			Self::ReadAuditLog(path, e) => write!(f, "Failed to read audit log {}: {e}", path.display()),
+			Self::ParseAuditLog(path, e) => write!(f, "Failed to parse audit log {}: {e}", path.display()),
+			Self::ServerOutput(inner, output) => write!(f, "{inner}{output}"),
+			Self::ServerExited(inner, status, output) => write!(f, "{inner}\nThe server has exited: {status}{output}"),
+			Self::WriteConfig(path, e) => write!(f, "Failed to write mongod configuration file {}: {e}", path.display()),
+			Self::ReadTopology(path, e) => write!(f, "Failed to read topology {}: {e}", path.display()),
+			Self::ParseTopology(path, e) => write!(f, "Failed to parse topology {}: {e}", path.display()),
+			Self::UnsupportedPlatform(reason) => write!(f, "Failed to determine MongoDB download platform: {reason}"),
+			Self::NoCacheDir => write!(f, "Failed to determine cache directory for downloaded binaries"),
+			Self::MakeCacheDir(path, e) => write!(f, "Failed to create cache directory {}: {e}", path.display()),
+			Self::ReadCacheDir(path, e) => write!(f, "Failed to read cache directory {}: {e}", path.display()),
+			Self::RemoveCachedVersion(path, e) => write!(f, "Failed to remove cached binaries {}: {e}", path.display()),
+			Self::EnterpriseLicenseNotAccepted => write!(f, "Refusing to download MongoDB Enterprise without accepting its license"),
+			Self::Download(url, e) => write!(f, "Failed to download {url}: {e}"),
+			Self::ExtractArchive(path, e) => write!(f, "Failed to extract {}: {e}", path.display()),
+			Self::ChecksumMismatch(path, expected, actual) => write!(
+				f,
+				"Checksum mismatch for {}: expected SHA-256 {expected}, got {actual}",
+				path.display()
+			),
+			Self::ComputeChecksum(path, e) => write!(f, "Failed to compute checksum of {}: {e}", path.display()),
+			Self::VerifySignature(path, e) => write!(f, "Failed to verify signature of {}: {e}", path.display()),
+			Self::AddShard(address, e) => write!(f, "Failed to add shard {address}: {e}"),
+			Self::EnableSharding(database, e) => write!(f, "Failed to enable sharding on database {database}: {e}"),
+			Self::GenerateCertificate(path, e) => write!(f, "Failed to generate TLS certificates in {}: {e}", path.display()),
+			Self::SetFeatureCompatibilityVersion(version, e) => write!(f, "Failed to set feature compatibility version to {version}: {e}"),
+			#[cfg(feature = "csfle")]
+			Self::CreateKeyVault(namespace, e) => write!(f, "Failed to create key vault collection {namespace}: {e}"),
+			#[cfg(feature = "csfle")]
+			Self::CreateDataKey(e) => write!(f, "Failed to create data key: {e}"),
+			#[cfg(feature = "mock-kms")]
+			Self::StartMockKms(e) => write!(f, "Failed to start mock KMS server: {e}"),
+		}
+	}
+}
+
+impl Error {
+	/// Check if the error is likely caused by a transient failure of the infrastructure, rather than a mistake in the configuration or the test.
+	///
+	/// This includes network failures and timeouts while downloading binaries or connecting to the server,
+	/// failing to create the temporary directory or to spawn the server, and a replica set that does not elect a primary in time.
+	/// Downloads rejected by the server, like a version that does not exist, authentication failures,
+	/// a server command that can not be run, and a server that exited during startup,
+	/// like on an invalid argument or configuration, are not considered infrastructure failures.
+	///
+	/// These are the errors retried by [`TempMongoBuilder::spawn_with_retries()`](crate::TempMongoBuilder::spawn_with_retries).
+	pub fn is_infrastructure(&self) -> bool {
+		self.inner.is_infrastructure()
+	}
+
+	/// Attach the last output of a server to the error.
+	///
+	/// If the server has exited, the exit status is attached too, and the error is no longer considered an infrastructure failure.
+	/// Otherwise, the output is left out if it is empty.
+	pub(crate) fn with_server_output(self, output: String, exit_status: Option<std::process::ExitStatus>) -> Self {
+		match exit_status {
+			Some(status) => ErrorInner::ServerExited(Box::new(self.inner), status, output).into(),
+			None if output.is_empty() => self,
+			None => ErrorInner::ServerOutput(Box::new(self.inner), output).into(),
+		}
+	}
+}
+
+impl ErrorInner {
+	/// Check if the error is likely caused by a transient failure of the infrastructure.
+	fn is_infrastructure(&self) -> bool {
+		use std::io::ErrorKind;
+
+		match self {
+			Self::SpawnServer(_, e) => matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::Interrupted | ErrorKind::ResourceBusy),
+			Self::ServerOutput(inner, _) => inner.is_infrastructure(),
+			Self::ServerExited(..) => false,
+			Self::Download(_, e) => is_transient_io(e),
+			Self::Connect(_, e) => is_transient_mongodb(e),
+			Self::MakeTempDir(_)
+			| Self::MakeDbDir(..)
+			| Self::Port
+			| Self::InitiateReplicaSet(_)
+			| Self::ReplicaSetTimeout(_)
+			| Self::NoPrimary => true,
+			_ => false,
 		}
 	}
 }
 
+/// Check if an I/O error is caused by the network, a timeout or an interruption, which are likely to be transient.
+fn is_transient_io(e: &std::io::Error) -> bool {
+	use std::io::ErrorKind;
+
+	matches!(
+		e.kind(),
+		ErrorKind::ConnectionRefused
+			| ErrorKind::ConnectionReset
+			| ErrorKind::ConnectionAborted
+			| ErrorKind::NotConnected
+			| ErrorKind::AddrInUse
+			| ErrorKind::AddrNotAvailable
+			| ErrorKind::BrokenPipe
+			| ErrorKind::TimedOut
+			| ErrorKind::Interrupted
+			| ErrorKind::UnexpectedEof
+	)
+}
+
+/// Check if an error of the MongoDB driver is caused by the network or a server that is not ready yet.
+///
+/// Authentication failures and invalid options are not transient.
+fn is_transient_mongodb(e: &mongodb::error::Error) -> bool {
+	use mongodb::error::ErrorKind;
+
+	match e.kind.as_ref() {
+		ErrorKind::Io(e) => is_transient_io(e),
+		ErrorKind::ServerSelection { .. } | ErrorKind::ConnectionPoolCleared { .. } => true,
+		_ => false,
+	}
+}
+
 impl From<ErrorInner> for Error {
 	fn from(inner: ErrorInner) -> Self {
 		Self { inner }
@@ -0,0 +1,117 @@
+use crate::{Error, StandaloneTopology, TempMongoTopology, Topology};
+use futures_util::future::try_join_all;
+use std::collections::BTreeMap;
+
+/// A named group of completely independent temporary MongoDB deployments.
+///
+/// All deployments are spawned concurrently, each in its own temporary directory.
+/// This is useful for testing features that span multiple clusters, like cross-cluster synchronization or migration tooling.
+#[derive(Debug)]
+pub struct MongoFleet {
+	instances: BTreeMap<String, TempMongoTopology>,
+}
+
+impl MongoFleet {
+	/// Spawn a standalone server for each of the given names.
+	pub async fn new<I>(names: I) -> Result<Self, Error>
+	where
+		I: IntoIterator,
+		I::Item: Into<String>,
+	{
+		let mut builder = Self::builder();
+		for name in names {
+			builder = builder.standalone(name);
+		}
+		builder.spawn().await
+	}
+
+	/// Create a builder to customize the deployments of the fleet.
+	pub fn builder() -> MongoFleetBuilder {
+		MongoFleetBuilder::new()
+	}
+
+	/// Get a deployment by name.
+	pub fn get(&self, name: &str) -> Option<&TempMongoTopology> {
+		self.instances.get(name)
+	}
+
+	/// Get a client for a deployment by name.
+	pub fn client(&self, name: &str) -> Option<&mongodb::Client> {
+		self.get(name).map(TempMongoTopology::client)
+	}
+
+	/// Get the number of deployments in the fleet.
+	pub fn len(&self) -> usize {
+		self.instances.len()
+	}
+
+	/// Check if the fleet has no deployments.
+	pub fn is_empty(&self) -> bool {
+		self.instances.is_empty()
+	}
+
+	/// Iterate over the names of the deployments, in sorted order.
+	pub fn names(&self) -> impl Iterator<Item = &str> {
+		self.instances.keys().map(String::as_str)
+	}
+
+	/// Iterate over all deployments and their names, in sorted order.
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &TempMongoTopology)> {
+		self.instances.iter().map(|(name, instance)| (name.as_str(), instance))
+	}
+
+	/// Take ownership of the individual deployments.
+	pub fn into_instances(self) -> BTreeMap<String, TempMongoTopology> {
+		self.instances
+	}
+
+	/// Kill all deployments and remove their temporary state directories on the filesystem.
+	///
+	/// All deployments are cleaned up, even if cleaning one of them fails.
+	/// The first error encountered is returned.
+	pub async fn kill_and_clean(self) -> Result<(), Error> {
+		let mut result = Ok(());
+		for instance in self.instances.into_values() {
+			let cleaned = instance.kill_and_clean().await;
+			if result.is_ok() {
+				result = cleaned;
+			}
+		}
+		result
+	}
+}
+
+/// Builder for customizing a [`MongoFleet`].
+#[derive(Debug, Clone, Default)]
+pub struct MongoFleetBuilder {
+	instances: BTreeMap<String, Topology>,
+}
+
+impl MongoFleetBuilder {
+	/// Create a new builder without any deployments.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Add a deployment with the given name and topology.
+	///
+	/// If a deployment with the same name was already added, it is replaced.
+	pub fn instance(mut self, name: impl Into<String>, topology: Topology) -> Self {
+		self.instances.insert(name.into(), topology);
+		self
+	}
+
+	/// Add a standalone server with the given name.
+	pub fn standalone(self, name: impl Into<String>) -> Self {
+		self.instance(name, Topology::Standalone(StandaloneTopology::default()))
+	}
+
+	/// Spawn all deployments concurrently.
+	///
+	/// If any deployment fails to start, the deployments that did start are killed and cleaned up when they are dropped.
+	pub async fn spawn(&self) -> Result<MongoFleet, Error> {
+		let instances = try_join_all(self.instances.values().map(Topology::spawn)).await?;
+		let instances = self.instances.keys().cloned().zip(instances).collect();
+		Ok(MongoFleet { instances })
+	}
+}
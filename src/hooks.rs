@@ -0,0 +1,38 @@
+use crate::TempMongo;
+use futures_util::future::BoxFuture;
+use std::sync::Arc;
+
+/// A hook that is called at a point in the lifecycle of a [`TempMongo`].
+///
+/// The hook returns a boxed future, so it can borrow the [`TempMongo`] object, like `|mongo| Box::pin(async move { ... })`.
+pub type LifecycleHook = Arc<dyn for<'a> Fn(&'a TempMongo) -> BoxFuture<'a, ()> + Send + Sync>;
+
+/// The registered lifecycle hooks, which are called in the order they were registered.
+#[derive(Clone, Default)]
+pub(crate) struct LifecycleHooks {
+	/// Called when the server accepts connections, before `mongocryptd` is spawned and the seed profile is loaded.
+	pub on_created: Vec<LifecycleHook>,
+
+	/// Called when the server is completely set up.
+	pub on_ready: Vec<LifecycleHook>,
+
+	/// Called before the server is shut down or killed.
+	pub on_before_cleanup: Vec<LifecycleHook>,
+}
+
+impl std::fmt::Debug for LifecycleHooks {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("LifecycleHooks")
+			.field("on_created", &self.on_created.len())
+			.field("on_ready", &self.on_ready.len())
+			.field("on_before_cleanup", &self.on_before_cleanup.len())
+			.finish()
+	}
+}
+
+/// Call all hooks in order.
+pub(crate) async fn run_hooks(hooks: &[LifecycleHook], mongo: &TempMongo) {
+	for hook in hooks {
+		hook(mongo).await;
+	}
+}
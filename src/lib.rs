@@ -3,8 +3,30 @@
 //! Use the [`TempMongo`] struct to get a [`mongodb::Client`] that is connected to a temporary MongoDB instance.
 //! All state of the spawned MongoDB instance is stored in a temporary directory, which will be cleaned up automatically (unless disabled).
 //!
-//! On Unix platforms, the client is connected over a Unix socket.
-//! Windows support is planned by picking a free TCP port on the loopback adapter.
+//! On Unix platforms, the server only listens on a Unix socket in the temporary directory and the client is connected over that socket.
+//! No TCP port is allocated, so highly parallel test runs can not race for ports.
+//! On Windows, the server listens on a free TCP port on the loopback adapter instead.
+//! The same goes for servers with TLS enabled, since the server certificate is verified against the host name.
+//!
+//! Use the [`TempMongoReplicaSet`] struct to spawn a replica set of local `mongod` processes instead, for example to test transactions.
+//!
+//! By default, the `mongod` binary is looked up in `PATH`.
+//! Set the `TEMP_MONGO_MONGOD` environment variable or use [`TempMongoBuilder::mongod_command()`] to run a binary from another location.
+//!
+//! # Features
+//!
+//! * `tokio-runtime` (default): use the `tokio` runtime for the driver.
+//! * `async-std-runtime`: use the `async-std` runtime for the driver.
+//! * `csfle`: create clients with automatic encryption, which requires the `libmongocrypt` library.
+//! * `mock-kms`: enable `MockKms`, an in-process mock KMS server for encryption tests.
+//! * `auth-by-default`: enable authentication with random credentials for all servers, replica sets and sharded clusters by default,
+//!   so tests can not accidentally rely on `--noauth` behavior. Use `without_auth()` on the builders to opt out.
+//! * `tracing`: emit [`tracing`](https://crates.io/crates/tracing) spans and events while downloading binaries,
+//!   spawning servers, waiting for them to accept connections, seeding data and cleaning up.
+//!   Events use targets like `temp_mongo::download`, `temp_mongo::spawn`, `temp_mongo::seed` and `temp_mongo::cleanup`.
+//!   The spans carry attributes named after the OpenTelemetry semantic conventions, like `process.pid` and `server.address`.
+//! * `log`: emit the same events through the [`log`](https://crates.io/crates/log) facade, with the same targets.
+//! * `cli`: build the `temp-mongo` binary, which starts and stops servers from the command line, with `up`, `list`, `down` and `clean`.
 //!
 //! # Example
 //!
@@ -29,11 +51,70 @@
 
 #![warn(missing_docs)]
 
+mod app_state;
+pub mod assertions;
+mod benchmark;
+mod cryptd;
+mod download;
 mod error;
+mod fleet;
+mod hooks;
+#[cfg(feature = "csfle")]
+mod local_kms;
+#[cfg(feature = "mock-kms")]
+mod mock_kms;
+mod nextest;
+mod pool;
+mod property;
+mod replica_set;
+mod scenario;
+mod sharded;
+mod shared;
 mod temp_mongo;
+mod topology;
 mod util;
 
+pub use app_state::FromTempMongo;
+pub use benchmark::BenchmarkMongo;
+pub use cryptd::{QueryAnalysis, TempMongoCryptd, TempMongoCryptdBuilder};
+pub use download::{BinaryCache, DownloadProgress, Edition, MongoBinaries, MongoDownloader};
 pub use error::Error;
+pub use fleet::{MongoFleet, MongoFleetBuilder};
+pub use hooks::LifecycleHook;
+#[cfg(feature = "csfle")]
+pub use local_kms::LocalKms;
+#[cfg(feature = "mock-kms")]
+pub use mock_kms::MockKms;
+pub use nextest::serve_for_nextest;
+pub use pool::InstancePool;
+pub use property::CaseRunner;
+pub use util::BindIp;
+pub use util::CloneOptions;
+pub use util::CurrentOp;
+pub use util::ExecutedCommand;
+pub use util::PoolStatistics;
+pub use util::{Heartbeat, LatencyStatistics};
+pub use util::FailureReport;
+pub use util::Credentials;
+pub use util::generate_keyfile;
+pub use util::{golden_diff, render_golden};
+pub use util::isolated_database_name;
+pub use util::LogForwarder;
 pub use util::DataSeeder;
+pub use util::{CollectionDiff, DatabaseDiff, DiffOptions, FieldMismatch};
+pub use util::Manifest;
+pub use util::MongodConfig;
+pub use util::ResourceLimits;
+pub use util::RetryPolicy;
+pub use util::SecretString;
+pub use util::{Opcounters, ServerStatus};
+pub use util::{OperationCounts, ProfiledOperation, ProfilingLevel};
+pub use util::StartupMetrics;
+pub use util::ViewSeeder;
+pub use replica_set::{MemberOptions, TempMongoReplicaSet, TempMongoReplicaSetBuilder};
+pub use scenario::ScenarioMongo;
+pub use sharded::{TempMongoSharded, TempMongoShardedBuilder};
+pub use shared::{DatabaseGuard, SharedMongo};
 pub use temp_mongo::TempMongo;
 pub use temp_mongo::TempMongoBuilder;
+pub use topology::{MemberTopology, ReplicaSetTopology, ShardedTopology, StandaloneTopology, TempMongoTopology, Topology};
@@ -0,0 +1,113 @@
+use crate::error::ErrorInner;
+use crate::Error;
+use mongocrypt::ctx::KmsProvider;
+use mongodb::bson::spec::BinarySubtype;
+use mongodb::bson::{doc, Binary, Document};
+use mongodb::client_encryption::{ClientEncryption, MasterKey};
+use mongodb::options::{IndexOptions, TlsOptions};
+use mongodb::{IndexModel, Namespace};
+use rand::Rng;
+
+/// The number of bytes in a local master key.
+const MASTER_KEY_LENGTH: usize = 96;
+
+/// A local KMS provider with data keys in the key vault of a temporary MongoDB instance.
+///
+/// Created with [`TempMongo::local_kms()`][crate::TempMongo::local_kms],
+/// or with [`LocalKms::create()`] for the client of any other temporary deployment.
+/// The master key only lives in memory, so the data keys can not be decrypted once this object is dropped.
+#[derive(Debug, Clone)]
+pub struct LocalKms {
+	master_key: Vec<u8>,
+	key_vault_namespace: Namespace,
+	data_keys: Vec<Binary>,
+}
+
+impl LocalKms {
+	/// Generate a random master key, create the key vault collection and create the given number of data keys.
+	///
+	/// The key vault collection is `encryption.__keyVault`.
+	pub async fn create(client: &mongodb::Client, data_keys: usize) -> Result<Self, Error> {
+		let mut master_key = vec![0; MASTER_KEY_LENGTH];
+		rand::thread_rng().fill(&mut master_key[..]);
+		let key_vault_namespace = Namespace::new("encryption", "__keyVault");
+
+		// Key alternative names must be unique, as required by the client-side encryption specification.
+		let index = IndexModel::builder()
+			.keys(doc! { "keyAltNames": 1 })
+			.options(IndexOptions::builder()
+				.unique(true)
+				.partial_filter_expression(doc! { "keyAltNames": { "$exists": true } })
+				.build())
+			.build();
+		client
+			.database(&key_vault_namespace.db)
+			.collection::<Document>(&key_vault_namespace.coll)
+			.create_index(index, None)
+			.await
+			.map_err(|e| ErrorInner::CreateKeyVault(key_vault_namespace.to_string(), e))?;
+
+		let mut kms = Self {
+			master_key,
+			key_vault_namespace,
+			data_keys: Vec::with_capacity(data_keys),
+		};
+		let encryption = ClientEncryption::new(client.clone(), kms.key_vault_namespace.clone(), kms.kms_providers())
+			.map_err(ErrorInner::CreateDataKey)?;
+		for _ in 0..data_keys {
+			let key = encryption
+				.create_data_key(MasterKey::Local)
+				.run()
+				.await
+				.map_err(ErrorInner::CreateDataKey)?;
+			kms.data_keys.push(key);
+		}
+		Ok(kms)
+	}
+
+	/// Get the random master key of 96 bytes.
+	pub fn master_key(&self) -> &[u8] {
+		&self.master_key
+	}
+
+	/// Get the namespace of the key vault collection.
+	pub fn key_vault_namespace(&self) -> &Namespace {
+		&self.key_vault_namespace
+	}
+
+	/// Get the IDs of the data keys, which are UUIDs.
+	pub fn data_keys(&self) -> &[Binary] {
+		&self.data_keys
+	}
+
+	/// Get the KMS providers to pass to the driver, with only the local provider.
+	pub fn kms_providers(&self) -> Vec<(KmsProvider, Document, Option<TlsOptions>)> {
+		let key = Binary {
+			subtype: BinarySubtype::Generic,
+			bytes: self.master_key.clone(),
+		};
+		vec![(KmsProvider::Local, doc! { "key": key }, None)]
+	}
+
+	/// Create the encrypted fields of a collection for Queryable Encryption.
+	///
+	/// Each field is given by its path and BSON type, like `("ssn", "string")`,
+	/// and is encrypted with the data key at the same index, with support for equality queries.
+	///
+	/// # Panics
+	/// This function panics if there are more fields than data keys.
+	pub fn encrypted_fields(&self, fields: &[(&str, &str)]) -> Document {
+		assert!(fields.len() <= self.data_keys.len(), "{} encrypted fields need as many data keys, but only {} were created", fields.len(), self.data_keys.len());
+		let fields: Vec<Document> = fields
+			.iter()
+			.zip(&self.data_keys)
+			.map(|((path, bson_type), key)| doc! {
+				"keyId": key.clone(),
+				"path": *path,
+				"bsonType": *bson_type,
+				"queries": { "queryType": "equality" },
+			})
+			.collect();
+		doc! { "fields": fields }
+	}
+}
@@ -0,0 +1,265 @@
+use crate::error::ErrorInner;
+use crate::util::{TempDir, TlsCertificates};
+use crate::Error;
+use mongodb::options::TlsOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// The access token handed out by the OAuth endpoints of the mock.
+const ACCESS_TOKEN: &str = "temp-mongo-mock-token";
+
+/// A mock KMS server for testing client-side encryption with cloud KMS providers, without cloud credentials.
+///
+/// The server listens on a free TCP port on the loopback adapter in a background thread, and uses TLS like the real services.
+/// It implements the key operations that `libmongocrypt` uses for AWS, Azure and GCP,
+/// and the OAuth token endpoints for Azure and GCP.
+/// Point the endpoints of the KMS providers and master keys at [`Self::endpoint()`],
+/// and pass [`Self::tls_options()`] as TLS options of the KMS providers, so the driver trusts the generated certificate.
+///
+/// The mock does not actually encrypt anything: the "ciphertext" of a data key is its plaintext.
+/// Any credentials are accepted.
+///
+/// The server is stopped when this object is dropped.
+/// Only available with the `mock-kms` feature.
+pub struct MockKms {
+	port: u16,
+	tls: TlsCertificates,
+	operations: Arc<Mutex<Vec<String>>>,
+	stop: Arc<AtomicBool>,
+	thread: Option<JoinHandle<()>>,
+
+	/// The temporary directory with the certificates.
+	///
+	/// Declared last, so it is only removed after the server is stopped.
+	tempdir: TempDir,
+}
+
+impl std::fmt::Debug for MockKms {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("MockKms")
+			.field("tempdir", &self.tempdir.path())
+			.field("endpoint", &self.endpoint())
+			.finish_non_exhaustive()
+	}
+}
+
+impl MockKms {
+	/// Generate certificates and start the mock KMS server.
+	///
+	/// The certificates are generated with the `openssl` command, which must be available in `PATH`.
+	pub fn new() -> Result<Self, Error> {
+		let tempdir = TempDir::new(true).map_err(ErrorInner::MakeTempDir)?;
+		let tls = TlsCertificates::generate(tempdir.path())?;
+		let config = server_config(tls.server_pem()).map_err(ErrorInner::StartMockKms)?;
+		let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).map_err(ErrorInner::StartMockKms)?;
+		let port = listener.local_addr().map_err(ErrorInner::StartMockKms)?.port();
+
+		let operations = Arc::new(Mutex::new(Vec::new()));
+		let stop = Arc::new(AtomicBool::new(false));
+		let thread = std::thread::Builder::new()
+			.name("temp-mongo-mock-kms".into())
+			.spawn({
+				let operations = operations.clone();
+				let stop = stop.clone();
+				move || serve(listener, config, &operations, &stop)
+			})
+			.map_err(ErrorInner::StartMockKms)?;
+
+		Ok(Self {
+			port,
+			tls,
+			operations,
+			stop,
+			thread: Some(thread),
+			tempdir,
+		})
+	}
+
+	/// Get the TCP port the mock KMS server is listening on.
+	pub fn port(&self) -> u16 {
+		self.port
+	}
+
+	/// Get the endpoint of the mock KMS server, as `host:port`.
+	///
+	/// Use this as `endpoint` of AWS and GCP master keys, as `keyVaultEndpoint` of Azure master keys,
+	/// and as `endpoint` or `identityPlatformEndpoint` of the GCP and Azure KMS providers.
+	pub fn endpoint(&self) -> String {
+		format!("127.0.0.1:{}", self.port)
+	}
+
+	/// Get the path of the certificate of the certificate authority that signed the server certificate.
+	pub fn ca_file(&self) -> &Path {
+		self.tls.ca_file()
+	}
+
+	/// Get the TLS options for a KMS provider that trust the certificate of the mock KMS server.
+	pub fn tls_options(&self) -> TlsOptions {
+		TlsOptions::builder()
+			.ca_file_path(self.ca_file().to_owned())
+			.build()
+	}
+
+	/// Get the operations handled by the mock KMS server so far, in order.
+	///
+	/// Operations are named after the provider and the operation, like `"aws:Encrypt"`, `"azure:unwrapkey"`, `"gcp:decrypt"` or `"oauth:token"`.
+	pub fn operations(&self) -> Vec<String> {
+		self.operations.lock().map(|operations| operations.clone()).unwrap_or_default()
+	}
+}
+
+impl Drop for MockKms {
+	fn drop(&mut self) {
+		self.stop.store(true, Ordering::Relaxed);
+		// Wake up the server thread, which is blocked in `accept()`.
+		TcpStream::connect((Ipv4Addr::LOCALHOST, self.port)).ok();
+		if let Some(thread) = self.thread.take() {
+			thread.join().ok();
+		}
+	}
+}
+
+/// Create the TLS configuration of the server from a PEM file with the certificate and the private key.
+fn server_config(pem: &Path) -> std::io::Result<Arc<rustls::ServerConfig>> {
+	let read = || std::fs::File::open(pem).map(BufReader::new);
+	let certificates = rustls_pemfile::certs(&mut read()?)?
+		.into_iter()
+		.map(rustls::Certificate)
+		.collect();
+	let key = rustls_pemfile::pkcs8_private_keys(&mut read()?)?
+		.pop()
+		.map(rustls::PrivateKey)
+		.ok_or_else(|| std::io::Error::other(format!("no private key in {}", pem.display())))?;
+	let config = rustls::ServerConfig::builder()
+		.with_safe_defaults()
+		.with_no_client_auth()
+		.with_single_cert(certificates, key)
+		.map_err(std::io::Error::other)?;
+	Ok(Arc::new(config))
+}
+
+/// Accept and handle connections until `stop` is set.
+fn serve(listener: TcpListener, config: Arc<rustls::ServerConfig>, operations: &Mutex<Vec<String>>, stop: &AtomicBool) {
+	for stream in listener.incoming() {
+		if stop.load(Ordering::Relaxed) {
+			return;
+		}
+		let Ok(stream) = stream else {
+			continue;
+		};
+		// Errors only affect a single request, which the client reports by itself.
+		handle_connection(stream, config.clone(), operations).ok();
+	}
+}
+
+/// Handle all requests on a single connection.
+fn handle_connection(stream: TcpStream, config: Arc<rustls::ServerConfig>, operations: &Mutex<Vec<String>>) -> std::io::Result<()> {
+	stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+	let connection = rustls::ServerConnection::new(config).map_err(std::io::Error::other)?;
+	let mut stream = BufReader::new(rustls::StreamOwned::new(connection, stream));
+
+	while let Some(request) = read_request(&mut stream)? {
+		let (operation, response) = respond(&request);
+		if let Ok(mut operations) = operations.lock() {
+			operations.push(operation);
+		}
+		let status = if response.is_null() { "404 Not Found" } else { "200 OK" };
+		let response = serde_json::to_string(&response)?;
+		write!(
+			stream.get_mut(),
+			"HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{response}",
+			response.len(),
+		)?;
+		stream.get_mut().flush()?;
+	}
+	Ok(())
+}
+
+/// An HTTP request to the mock KMS server.
+struct Request {
+	/// The path of the request, without the query string.
+	path: String,
+
+	/// The value of the `X-Amz-Target` header, used by AWS to select the operation.
+	amz_target: Option<String>,
+
+	/// The JSON body of the request, or `Null` if there is none.
+	body: serde_json::Value,
+}
+
+/// Read an HTTP request from a stream.
+///
+/// Returns `None` if the client closed the connection.
+fn read_request(stream: &mut impl BufRead) -> std::io::Result<Option<Request>> {
+	let mut line = String::new();
+	if stream.read_line(&mut line)? == 0 {
+		return Ok(None);
+	}
+	let target = line.split_whitespace().nth(1).unwrap_or_default();
+	let path = target.split('?').next().unwrap_or_default().to_owned();
+
+	let mut amz_target = None;
+	let mut content_length = 0;
+	loop {
+		line.clear();
+		stream.read_line(&mut line)?;
+		let header = line.trim_end();
+		if header.is_empty() {
+			break;
+		}
+		let Some((name, value)) = header.split_once(':') else {
+			continue;
+		};
+		let value = value.trim();
+		if name.eq_ignore_ascii_case("content-length") {
+			content_length = value.parse().map_err(std::io::Error::other)?;
+		} else if name.eq_ignore_ascii_case("x-amz-target") {
+			amz_target = Some(value.to_owned());
+		}
+	}
+
+	let mut body = vec![0; content_length];
+	stream.read_exact(&mut body)?;
+	// OAuth token requests are form encoded, which is not needed by the mock.
+	let body = serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
+	Ok(Some(Request { path, amz_target, body }))
+}
+
+/// Determine the operation of a request and the JSON response to it.
+///
+/// The response is `Null` for unknown operations.
+fn respond(request: &Request) -> (String, serde_json::Value) {
+	use serde_json::json;
+
+	let field = |name: &str| request.body.get(name).cloned().unwrap_or_default();
+	if let Some(target) = &request.amz_target {
+		let operation = target.rsplit('.').next().unwrap_or_default();
+		let response = match operation {
+			"Encrypt" => json!({ "CiphertextBlob": field("Plaintext"), "KeyId": field("KeyId") }),
+			"Decrypt" => json!({ "Plaintext": field("CiphertextBlob"), "KeyId": "mock" }),
+			_ => serde_json::Value::Null,
+		};
+		return (format!("aws:{operation}"), response);
+	}
+
+	let operation = request.path.rsplit(['/', ':']).next().unwrap_or_default();
+	let response = match operation {
+		"wrapkey" | "unwrapkey" => json!({ "kid": request.path, "value": field("value") }),
+		"encrypt" => json!({ "ciphertext": field("plaintext") }),
+		"decrypt" => json!({ "plaintext": field("ciphertext") }),
+		"token" => json!({ "access_token": ACCESS_TOKEN, "token_type": "Bearer", "expires_in": 3600 }),
+		_ => serde_json::Value::Null,
+	};
+	let provider = match operation {
+		"wrapkey" | "unwrapkey" => "azure",
+		"encrypt" | "decrypt" => "gcp",
+		"token" => "oauth",
+		_ => "unknown",
+	};
+	(format!("{provider}:{operation}"), response)
+}
@@ -0,0 +1,43 @@
+use crate::error::ErrorInner;
+use crate::{Error, TempMongoBuilder};
+use std::future::Future;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// The environment variable with the path of the file that `cargo-nextest` reads environment variables from after a setup script.
+const NEXTEST_ENV: &str = "NEXTEST_ENV";
+
+/// Spawn a server and make its connection string available to the tests run by `cargo-nextest`, until `teardown` completes.
+///
+/// Once the server is ready, the environment variables `MONGODB_URI` and `TEMP_MONGO_PID` are written as `KEY=VALUE` lines to standard output.
+/// `TEMP_MONGO_PID` is the PID of the current process, so a wrapper script can signal it to tear down the server.
+/// If the `NEXTEST_ENV` environment variable is set, the same lines are appended to the file it points to,
+/// which is how a nextest setup script passes environment variables to the tests.
+/// After `teardown` completes, the server is cleaned up with [`crate::TempMongo::kill_and_clean()`].
+///
+/// Nextest waits for setup scripts to exit, so the setup script should run this in a detached process.
+/// The `temp-mongo-nextest` binary does this with the `--detach` option.
+pub async fn serve_for_nextest<F>(builder: &TempMongoBuilder, teardown: F) -> Result<(), Error>
+where
+	F: Future<Output = ()>,
+{
+	let mongo = builder.spawn().await?;
+	let variables = format!("MONGODB_URI={}\nTEMP_MONGO_PID={}\n", mongo.uri(), std::process::id());
+
+	if let Some(path) = std::env::var_os(NEXTEST_ENV).map(PathBuf::from) {
+		let written = std::fs::OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(&path)
+			.and_then(|mut file| file.write_all(variables.as_bytes()));
+		if let Err(e) = written {
+			mongo.kill_and_clean().await.ok();
+			return Err(ErrorInner::WriteNextestEnv(path, e).into());
+		}
+	}
+	let mut stdout = std::io::stdout();
+	stdout.write_all(variables.as_bytes()).and_then(|()| stdout.flush()).ok();
+
+	teardown.await;
+	mongo.kill_and_clean().await
+}
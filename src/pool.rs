@@ -0,0 +1,154 @@
+use crate::error::ErrorInner;
+use crate::{Error, TempMongo, TempMongoBuilder};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// A pool of temporary MongoDB instances that are started in the background, so tests get a running instance instantly.
+///
+/// The pool keeps `size` instances ready.
+/// [`Self::acquire()`] hands out a ready instance and starts a replacement in the background.
+/// [`Self::release()`] removes all data from an instance and returns it to the pool, or cleans it up if the pool is full.
+///
+/// The instances are started on a runtime that is owned by the pool, in the background threads of that runtime.
+/// Their clients can still be used from any runtime, as long as the pool is alive.
+/// Remaining instances are cleaned up when the pool is dropped.
+pub struct InstancePool {
+	/// The instances that are ready to be handed out, shared with the background tasks.
+	idle: Arc<Idle>,
+
+	/// The options used to start new instances.
+	builder: TempMongoBuilder,
+
+	/// The number of instances to keep ready.
+	size: usize,
+
+	/// The runtime that starts the instances in the background.
+	///
+	/// This is only `None` while the pool is dropped.
+	runtime: Option<tokio::runtime::Runtime>,
+}
+
+/// The instances of a pool that are ready to be handed out.
+#[derive(Default)]
+struct Idle {
+	/// The started instances, or the errors of instances that failed to start.
+	instances: Mutex<VecDeque<Result<TempMongo, Error>>>,
+
+	/// Notified each time an instance is added.
+	added: Notify,
+}
+
+impl Idle {
+	/// Add an instance and wake up a waiting [`InstancePool::acquire()`].
+	fn push(&self, instance: Result<TempMongo, Error>) {
+		if let Ok(mut instances) = self.instances.lock() {
+			instances.push_back(instance);
+		}
+		self.added.notify_one();
+	}
+
+	/// Take the oldest instance, if any.
+	fn pop(&self) -> Option<Result<TempMongo, Error>> {
+		self.instances.lock().ok()?.pop_front()
+	}
+
+	/// Get the number of instances.
+	fn len(&self) -> usize {
+		self.instances.lock().map(|instances| instances.len()).unwrap_or(0)
+	}
+}
+
+impl std::fmt::Debug for InstancePool {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("InstancePool")
+			.field("size", &self.size)
+			.field("idle", &self.idle.len())
+			.finish_non_exhaustive()
+	}
+}
+
+impl InstancePool {
+	/// Create a pool that keeps `size` instances with default options ready.
+	///
+	/// The instances are started in the background, so this returns immediately.
+	pub fn new(size: usize) -> Result<Self, Error> {
+		Self::with_builder(TempMongo::builder(), size)
+	}
+
+	/// Create a pool that keeps `size` instances ready, started with the options of a builder.
+	///
+	/// The instances are started in the background, so this returns immediately.
+	pub fn with_builder(builder: TempMongoBuilder, size: usize) -> Result<Self, Error> {
+		let runtime = tokio::runtime::Builder::new_multi_thread()
+			.thread_name("temp-mongo-pool")
+			.enable_all()
+			.build()
+			.map_err(ErrorInner::StartRuntime)?;
+		let pool = Self {
+			idle: Arc::default(),
+			builder,
+			size,
+			runtime: Some(runtime),
+		};
+		for _ in 0..size {
+			pool.start_instance();
+		}
+		Ok(pool)
+	}
+
+	/// Get the number of instances the pool keeps ready.
+	pub fn size(&self) -> usize {
+		self.size
+	}
+
+	/// Take a ready instance from the pool, and start a replacement in the background.
+	///
+	/// If no instance is ready yet, this waits for the next one to finish starting.
+	/// If that instance failed to start, the error is returned.
+	pub async fn acquire(&self) -> Result<TempMongo, Error> {
+		self.start_instance();
+		loop {
+			if let Some(instance) = self.idle.pop() {
+				return instance;
+			}
+			self.idle.added.notified().await;
+		}
+	}
+
+	/// Return an instance to the pool, after removing all its data with [`TempMongo::reset()`].
+	///
+	/// If the pool already has enough ready instances, or the reset fails, the instance is cleaned up instead.
+	pub async fn release(&self, mongo: TempMongo) -> Result<(), Error> {
+		if self.idle.len() >= self.size {
+			return mongo.kill_and_clean().await;
+		}
+		if let Err(e) = mongo.reset().await {
+			mongo.kill_and_clean().await.ok();
+			return Err(e);
+		}
+		self.idle.push(Ok(mongo));
+		Ok(())
+	}
+
+	/// Start a new instance in the background, and add it to the idle instances when it is ready.
+	fn start_instance(&self) {
+		let Some(runtime) = &self.runtime else {
+			return;
+		};
+		let idle = self.idle.clone();
+		let builder = self.builder.clone();
+		runtime.spawn(async move {
+			idle.push(builder.spawn().await);
+		});
+	}
+}
+
+impl Drop for InstancePool {
+	fn drop(&mut self) {
+		// Dropping a runtime blocks, which is not allowed when the pool is dropped in an async context.
+		if let Some(runtime) = self.runtime.take() {
+			runtime.shutdown_background();
+		}
+	}
+}
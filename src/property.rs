@@ -0,0 +1,78 @@
+use crate::{Error, TempMongo};
+use std::future::Future;
+
+/// Runs the generated cases of a property test against one server, with clean data for each case.
+///
+/// Before each case, all data is removed with [`TempMongo::reset()`] and the seed profile is loaded, if one is set.
+/// The server is not restarted, so thousands of cases stay fast.
+/// Create a runner with [`TempMongo::case_runner()`].
+///
+/// Property testing frameworks like `proptest` and `quickcheck` call the test synchronously.
+/// Drive the runner there with a runtime that outlives the cases, like the one of [`crate::BenchmarkMongo`]:
+///
+/// ```no_run
+/// # fn main() -> Result<(), temp_mongo::Error> {
+/// use mongodb::bson::{doc, Document};
+/// use temp_mongo::TempMongo;
+///
+/// let mongo = TempMongo::for_benchmarks()?;
+/// let mut runner = mongo.mongo().case_runner();
+/// for count in 0..100 {
+///     mongo.block_on(runner.run_case(|mongo| async move {
+///         let collection = mongo.client().database("test").collection::<Document>("counts");
+///         collection.insert_one(doc! { "count": count }, None).await.unwrap();
+///         assert_eq!(collection.count_documents(None, None).await.unwrap(), 1);
+///     }))?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct CaseRunner<'a> {
+	/// The server to run the cases against.
+	mongo: &'a TempMongo,
+
+	/// The seed profile to load before each case.
+	profile: Option<String>,
+
+	/// The number of cases that were run.
+	cases_run: usize,
+}
+
+impl<'a> CaseRunner<'a> {
+	/// Create a runner for a server.
+	pub fn new(mongo: &'a TempMongo) -> Self {
+		Self {
+			mongo,
+			profile: None,
+			cases_run: 0,
+		}
+	}
+
+	/// Load a seed profile registered with [`crate::TempMongoBuilder::seed_profile()`] before each case.
+	pub fn with_profile(mut self, name: impl Into<String>) -> Self {
+		self.profile = Some(name.into());
+		self
+	}
+
+	/// Reset the data of the server and run a single case.
+	///
+	/// Returns the output of the case, or an error if resetting the data failed.
+	pub async fn run_case<F, Fut>(&mut self, case: F) -> Result<Fut::Output, Error>
+	where
+		F: FnOnce(&'a TempMongo) -> Fut,
+		Fut: Future,
+	{
+		self.mongo.reset().await?;
+		if let Some(name) = &self.profile {
+			self.mongo.load_profile(name).await?;
+		}
+		self.cases_run += 1;
+		Ok(case(self.mongo).await)
+	}
+
+	/// Get the number of cases that were run so far.
+	pub fn cases_run(&self) -> usize {
+		self.cases_run
+	}
+}
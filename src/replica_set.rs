@@ -0,0 +1,1326 @@
+use crate::error::ErrorInner;
+use crate::util::{
+	bind_failed, default_credentials, event, fast_startup_args, feature_compatibility_version,
+	free_port, generate_keyfile, random_password, redact_uri, server_output_tail,
+	set_feature_compatibility_version, set_parameter_args, spawn_server, update_password,
+	verbosity_args, BindIp, ConfigSource, Credentials, KillOnDrop, MongodConfig, ResourceLimits,
+	SecretString, TempDir, DEFAULT_PORT_RETRIES, FAST_OPLOG_SIZE_MB,
+};
+use crate::Error;
+use mongodb::bson::{doc, Bson, Document};
+use mongodb::options::{ClientOptions, ServerAddress};
+use rand::Rng;
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+/// A temporary MongoDB replica set.
+///
+/// Each member of the replica set is a separate `mongod` process listening on a free TCP port on the loopback adapter.
+/// All state of the replica set is stored in a single temporary directory.
+/// Unless disabled, the temporary directory is deleted when this object is dropped.
+pub struct TempMongoReplicaSet {
+	name: String,
+	credentials: Option<Credentials>,
+	members: Vec<Member>,
+	client: mongodb::Client,
+	builder: TempMongoReplicaSetBuilder,
+	keyfile: Option<PathBuf>,
+	next_id: i32,
+
+	/// The temporary state directory.
+	///
+	/// Declared last, so it is only removed after all processes are killed.
+	tempdir: TempDir,
+}
+
+/// A single member of a [`TempMongoReplicaSet`].
+struct Member {
+	id: i32,
+	port: u16,
+	arbiter: bool,
+	options: MemberOptions,
+	log_path: PathBuf,
+	output_path: PathBuf,
+	command: OsString,
+	args: Vec<OsString>,
+	server: KillOnDrop,
+	client: mongodb::Client,
+}
+
+impl std::fmt::Debug for TempMongoReplicaSet {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("TempMongoReplicaSet")
+			.field("tempdir", &self.tempdir.path())
+			.field("name", &self.name)
+			.field("uri", &redact_uri(&self.uri()))
+			.field("member_pids", &self.members.iter().map(|member| member.server.id()).collect::<Vec<_>>())
+			.finish_non_exhaustive()
+	}
+}
+
+impl TempMongoReplicaSet {
+	/// Spawn a new replica set with `members` members.
+	pub async fn new(members: usize) -> Result<Self, Error> {
+		Self::from_builder(&TempMongoReplicaSetBuilder::new().members(members)).await
+	}
+
+	/// Create a builder to customize your [`TempMongoReplicaSet`].
+	///
+	/// After configuring the desired options, run [`TempMongoReplicaSetBuilder::spawn()`].
+	pub fn builder() -> TempMongoReplicaSetBuilder {
+		TempMongoReplicaSetBuilder::new()
+	}
+
+	/// Get the name of the replica set.
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	/// Get the path of the temporary state directory.
+	pub fn directory(&self) -> &Path {
+		self.tempdir.path()
+	}
+
+	/// Get the number of members in the replica set.
+	pub fn member_count(&self) -> usize {
+		self.members.len()
+	}
+
+	/// Get the replica set aware connection string for the replica set.
+	///
+	/// If authentication is enabled, the connection string includes the credentials of the root user.
+	pub fn uri(&self) -> String {
+		let hosts: Vec<_> = self.members.iter().map(|member| host(member.port)).collect();
+		match &self.credentials {
+			Some(credentials) => format!(
+				"mongodb://{}{}/?replicaSet={}&authSource=admin",
+				credentials.to_user_info(),
+				hosts.join(","),
+				self.name,
+			),
+			None => format!("mongodb://{}/?replicaSet={}", hosts.join(","), self.name),
+		}
+	}
+
+	/// Get the comma separated `host:port` addresses of all members.
+	pub(crate) fn hosts(&self) -> String {
+		self.members.iter().map(|member| host(member.port)).collect::<Vec<_>>().join(",")
+	}
+
+	/// Get the credentials of the root user, if authentication is enabled.
+	pub fn credentials(&self) -> Option<&Credentials> {
+		self.credentials.as_ref()
+	}
+
+	/// Change the password of a user in the `admin` database to a new random password.
+	///
+	/// Returns the new password.
+	/// If the user is the root user from [`Self::credentials()`], the stored credentials and [`Self::uri()`] are updated,
+	/// and all clients of this object are replaced by clients that authenticate with the new password.
+	/// Clones of the previous clients keep their open connections, but new connections fail to authenticate.
+	/// This can be used to test the credential refresh logic of an application.
+	pub async fn rotate_password(&mut self, username: &str) -> Result<String, Error> {
+		let password = random_password();
+		update_password(&self.client, username, &password).await?;
+		if let Some(credentials) = self.credentials.as_mut().filter(|credentials| credentials.username == username) {
+			credentials.password = SecretString::new(password.clone());
+			self.builder.credentials = Some(credentials.clone());
+			for member in &mut self.members {
+				member.client = direct_client(member.port, member_credentials(&self.builder, member.arbiter))?;
+			}
+			self.client = replica_set_client(&self.name, &self.members, self.credentials.as_ref()).await?;
+		}
+		Ok(password)
+	}
+
+	/// Get a replica set aware client for the replica set.
+	///
+	/// This returns a client by reference,
+	/// but it can be cloned and sent to other threads or tasks if needed.
+	pub fn client(&self) -> &mongodb::Client {
+		&self.client
+	}
+
+	/// Get a client that is directly connected to a single member of the replica set.
+	///
+	/// # Panics
+	/// This function panics if `index` is out of bounds.
+	pub fn member_client(&self, index: usize) -> &mongodb::Client {
+		&self.members[index].client
+	}
+
+	/// Get the direct connection string for a single member of the replica set.
+	///
+	/// If authentication is enabled, the connection string includes the credentials of the root user.
+	///
+	/// # Panics
+	/// This function panics if `index` is out of bounds.
+	pub fn member_uri(&self, index: usize) -> String {
+		let host = host(self.members[index].port);
+		match &self.credentials {
+			Some(credentials) => format!(
+				"mongodb://{}{host}/?directConnection=true&authSource=admin",
+				credentials.to_user_info(),
+			),
+			None => format!("mongodb://{host}/?directConnection=true"),
+		}
+	}
+
+	/// Get the TCP port a member of the replica set is listening on.
+	///
+	/// # Panics
+	/// This function panics if `index` is out of bounds.
+	pub fn member_port(&self, index: usize) -> u16 {
+		self.members[index].port
+	}
+
+	/// Get the PID of the `mongod` process of a member of the replica set.
+	///
+	/// After a restart, this returns the PID of the new process.
+	///
+	/// # Panics
+	/// This function panics if `index` is out of bounds.
+	pub fn member_pid(&self, index: usize) -> u32 {
+		self.members[index].server.id()
+	}
+
+	/// Get the index of the arbiter member, if the replica set has one.
+	pub fn arbiter_index(&self) -> Option<usize> {
+		self.members.iter().position(|member| member.arbiter)
+	}
+
+	/// Get the path of the log file of a member of the replica set.
+	///
+	/// # Panics
+	/// This function panics if `index` is out of bounds.
+	pub fn member_log_path(&self, index: usize) -> &Path {
+		&self.members[index].log_path
+	}
+
+	/// Get the index of the current primary, if the replica set has one.
+	pub async fn primary_index(&self) -> Option<usize> {
+		find_primary(self.members.iter().map(|member| &member.client)).await
+	}
+
+	/// Ask the current primary to step down, triggering an election.
+	///
+	/// The stepped down member can not become primary again for 60 seconds.
+	/// Returns the index of the member that stepped down.
+	pub async fn step_down_primary(&self) -> Result<usize, Error> {
+		let primary = self.primary_index().await.ok_or(ErrorInner::NoPrimary)?;
+		self.members[primary]
+			.client
+			.database("admin")
+			.run_command(doc! { "replSetStepDown": 60 }, None)
+			.await
+			.map_err(ErrorInner::StepDown)?;
+		Ok(primary)
+	}
+
+	/// Wait until a member other than `previous` has been elected primary.
+	///
+	/// Returns the index of the new primary.
+	pub async fn await_new_primary(&self, previous: usize, timeout: Duration) -> Result<usize, Error> {
+		let start = Instant::now();
+		loop {
+			match self.primary_index().await {
+				Some(index) if index != previous => return Ok(index),
+				_ => (),
+			}
+			if start.elapsed() > timeout {
+				return Err(ErrorInner::ReplicaSetTimeout(timeout).into());
+			}
+			sleep(Duration::from_millis(100)).await;
+		}
+	}
+
+	/// Wait until the replica set has a primary and all other data bearing members are secondaries.
+	///
+	/// This is useful after restarting or adding members, to wait until they caught up with the primary.
+	/// Note that this times out if any member is not running.
+	pub async fn await_ready(&self, timeout: Duration) -> Result<(), Error> {
+		let clients: Vec<_> = self.members
+			.iter()
+			.filter(|member| !member.arbiter)
+			.map(|member| &member.client)
+			.collect();
+		wait_for_ready(&clients, timeout).await
+	}
+
+	/// Kill a member of the replica set with `SIGKILL`, simulating a crash.
+	///
+	/// The member can be started again with [`Self::restart_member()`].
+	///
+	/// # Panics
+	/// This function panics if `index` is out of bounds.
+	pub fn kill_member(&mut self, index: usize) -> Result<(), Error> {
+		let server = &mut self.members[index].server;
+		server.kill().map_err(ErrorInner::KillServer)?;
+		server.wait().map_err(ErrorInner::KillServer)?;
+		Ok(())
+	}
+
+	/// Kill the current primary with `SIGKILL`, simulating a crash.
+	///
+	/// Returns the index of the killed member.
+	pub async fn kill_primary(&mut self) -> Result<usize, Error> {
+		let primary = self.primary_index().await.ok_or(ErrorInner::NoPrimary)?;
+		self.kill_member(primary)?;
+		Ok(primary)
+	}
+
+	/// Kill a randomly chosen member with `SIGKILL`, simulating a crash.
+	///
+	/// Returns the index of the killed member.
+	pub fn kill_random_member(&mut self) -> Result<usize, Error> {
+		let index = rand::thread_rng().gen_range(0..self.members.len());
+		self.kill_member(index)?;
+		Ok(index)
+	}
+
+	/// Start a previously killed member again with the same data directory and port.
+	///
+	/// The member rejoins the replica set after it has started.
+	/// If the member is still running, it is killed first.
+	///
+	/// # Panics
+	/// This function panics if `index` is out of bounds.
+	pub fn restart_member(&mut self, index: usize) -> Result<(), Error> {
+		let member = &mut self.members[index];
+		member.server.kill().map_err(ErrorInner::KillServer)?;
+		member.server.wait().map_err(ErrorInner::KillServer)?;
+		member.server = spawn_server(&member.command, &member.args, &member.output_path, &self.builder.resource_limits)?;
+		Ok(())
+	}
+
+	/// Spawn a new data bearing member and add it to the replica set.
+	///
+	/// Returns the index of the new member.
+	pub async fn add_member(&mut self) -> Result<usize, Error> {
+		self.add_member_with(MemberOptions::new()).await
+	}
+
+	/// Spawn a new data bearing member with the given options and add it to the replica set.
+	///
+	/// Returns the index of the new member.
+	pub async fn add_member_with(&mut self, options: MemberOptions) -> Result<usize, Error> {
+		let mut member = Member::spawn(&self.builder, self.tempdir.path(), self.next_id, false, options, self.keyfile.as_deref())?;
+		self.next_id += 1;
+		member.await_listening(&self.builder).await?;
+
+		let mut config = self.get_config().await?;
+		let mut members = config.get_array("members").cloned().unwrap_or_default();
+		let delay_field = secondary_delay_field(std::slice::from_ref(&member)).await?;
+		members.push(member.config(delay_field).into());
+		config.insert("members", members);
+		self.reconfigure(config).await?;
+
+		self.members.push(member);
+		Ok(self.members.len() - 1)
+	}
+
+	/// Remove a member from the replica set and kill it.
+	///
+	/// The indices of all members after the removed member shift down by one.
+	///
+	/// The current primary can not be removed, since it processes the reconfiguration.
+	/// Step it down first with [`Self::step_down_primary()`].
+	///
+	/// # Panics
+	/// This function panics if `index` is out of bounds.
+	pub async fn remove_member(&mut self, index: usize) -> Result<(), Error> {
+		let id = self.members[index].id;
+		if self.primary_index().await == Some(index) {
+			return Err(ErrorInner::RemovePrimary(index).into());
+		}
+
+		let mut config = self.get_config().await?;
+		let members: Vec<_> = config
+			.get_array("members")
+			.cloned()
+			.unwrap_or_default()
+			.into_iter()
+			.filter(|member| member.as_document().and_then(|member| member.get_i32("_id").ok()) != Some(id))
+			.collect();
+		config.insert("members", members);
+		self.reconfigure(config).await?;
+
+		let mut member = self.members.remove(index);
+		member.client.shutdown_immediate().await;
+		member.server.kill().map_err(ErrorInner::KillServer)?;
+		member.server.wait().map_err(ErrorInner::KillServer)?;
+		Ok(())
+	}
+
+	/// Get the current replica set configuration from the primary.
+	async fn get_config(&self) -> Result<Document, Error> {
+		let reply = self.client
+			.database("admin")
+			.run_command(doc! { "replSetGetConfig": 1 }, None)
+			.await
+			.map_err(ErrorInner::Reconfigure)?;
+		let config = reply.get_document("config").cloned().unwrap_or_default();
+		Ok(config)
+	}
+
+	/// Apply a new replica set configuration with an incremented version number.
+	async fn reconfigure(&self, mut config: Document) -> Result<(), Error> {
+		let version = config.get_i32("version").unwrap_or(1);
+		config.insert("version", version + 1);
+		self.client
+			.database("admin")
+			.run_command(doc! { "replSetReconfig": config }, None)
+			.await
+			.map_err(ErrorInner::Reconfigure)?;
+		Ok(())
+	}
+
+	/// Set the feature compatibility version of the replica set, like `"6.0"`.
+	///
+	/// This waits until the upgrade or downgrade is complete.
+	/// It can be used to test compatibility behavior of a newer binary that runs with the feature set of an older version.
+	pub async fn set_fcv(&self, version: &str) -> Result<(), Error> {
+		set_feature_compatibility_version(&self.client, version).await
+	}
+
+	/// Get the feature compatibility version of the replica set.
+	pub async fn fcv(&self) -> mongodb::error::Result<String> {
+		feature_compatibility_version(&self.client).await
+	}
+
+	/// Enable or disable clean-up of the temporary directory when this object is dropped.
+	pub fn set_clean_on_drop(&mut self, clean_on_drop: bool) {
+		self.tempdir.set_clean_on_drop(clean_on_drop);
+	}
+
+	/// Kill all members and remove the temporary state directory on the filesystem.
+	///
+	/// Note that the members will also be killed when this object is dropped,
+	/// and unless disabled, the temporary state directory will be removed by the [`Drop`] implementation too.
+	///
+	/// This function ignores the value of `clean_on_drop`.
+	/// It also allows for better error handling compared to just dropping the object.
+	#[cfg_attr(feature = "tracing", tracing::instrument(target = "temp_mongo::cleanup", skip_all, fields(name = %self.name)))]
+	pub async fn kill_and_clean(mut self) -> Result<(), Error> {
+		self.kill_members().await?;
+		sleep(Duration::from_millis(50)).await;
+
+		let path = self.tempdir.path().to_owned();
+		event!(debug, target: "temp_mongo::cleanup", "removing temporary directory {}", path.display());
+		self.tempdir
+			.close()
+			.map_err(|e| ErrorInner::CleanDir(path, e))?;
+		Ok(())
+	}
+
+	/// Kill all members, but leave the temporary state directory on the filesystem.
+	///
+	/// Note that the members will also be killed when this object is dropped.
+	///
+	/// This function ignores the value of `clean_on_drop`.
+	/// It also allows for better error handling compared to just dropping the object.
+	pub async fn kill_no_clean(mut self) -> Result<(), Error> {
+		self.kill_members().await?;
+		let _path = self.tempdir.into_path();
+		Ok(())
+	}
+
+	/// Shut down all clients and kill all member processes.
+	async fn kill_members(&mut self) -> Result<(), Error> {
+		self.client.clone().shutdown_immediate().await;
+		for member in &mut self.members {
+			member.client.clone().shutdown_immediate().await;
+			member.server.kill().map_err(ErrorInner::KillServer)?;
+			member.server.wait().map_err(ErrorInner::KillServer)?;
+		}
+		Ok(())
+	}
+
+	/// Create a temporary directory, spawn all members and initiate the replica set.
+	#[cfg_attr(feature = "tracing", tracing::instrument(name = "spawn_replica_set", target = "temp_mongo::spawn", skip_all, fields(name = %builder.name, members = builder.members)))]
+	async fn from_builder(builder: &TempMongoReplicaSetBuilder) -> Result<Self, Error> {
+		builder.validate()?;
+		let tempdir = builder.make_temp_dir().map_err(ErrorInner::MakeTempDir)?;
+
+		let keyfile = match (&builder.credentials, &builder.keyfile) {
+			(Some(_), Some(keyfile)) => Some(keyfile.clone()),
+			(Some(_), None) => {
+				let path = tempdir.path().join("keyfile");
+				generate_keyfile(&path).map_err(|e| ErrorInner::WriteKeyfile(path.clone(), e))?;
+				Some(path)
+			},
+			(None, _) => None,
+		};
+
+		let mut members = Vec::with_capacity(builder.members + 1);
+		for index in 0..builder.members {
+			let options = builder.member_options.get(&index).cloned().unwrap_or_default();
+			members.push(Member::spawn(builder, tempdir.path(), index as i32, false, options, keyfile.as_deref())?);
+		}
+		if builder.arbiter {
+			members.push(Member::spawn(builder, tempdir.path(), builder.members as i32, true, MemberOptions::new(), keyfile.as_deref())?);
+		}
+		let next_id = members.len() as i32;
+
+		// The members are spawned first and only then awaited, so they start in parallel.
+		for member in &mut members {
+			member.await_listening(builder).await?;
+		}
+
+		let delay_field = secondary_delay_field(&members).await?;
+		let mut config = doc! {
+			"_id": &builder.name,
+			"members": members.iter().map(|member| member.config(delay_field)).collect::<Vec<_>>(),
+		};
+		if builder.cluster_role == ClusterRole::ConfigServer {
+			config.insert("configsvr", true);
+		}
+		let mut settings = Document::new();
+		if let Some(interval) = builder.heartbeat_interval {
+			settings.insert("heartbeatIntervalMillis", interval.as_millis() as i64);
+		}
+		if let Some(timeout) = builder.election_timeout {
+			settings.insert("electionTimeoutMillis", timeout.as_millis() as i64);
+		}
+		if !settings.is_empty() {
+			config.insert("settings", settings);
+		}
+
+		// Bootstrap the replica set with unauthenticated clients.
+		// When authentication is enabled, this relies on the localhost exception until the first user is created.
+		let bootstrap = members
+			.iter()
+			.map(|member| direct_client(member.port, None))
+			.collect::<Result<Vec<_>, _>>()?;
+
+		let first = bootstrap.first().ok_or(ErrorInner::NoMembers)?;
+		first
+			.database("admin")
+			.run_command(doc! { "replSetInitiate": config }, None)
+			.await
+			.map_err(|e| with_exited_members_output(ErrorInner::InitiateReplicaSet(e).into(), &mut members))?;
+
+		let primary = Self::wait_for_primary(&bootstrap, builder.timeout)
+			.await
+			.map_err(|e| with_exited_members_output(e, &mut members))?;
+
+		if let Some(credentials) = &builder.credentials {
+			bootstrap[primary]
+				.database("admin")
+				.run_command(doc! {
+					"createUser": &credentials.username,
+					"pwd": credentials.password.expose(),
+					"roles": [{ "role": "root", "db": "admin" }],
+				}, None)
+				.await
+				.map_err(|e| ErrorInner::CreateUser(credentials.username.clone(), e))?;
+		}
+
+		let data_bearing: Vec<_> = bootstrap
+			.iter()
+			.zip(&members)
+			.filter(|(_, member)| !member.arbiter)
+			.map(|(client, _)| client)
+			.collect();
+		wait_for_ready(&data_bearing, builder.timeout)
+			.await
+			.map_err(|e| with_exited_members_output(e, &mut members))?;
+
+		for client in bootstrap {
+			client.shutdown_immediate().await;
+		}
+
+		let client = replica_set_client(&builder.name, &members, builder.credentials.as_ref()).await?;
+
+		Ok(Self {
+			tempdir,
+			name: builder.name.clone(),
+			credentials: builder.credentials.clone(),
+			members,
+			client,
+			builder: builder.clone(),
+			keyfile,
+			next_id,
+		})
+	}
+
+	/// Wait until one of the members reports itself as writable primary.
+	///
+	/// Returns the index of the primary.
+	async fn wait_for_primary(clients: &[mongodb::Client], timeout: Duration) -> Result<usize, Error> {
+		let start = Instant::now();
+		loop {
+			if let Some(index) = find_primary(clients.iter()).await {
+				event!(debug, target: "temp_mongo::spawn", "member {index} became primary after {:?}", start.elapsed());
+				return Ok(index);
+			}
+			event!(trace, target: "temp_mongo::spawn", "waiting for a primary");
+			if start.elapsed() > timeout {
+				return Err(ErrorInner::ReplicaSetTimeout(timeout).into());
+			}
+			sleep(Duration::from_millis(100)).await;
+		}
+	}
+}
+
+impl Member {
+	/// Spawn the `mongod` process for a member and connect to it directly.
+	fn spawn(
+		builder: &TempMongoReplicaSetBuilder,
+		directory: &Path,
+		id: i32,
+		arbiter: bool,
+		options: MemberOptions,
+		keyfile: Option<&Path>,
+	) -> Result<Self, Error> {
+		let member_dir = directory.join(format!("member-{id}"));
+		let db_dir = member_dir.join("db");
+		let log_path = member_dir.join("mongod.log");
+		let output_path = member_dir.join("mongod.out");
+		std::fs::create_dir_all(&db_dir).map_err(|e| ErrorInner::MakeDbDir(db_dir.clone(), e))?;
+
+		let port = free_port()?;
+
+		let mut args = builder.bind_ip.args("127.0.0.1", true);
+		args.extend([
+			"--port".into(),
+			port.to_string().into(),
+			"--dbpath".into(),
+			db_dir.into(),
+			"--logpath".into(),
+			log_path.clone().into(),
+			"--replSet".into(),
+			builder.name.clone().into(),
+		]);
+		// The default socket that mongod derives from the port is moved into the directory of the member,
+		// so it is removed with the temporary directory and can not collide with other instances.
+		#[cfg(unix)]
+		{
+			args.extend(["--unixSocketPrefix".into(), member_dir.into()]);
+		}
+		if let Some(config) = &builder.config {
+			args.extend(["--config".into(), config.resolve(directory)?.into()]);
+		}
+		let oplog_size_mb = builder.oplog_size_mb.or(builder.fast_startup.then_some(FAST_OPLOG_SIZE_MB));
+		if let Some(size) = oplog_size_mb {
+			args.extend(["--oplogSize".into(), size.to_string().into()]);
+		}
+		if builder.fast_startup {
+			args.extend(fast_startup_args(builder.get_command(), true, &builder.parameters));
+		}
+		if let Some(size) = builder.wired_tiger_cache_size_gb {
+			args.extend(["--wiredTigerCacheSizeGB".into(), size.to_string().into()]);
+		}
+		if let Some(engine) = &builder.storage_engine {
+			args.extend(["--storageEngine".into(), engine.into()]);
+		}
+		args.extend(verbosity_args(builder.quiet, builder.verbosity));
+		if builder.no_scripting {
+			args.push("--noscripting".into());
+		}
+		args.extend(set_parameter_args(&builder.parameters));
+		match keyfile {
+			Some(keyfile) => args.extend(["--keyFile".into(), keyfile.into()]),
+			None => args.push("--noauth".into()),
+		}
+		match builder.cluster_role {
+			ClusterRole::None => (),
+			ClusterRole::ConfigServer => args.push("--configsvr".into()),
+			ClusterRole::ShardServer => args.push("--shardsvr".into()),
+		}
+
+		let command = builder.get_command().to_owned();
+		let server = spawn_server(&command, &args, &output_path, &builder.resource_limits)?;
+		let client = direct_client(port, member_credentials(builder, arbiter))?;
+
+		Ok(Self {
+			id,
+			port,
+			arbiter,
+			options,
+			log_path,
+			output_path,
+			command,
+			args,
+			server,
+			client,
+		})
+	}
+
+	/// Wait until the member is listening for connections.
+	///
+	/// If the port of the member was taken by another process before the member could bind to it,
+	/// the member is restarted on a new port, up to the configured number of retries.
+	async fn await_listening(&mut self, builder: &TempMongoReplicaSetBuilder) -> Result<(), Error> {
+		let mut retries_left = builder.port_retries;
+		while retries_left > 0 && bind_failed(&mut self.server, &self.log_path).await {
+			retries_left -= 1;
+			let port = free_port()?;
+			if let Some(index) = self.args.iter().position(|arg| arg == "--port") {
+				self.args[index + 1] = port.to_string().into();
+			}
+			// Remove the old log, so it is not mistaken for the log of the next attempt.
+			std::fs::remove_file(&self.log_path).ok();
+			self.server = spawn_server(&self.command, &self.args, &self.output_path, &builder.resource_limits)?;
+			self.client = direct_client(port, member_credentials(builder, self.arbiter))?;
+			self.port = port;
+		}
+		Ok(())
+	}
+
+	/// Get the replica set configuration document for the member.
+	///
+	/// The replication delay is set with `delay_field`, see [`secondary_delay_field()`].
+	fn config(&self, delay_field: &str) -> Document {
+		let mut config = doc! {
+			"_id": self.id,
+			"host": host(self.port),
+		};
+		if self.arbiter {
+			config.insert("arbiterOnly", true);
+		}
+		if let Some(priority) = self.options.effective_priority() {
+			config.insert("priority", priority);
+		}
+		if self.options.hidden {
+			config.insert("hidden", true);
+		}
+		if let Some(delay) = self.options.secondary_delay {
+			config.insert(delay_field, delay.as_secs() as i64);
+		}
+		if !self.options.tags.is_empty() {
+			let tags: Document = self.options.tags
+				.iter()
+				.map(|(key, value)| (key.clone(), value.into()))
+				.collect();
+			config.insert("tags", tags);
+		}
+		config
+	}
+}
+
+/// Attach the exit status of the first member that has exited and the last output of all exited members to an error.
+fn with_exited_members_output(error: Error, members: &mut [Member]) -> Error {
+	let mut exit_status = None;
+	let mut output = String::new();
+	for member in members {
+		if let Ok(Some(status)) = member.server.try_wait() {
+			exit_status.get_or_insert(status);
+			output.push_str(&server_output_tail(&member.output_path, &member.log_path));
+		}
+	}
+	error.with_server_output(output, exit_status)
+}
+
+/// Wait until one client is connected to a writable primary and all others to a secondary.
+async fn wait_for_ready(clients: &[&mongodb::Client], timeout: Duration) -> Result<(), Error> {
+	let start = Instant::now();
+	loop {
+		if is_ready(clients).await {
+			event!(debug, target: "temp_mongo::spawn", "replica set became ready after {:?}", start.elapsed());
+			return Ok(());
+		}
+		event!(trace, target: "temp_mongo::spawn", "waiting for a primary and secondaries");
+		if start.elapsed() > timeout {
+			return Err(ErrorInner::ReplicaSetTimeout(timeout).into());
+		}
+		sleep(Duration::from_millis(100)).await;
+	}
+}
+
+/// Get the name of the member configuration field for the replication delay, for the version of the members.
+///
+/// MongoDB 5.0 renamed `slaveDelay` to `secondaryDelaySecs`.
+/// The version is only checked if one of the members is delayed.
+async fn secondary_delay_field(members: &[Member]) -> Result<&'static str, Error> {
+	let Some(member) = members.iter().find(|member| member.options.secondary_delay.is_some()) else {
+		return Ok("secondaryDelaySecs");
+	};
+	let build_info = member.client
+		.database("admin")
+		.run_command(doc! { "buildInfo": 1 }, None)
+		.await
+		.map_err(ErrorInner::BuildInfo)?;
+	let major = match build_info.get_array("versionArray").ok().and_then(|version| version.first()) {
+		Some(Bson::Int32(major)) => i64::from(*major),
+		Some(Bson::Int64(major)) => *major,
+		_ => 0,
+	};
+	match major {
+		0..=4 => Ok("slaveDelay"),
+		_ => Ok("secondaryDelaySecs"),
+	}
+}
+
+/// Check if one client is connected to a writable primary and all others to a secondary.
+async fn is_ready(clients: &[&mongodb::Client]) -> bool {
+	let mut primaries = 0;
+	for client in clients {
+		let hello = client
+			.database("admin")
+			.run_command(doc! { "hello": 1 }, None)
+			.await;
+		let Ok(hello) = hello else {
+			return false;
+		};
+		if hello.get_bool("isWritablePrimary").unwrap_or(false) {
+			primaries += 1;
+		} else if !hello.get_bool("secondary").unwrap_or(false) {
+			return false;
+		}
+	}
+	primaries == 1
+}
+
+/// Find the first client that is connected to a writable primary.
+///
+/// Returns the index of the client.
+async fn find_primary<'a>(clients: impl Iterator<Item = &'a mongodb::Client>) -> Option<usize> {
+	for (index, client) in clients.enumerate() {
+		let hello = client
+			.database("admin")
+			.run_command(doc! { "hello": 1 }, None)
+			.await;
+		if let Ok(hello) = hello {
+			if hello.get_bool("isWritablePrimary").unwrap_or(false) {
+				return Some(index);
+			}
+		}
+	}
+	None
+}
+
+/// Create a replica set aware client for all members and check that it can connect.
+async fn replica_set_client(name: &str, members: &[Member], credentials: Option<&Credentials>) -> Result<mongodb::Client, Error> {
+	let hosts: Vec<_> = members
+		.iter()
+		.map(|member| ServerAddress::Tcp {
+			host: "127.0.0.1".into(),
+			port: Some(member.port),
+		})
+		.collect();
+	let client_options = ClientOptions::builder()
+		.hosts(hosts)
+		.repl_set_name(name.to_owned())
+		.credential(credentials.map(Credentials::to_credential))
+		.connect_timeout(Duration::from_millis(100))
+		.build();
+	let uri = members.iter().map(|member| host(member.port)).collect::<Vec<_>>().join(",");
+	let client = mongodb::Client::with_options(client_options)
+		.map_err(|e| ErrorInner::Connect(uri.clone(), e))?;
+	client
+		.list_databases(None, None)
+		.await
+		.map_err(|e| ErrorInner::Connect(uri, e))?;
+	Ok(client)
+}
+
+/// Get the credentials for the client of a member.
+///
+/// Arbiters do not replicate users, so they can not authenticate clients.
+fn member_credentials(builder: &TempMongoReplicaSetBuilder, arbiter: bool) -> Option<&Credentials> {
+	builder.credentials.as_ref().filter(|_| !arbiter)
+}
+
+/// Create a client that is directly connected to a single member.
+fn direct_client(port: u16, credentials: Option<&Credentials>) -> Result<mongodb::Client, Error> {
+	let client_options = ClientOptions::builder()
+		.hosts(vec![ServerAddress::Tcp {
+			host: "127.0.0.1".into(),
+			port: Some(port),
+		}])
+		.credential(credentials.map(Credentials::to_credential))
+		.connect_timeout(Duration::from_millis(100))
+		.direct_connection(true)
+		.build();
+	let client = mongodb::Client::with_options(client_options)
+		.map_err(|e| ErrorInner::Connect(host(port), e))?;
+	Ok(client)
+}
+
+/// Get the `host:port` address of a member listening on the loopback adapter.
+fn host(port: u16) -> String {
+	format!("127.0.0.1:{port}")
+}
+
+/// Options for a single data bearing member of a [`TempMongoReplicaSet`].
+#[derive(Debug, Clone, Default)]
+pub struct MemberOptions {
+	/// The replica set tags of the member, used for tagged read preferences and write concerns.
+	pub tags: BTreeMap<String, String>,
+
+	/// Hide the member from clients, for example for an analytics replica.
+	///
+	/// Hidden members always get priority 0.
+	pub hidden: bool,
+
+	/// The election priority of the member.
+	///
+	/// Uses the server default if set to `None`, or 0 for hidden and delayed members.
+	pub priority: Option<f64>,
+
+	/// Delay replication to this member, for example to test restoring from a delayed secondary.
+	///
+	/// Delayed members always get priority 0.
+	pub secondary_delay: Option<Duration>,
+}
+
+impl MemberOptions {
+	/// Create new options for a member without tags.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Add a replica set tag to the member, for example `dc: "east"`.
+	pub fn tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+		self.tags.insert(key.into(), value.into());
+		self
+	}
+
+	/// Hide the member from clients.
+	pub fn hidden(mut self, hidden: bool) -> Self {
+		self.hidden = hidden;
+		self
+	}
+
+	/// Set the election priority of the member.
+	///
+	/// Use a priority of 0 to prevent the member from becoming primary.
+	pub fn priority(mut self, priority: f64) -> Self {
+		self.priority = Some(priority);
+		self
+	}
+
+	/// Delay replication to the member by the given duration, rounded down to whole seconds.
+	///
+	/// The delay is configured as `secondaryDelaySecs`, or as `slaveDelay` for servers older than MongoDB 5.0.
+	pub fn secondary_delay(mut self, delay: Duration) -> Self {
+		self.secondary_delay = Some(delay);
+		self
+	}
+
+	/// Get the election priority to use in the replica set configuration, if any.
+	fn effective_priority(&self) -> Option<f64> {
+		if self.hidden || self.secondary_delay.is_some() {
+			Some(0.0)
+		} else {
+			self.priority
+		}
+	}
+}
+
+/// The role of a replica set in a sharded cluster.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ClusterRole {
+	/// The replica set is not part of a sharded cluster.
+	None,
+
+	/// The replica set holds the metadata of a sharded cluster.
+	ConfigServer,
+
+	/// The replica set is a shard of a sharded cluster.
+	ShardServer,
+}
+
+/// Builder for customizing your [`TempMongoReplicaSet`] object.
+///
+/// After configuring the desired options, run [`TempMongoReplicaSetBuilder::spawn()`].
+#[derive(Debug, Clone)]
+pub struct TempMongoReplicaSetBuilder {
+	/// The parent directory for the temporary directory.
+	///
+	/// Use the system default if set to `None`.
+	parent_directory: Option<PathBuf>,
+
+	/// Clean up the temprorary directory when the [`TempMongoReplicaSet`] object is dropped.
+	clean_on_drop: bool,
+
+	/// The mongdb command to execute.
+	command: Option<OsString>,
+
+	/// The name of the replica set.
+	name: String,
+
+	/// The number of data bearing members of the replica set.
+	members: usize,
+
+	/// Add an arbiter to the replica set.
+	arbiter: bool,
+
+	/// The options for the data bearing members, by index.
+	member_options: BTreeMap<usize, MemberOptions>,
+
+	/// The maximum time to wait for a primary to be elected.
+	timeout: Duration,
+
+	/// The size of the oplog of each member in megabytes.
+	///
+	/// Use the `mongod` default if set to `None`.
+	oplog_size_mb: Option<u64>,
+
+	/// The interval between heartbeats of the members.
+	heartbeat_interval: Option<Duration>,
+
+	/// The time without a reachable primary after which an election is called.
+	election_timeout: Option<Duration>,
+
+	/// The root user to create, which also enables authentication.
+	credentials: Option<Credentials>,
+
+	/// The keyfile the members authenticate to each other with.
+	///
+	/// Generate a keyfile in the temporary directory if set to `None`.
+	keyfile: Option<PathBuf>,
+
+	/// The role of the replica set in a sharded cluster.
+	cluster_role: ClusterRole,
+
+	/// The configuration file to pass to each member.
+	config: Option<ConfigSource>,
+
+	/// Pass extra arguments to each member that make it start and run faster.
+	fast_startup: bool,
+
+	/// The maximum size of the WiredTiger cache of each member in gigabytes.
+	///
+	/// Use the `mongod` default if set to `None`.
+	wired_tiger_cache_size_gb: Option<f64>,
+
+	/// The storage engine of each member.
+	///
+	/// Use the `mongod` default if set to `None`.
+	storage_engine: Option<String>,
+
+	/// Suppress most log output of each member.
+	quiet: bool,
+
+	/// The log verbosity level of each member, from 0 to 5.
+	verbosity: u8,
+
+	/// Disable server-side JavaScript execution.
+	no_scripting: bool,
+
+	/// The server parameters to set on each member with `--setParameter`.
+	parameters: BTreeMap<String, String>,
+
+	/// The resource limits for each member.
+	resource_limits: ResourceLimits,
+
+	/// The number of times to restart a member on a new port if its port was taken.
+	port_retries: u32,
+
+	/// The network interfaces the members listen on.
+	bind_ip: BindIp,
+}
+
+impl TempMongoReplicaSetBuilder {
+	/// Create a new builder for a replica set with three members.
+	pub fn new() -> Self {
+		Self {
+			parent_directory: None,
+			clean_on_drop: true,
+			command: std::env::var_os("TEMP_MONGO_MONGOD").filter(|command| !command.is_empty()),
+			name: "rs0".into(),
+			members: 3,
+			arbiter: false,
+			member_options: BTreeMap::new(),
+			timeout: Duration::from_secs(30),
+			oplog_size_mb: None,
+			heartbeat_interval: None,
+			election_timeout: None,
+			credentials: default_credentials(),
+			keyfile: None,
+			cluster_role: ClusterRole::None,
+			config: None,
+			fast_startup: false,
+			wired_tiger_cache_size_gb: None,
+			storage_engine: None,
+			quiet: false,
+			verbosity: 0,
+			no_scripting: false,
+			parameters: BTreeMap::new(),
+			resource_limits: ResourceLimits::new(),
+			port_retries: DEFAULT_PORT_RETRIES,
+			bind_ip: BindIp::Loopback,
+		}
+	}
+
+	/// Spawn the members, initiate the replica set and connect to it.
+	pub async fn spawn(&self) -> Result<TempMongoReplicaSet, Error> {
+		TempMongoReplicaSet::from_builder(self).await
+	}
+
+	/// Set the number of data bearing members of the replica set.
+	///
+	/// At least one data bearing member is required, even with an arbiter.
+	pub fn members(mut self, members: usize) -> Self {
+		self.members = members;
+		self
+	}
+
+	/// Set the options for one of the data bearing members, such as replica set tags.
+	///
+	/// Members without explicit options use [`MemberOptions::default()`].
+	/// Spawning the replica set fails if `index` is not below the number of data bearing members.
+	pub fn member_options(mut self, index: usize, options: MemberOptions) -> Self {
+		self.member_options.insert(index, options);
+		self
+	}
+
+	/// Add an arbiter to the replica set, in addition to the data bearing members.
+	///
+	/// The arbiter is always the last member of the replica set.
+	/// For example, use two members and an arbiter to test elections in a primary-secondary-arbiter topology.
+	pub fn arbiter(mut self, arbiter: bool) -> Self {
+		self.arbiter = arbiter;
+		self
+	}
+
+	/// Set the name of the replica set.
+	pub fn name(mut self, name: impl Into<String>) -> Self {
+		self.name = name.into();
+		self
+	}
+
+	/// Enable or disable cleaning of the temporary state directory when the [`TempMongoReplicaSet`] object is dropped.
+	///
+	/// This can also be changed after creation with [`TempMongoReplicaSet::set_clean_on_drop()`].
+	pub fn clean_on_drop(mut self, clean_on_drop: bool) -> Self {
+		self.clean_on_drop = clean_on_drop;
+		self
+	}
+
+	/// Overwrite the `mongod` command to run.
+	///
+	/// Can be used to run a `mongod` binary from an alternative location.
+	/// Defaults to the value of the `TEMP_MONGO_MONGOD` environment variable if it is set, or `mongod` otherwise.
+	pub fn mongod_command(mut self, command: impl Into<OsString>) -> Self {
+		self.command = Some(command.into());
+		self
+	}
+
+	/// Set the maximum time to wait for the replica set to become ready.
+	///
+	/// The replica set is ready when a primary has been elected and all other data bearing members are secondaries.
+	pub fn timeout(mut self, timeout: Duration) -> Self {
+		self.timeout = timeout;
+		self
+	}
+
+	/// Set the size of the oplog of each member in megabytes.
+	///
+	/// A small oplog makes it practical to test oplog rollover in short-lived tests.
+	pub fn oplog_size_mb(mut self, size: u64) -> Self {
+		self.oplog_size_mb = Some(size);
+		self
+	}
+
+	/// Set the interval between heartbeats of the members.
+	///
+	/// This sets `settings.heartbeatIntervalMillis` in the replica set configuration.
+	pub fn heartbeat_interval(mut self, interval: Duration) -> Self {
+		self.heartbeat_interval = Some(interval);
+		self
+	}
+
+	/// Set the time without a reachable primary after which the secondaries call an election.
+	///
+	/// This sets `settings.electionTimeoutMillis` in the replica set configuration.
+	/// A short election timeout speeds up failover tests.
+	pub fn election_timeout(mut self, timeout: Duration) -> Self {
+		self.election_timeout = Some(timeout);
+		self
+	}
+
+	/// Enable or disable the fast startup profile.
+	///
+	/// The profile disables periodic checkpoints and diagnostic data collection,
+	/// and uses a small oplog unless the size is set with [`Self::oplog_size_mb()`].
+	/// This makes the members start and run noticeably faster, at the cost of durability that tests do not need.
+	/// Parameters set with [`Self::set_parameter()`], like `diagnosticDataCollectionEnabled`, take precedence over the profile.
+	pub fn fast_startup(mut self, fast_startup: bool) -> Self {
+		self.fast_startup = fast_startup;
+		self
+	}
+
+	/// Set the maximum size of the WiredTiger cache of each member in gigabytes.
+	///
+	/// By default, `mongod` uses half of the system memory minus one gigabyte,
+	/// which is wasteful when many temporary instances run in parallel and can cause them to be killed for running out of memory.
+	/// The minimum is 0.25 gigabytes.
+	pub fn wired_tiger_cache_size_gb(mut self, size: f64) -> Self {
+		self.wired_tiger_cache_size_gb = Some(size);
+		self
+	}
+
+	/// Set the storage engine of each member, like `inMemory` or `ephemeralForTest`.
+	///
+	/// A purely in-memory storage engine makes temporary instances faster,
+	/// but availability depends on the `mongod` build:
+	/// `inMemory` is only available in MongoDB Enterprise and Percona Server,
+	/// and `ephemeralForTest` was removed in MongoDB 7.0.
+	pub fn storage_engine(mut self, engine: impl Into<String>) -> Self {
+		self.storage_engine = Some(engine.into());
+		self
+	}
+
+	/// Suppress most log output of each member, by passing `--quiet`.
+	///
+	/// This keeps the log files small when many instances run in a test suite.
+	pub fn quiet(mut self, quiet: bool) -> Self {
+		self.quiet = quiet;
+		self
+	}
+
+	/// Set the log verbosity level of each member, from 0 to 5.
+	///
+	/// A level above 0 passes `-v` flags, which is useful for debugging the server itself.
+	/// Levels above 5 are treated as 5.
+	pub fn verbosity(mut self, level: u8) -> Self {
+		self.verbosity = level;
+		self
+	}
+
+	/// Disable server-side JavaScript execution of each member, by passing `--noscripting`.
+	///
+	/// Many production deployments use this hardened configuration.
+	/// Operations that run JavaScript on the server, like `$where`, `$function` and `$accumulator`, fail with this option.
+	pub fn no_scripting(mut self, no_scripting: bool) -> Self {
+		self.no_scripting = no_scripting;
+		self
+	}
+
+	/// Set a server parameter of each member, passed as `--setParameter <key>=<value>`.
+	///
+	/// Can be called multiple times to set multiple parameters, like `enableTestCommands=1` or `ttlMonitorSleepSecs=1`.
+	/// Setting the same parameter again replaces the previous value.
+	pub fn set_parameter(mut self, key: impl Into<String>, value: impl ToString) -> Self {
+		self.parameters.insert(key.into(), value.to_string());
+		self
+	}
+
+	/// Set resource limits for each member.
+	///
+	/// Resource limits are only supported on Unix, they are ignored on other platforms.
+	pub fn resource_limits(mut self, limits: ResourceLimits) -> Self {
+		self.resource_limits = limits;
+		self
+	}
+
+	/// Set the number of times to restart a member on a new port if its port was taken by another process.
+	///
+	/// A free port is selected before each member is started, so another process may bind to it first.
+	/// Defaults to 3.
+	pub fn port_retries(mut self, retries: u32) -> Self {
+		self.port_retries = retries;
+		self
+	}
+
+	/// Set the network interfaces the members listen on.
+	///
+	/// The members address each other on the loopback adapter,
+	/// so `127.0.0.1` is always added to [`BindIp::Addresses`].
+	/// Defaults to [`BindIp::Loopback`].
+	pub fn bind_ip(mut self, bind_ip: BindIp) -> Self {
+		self.bind_ip = bind_ip;
+		self
+	}
+
+	/// Pass an existing configuration file to each member.
+	///
+	/// Options that are passed on the command line, like the data directory, the port and the replica set name,
+	/// take precedence over the configuration file.
+	/// This replaces any configuration set with [`Self::config()`].
+	pub fn config_file(mut self, path: impl Into<PathBuf>) -> Self {
+		self.config = Some(ConfigSource::File(path.into()));
+		self
+	}
+
+	/// Pass a configuration to each member, written to a file in the temporary directory.
+	///
+	/// Options that are passed on the command line, like the data directory, the port and the replica set name,
+	/// take precedence over the configuration.
+	/// This replaces any configuration file set with [`Self::config_file()`].
+	pub fn config(mut self, config: MongodConfig) -> Self {
+		self.config = Some(ConfigSource::Inline(config));
+		self
+	}
+
+	/// Enable authentication and create a root user with the given credentials.
+	///
+	/// The members authenticate to each other with a keyfile that is generated in the temporary state directory.
+	/// All clients of the replica set authenticate as the root user.
+	///
+	/// With the `auth-by-default` feature, authentication is enabled with random credentials by default.
+	pub fn auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+		self.credentials = Some(Credentials::new(username, password));
+		self
+	}
+
+	/// Disable authentication, even with the `auth-by-default` feature.
+	pub fn without_auth(mut self) -> Self {
+		self.credentials = None;
+		self
+	}
+
+	/// Set the parent directory for the temporary directory.
+	pub(crate) fn parent_directory(mut self, parent_directory: impl Into<PathBuf>) -> Self {
+		self.parent_directory = Some(parent_directory.into());
+		self
+	}
+
+	/// Use an existing keyfile for the authentication between members, instead of generating one.
+	///
+	/// This lets the replica sets of a sharded cluster share one keyfile.
+	/// The keyfile is only used if authentication is enabled.
+	pub(crate) fn keyfile(mut self, keyfile: impl Into<PathBuf>) -> Self {
+		self.keyfile = Some(keyfile.into());
+		self
+	}
+
+	/// Set the role of the replica set in a sharded cluster.
+	pub(crate) fn cluster_role(mut self, cluster_role: ClusterRole) -> Self {
+		self.cluster_role = cluster_role;
+		self
+	}
+
+	/// Get the command to execute to run MongoDB.
+	pub fn get_command(&self) -> &OsStr {
+		self.command.as_deref().unwrap_or("mongod".as_ref())
+	}
+
+	/// Get the command to execute to run MongDB as a string, for diagnostic purposes.
+	pub fn get_command_string(&self) -> String {
+		self.get_command().to_string_lossy().into()
+	}
+
+	/// Check that the configuration describes a replica set that can be initiated.
+	///
+	/// Without data bearing members, the replica set would be initiated on the arbiter.
+	fn validate(&self) -> Result<(), Error> {
+		if self.members == 0 {
+			return Err(ErrorInner::NoMembers.into());
+		}
+		if let Some((&index, _)) = self.member_options.range(self.members..).next() {
+			return Err(ErrorInner::MemberOptionsIndex(index, self.members).into());
+		}
+		Ok(())
+	}
+
+	/// Create a temporary directory according to the configuration of the builder.
+	fn make_temp_dir(&self) -> std::io::Result<TempDir> {
+		match &self.parent_directory {
+			Some(dir) => TempDir::new_in(dir, self.clean_on_drop),
+			None => TempDir::new(self.clean_on_drop),
+		}
+	}
+}
+
+impl Default for TempMongoReplicaSetBuilder {
+	fn default() -> Self {
+		Self::new()
+	}
+}
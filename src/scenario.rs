@@ -0,0 +1,56 @@
+use crate::{DatabaseGuard, Error, SharedMongo, TempMongo};
+
+/// A temporary database for a single scenario of a BDD suite, like a [`cucumber`](https://crates.io/crates/cucumber) `World`.
+///
+/// Embed this in the world of the suite to give each scenario its own database on the server shared by the whole run,
+/// see [`TempMongo::shared()`].
+/// The server is only started when a step first uses it, so this implements [`Default`] as required by `#[derive(World)]`.
+/// The database of a scenario is removed after the scenario, and the server is removed when the run finishes.
+///
+/// ```no_run
+/// use temp_mongo::ScenarioMongo;
+///
+/// #[derive(Debug, Default)]
+/// struct AnimalWorld {
+///     mongo: ScenarioMongo,
+/// }
+///
+/// async fn given_a_dog(world: &mut AnimalWorld) {
+///     let database = world.mongo.database().await.unwrap();
+///     database.collection("animals").insert_one(mongodb::bson::doc! { "species": "dog" }, None).await.unwrap();
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct ScenarioMongo {
+	/// The handle of the scenario, once a step used it.
+	handle: Option<SharedMongo>,
+}
+
+impl ScenarioMongo {
+	/// Create a scenario database, starting the shared server if needed.
+	///
+	/// Use this as asynchronous initializer of the world, to fail the scenario early if the server can not be started.
+	pub async fn new() -> Result<Self, Error> {
+		let mut scenario = Self::default();
+		scenario.handle().await?;
+		Ok(scenario)
+	}
+
+	/// Get the database of the scenario, starting the shared server if needed.
+	pub async fn database(&mut self) -> Result<&DatabaseGuard, Error> {
+		Ok(self.handle().await?.database())
+	}
+
+	/// Get a client connected to the shared server, starting it if needed.
+	pub async fn client(&mut self) -> Result<&mongodb::Client, Error> {
+		Ok(self.handle().await?.client())
+	}
+
+	/// Get the handle to the shared server, getting a new one on first use.
+	async fn handle(&mut self) -> Result<&SharedMongo, Error> {
+		if self.handle.is_none() {
+			self.handle = Some(TempMongo::shared().await?);
+		}
+		Ok(self.handle.as_ref().expect("handle is set"))
+	}
+}
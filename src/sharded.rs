@@ -0,0 +1,774 @@
+use crate::error::ErrorInner;
+use crate::replica_set::ClusterRole;
+use crate::util::{
+	default_credentials, event, feature_compatibility_version, generate_keyfile,
+	set_feature_compatibility_version, spawn_on_free_port, verbosity_args, Credentials, KillOnDrop,
+	ResourceLimits, TempDir, DEFAULT_PORT_RETRIES,
+};
+use crate::{Error, TempMongoReplicaSet, TempMongoReplicaSetBuilder};
+use futures_util::future::try_join_all;
+use mongodb::bson::{doc, Document};
+use mongodb::options::{ClientOptions, ServerAddress};
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// A temporary MongoDB sharded cluster.
+///
+/// The cluster consists of a config server replica set, one or more shard replica sets and a `mongos` router.
+/// All members are separate processes listening on free TCP ports on the loopback adapter.
+/// All state of the cluster is stored in a single temporary directory.
+/// Unless disabled, the temporary directory is deleted when this object is dropped.
+pub struct TempMongoSharded {
+	config_servers: TempMongoReplicaSet,
+	shards: Vec<TempMongoReplicaSet>,
+	routers: Vec<Mongos>,
+	client: mongodb::Client,
+	credentials: Option<Credentials>,
+
+	/// The temporary state directory.
+	///
+	/// Declared last, so it is only removed after all processes are killed.
+	tempdir: TempDir,
+}
+
+/// A `mongos` router of a [`TempMongoSharded`].
+struct Mongos {
+	port: u16,
+	log_path: PathBuf,
+	server: KillOnDrop,
+	client: mongodb::Client,
+}
+
+impl std::fmt::Debug for TempMongoSharded {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("TempMongoSharded")
+			.field("tempdir", &self.tempdir.path())
+			.field("uri", &self.uri())
+			.field("config_servers", &self.config_servers)
+			.field("shards", &self.shards)
+			.field("router_pids", &self.routers.iter().map(|router| router.server.id()).collect::<Vec<_>>())
+			.finish_non_exhaustive()
+	}
+}
+
+impl TempMongoSharded {
+	/// Spawn a new sharded cluster with `shards` single-member shards.
+	pub async fn new(shards: usize) -> Result<Self, Error> {
+		Self::from_builder(&TempMongoShardedBuilder::new().shards(shards)).await
+	}
+
+	/// Create a builder to customize your [`TempMongoSharded`].
+	///
+	/// After configuring the desired options, run [`TempMongoShardedBuilder::spawn()`].
+	pub fn builder() -> TempMongoShardedBuilder {
+		TempMongoShardedBuilder::new()
+	}
+
+	/// Get the path of the temporary state directory.
+	pub fn directory(&self) -> &Path {
+		self.tempdir.path()
+	}
+
+	/// Get the connection string for all `mongos` routers.
+	///
+	/// With multiple routers, the driver load balances operations over all of them.
+	/// If authentication is enabled, the connection string includes the credentials of the root user.
+	pub fn uri(&self) -> String {
+		let hosts: Vec<_> = self.routers.iter().map(|router| host(router.port)).collect();
+		router_uri(&hosts.join(","), self.credentials.as_ref())
+	}
+
+	/// Get the credentials of the root user, if authentication is enabled.
+	pub fn credentials(&self) -> Option<&Credentials> {
+		self.credentials.as_ref()
+	}
+
+	/// Get a client connected to all `mongos` routers.
+	///
+	/// This returns a client by reference,
+	/// but it can be cloned and sent to other threads or tasks if needed.
+	pub fn client(&self) -> &mongodb::Client {
+		&self.client
+	}
+
+	/// Get the number of `mongos` routers.
+	pub fn router_count(&self) -> usize {
+		self.routers.len()
+	}
+
+	/// Get the connection string for a single `mongos` router.
+	///
+	/// If authentication is enabled, the connection string includes the credentials of the root user.
+	///
+	/// # Panics
+	/// This function panics if `index` is out of bounds.
+	pub fn router_uri(&self, index: usize) -> String {
+		router_uri(&host(self.routers[index].port), self.credentials.as_ref())
+	}
+
+	/// Get a client connected to a single `mongos` router.
+	///
+	/// # Panics
+	/// This function panics if `index` is out of bounds.
+	pub fn router_client(&self, index: usize) -> &mongodb::Client {
+		&self.routers[index].client
+	}
+
+	/// Get the TCP port a `mongos` router is listening on.
+	///
+	/// # Panics
+	/// This function panics if `index` is out of bounds.
+	pub fn router_port(&self, index: usize) -> u16 {
+		self.routers[index].port
+	}
+
+	/// Get the PID of a `mongos` router process.
+	///
+	/// # Panics
+	/// This function panics if `index` is out of bounds.
+	pub fn router_pid(&self, index: usize) -> u32 {
+		self.routers[index].server.id()
+	}
+
+	/// Get the path of the log file of a `mongos` router.
+	///
+	/// # Panics
+	/// This function panics if `index` is out of bounds.
+	pub fn mongos_log_path(&self, index: usize) -> &Path {
+		&self.routers[index].log_path
+	}
+
+	/// Get the config server replica set.
+	pub fn config_servers(&self) -> &TempMongoReplicaSet {
+		&self.config_servers
+	}
+
+	/// Get the replica sets of all shards.
+	pub fn shards(&self) -> &[TempMongoReplicaSet] {
+		&self.shards
+	}
+
+	/// Shard a collection on the given shard key.
+	///
+	/// Sharding must be enabled on the database of the collection, see [`TempMongoShardedBuilder::enable_sharding()`].
+	/// # Arguments
+	/// * `namespace` - The namespace of the collection, in the form `database.collection`
+	/// * `key` - The shard key, for example `doc! { "user_id": "hashed" }`
+	pub async fn shard_collection(&self, namespace: &str, key: Document) -> mongodb::error::Result<()> {
+		self.client
+			.database("admin")
+			.run_command(doc! { "shardCollection": namespace, "key": key }, None)
+			.await?;
+		Ok(())
+	}
+
+	/// Associate a shard with a zone, creating the zone if it does not exist yet.
+	///
+	/// # Panics
+	/// This function panics if `shard` is out of bounds.
+	/// # Arguments
+	/// * `shard` - The index of the shard
+	/// * `zone` - The name of the zone
+	pub async fn add_shard_to_zone(&self, shard: usize, zone: &str) -> mongodb::error::Result<()> {
+		self.client
+			.database("admin")
+			.run_command(doc! { "addShardToZone": self.shards[shard].name(), "zone": zone }, None)
+			.await?;
+		Ok(())
+	}
+
+	/// Remove the association between a shard and a zone.
+	///
+	/// # Panics
+	/// This function panics if `shard` is out of bounds.
+	/// # Arguments
+	/// * `shard` - The index of the shard
+	/// * `zone` - The name of the zone
+	pub async fn remove_shard_from_zone(&self, shard: usize, zone: &str) -> mongodb::error::Result<()> {
+		self.client
+			.database("admin")
+			.run_command(doc! { "removeShardFromZone": self.shards[shard].name(), "zone": zone }, None)
+			.await?;
+		Ok(())
+	}
+
+	/// Assign a range of shard key values of a collection to a zone.
+	///
+	/// Documents with a shard key in the range `[min, max)` are placed on the shards associated with the zone.
+	/// # Arguments
+	/// * `namespace` - The namespace of the collection, in the form `database.collection`
+	/// * `min` - The inclusive lower bound of the range, for example `doc! { "region": "EU" }`
+	/// * `max` - The exclusive upper bound of the range, for example `doc! { "region": "EV" }`
+	/// * `zone` - The name of the zone
+	pub async fn update_zone_key_range(&self, namespace: &str, min: Document, max: Document, zone: &str) -> mongodb::error::Result<()> {
+		self.client
+			.database("admin")
+			.run_command(doc! { "updateZoneKeyRange": namespace, "min": min, "max": max, "zone": zone }, None)
+			.await?;
+		Ok(())
+	}
+
+	/// Remove the zone assignment of a range of shard key values of a collection.
+	/// # Arguments
+	/// * `namespace` - The namespace of the collection, in the form `database.collection`
+	/// * `min` - The inclusive lower bound of the range
+	/// * `max` - The exclusive upper bound of the range
+	pub async fn remove_zone_key_range(&self, namespace: &str, min: Document, max: Document) -> mongodb::error::Result<()> {
+		self.client
+			.database("admin")
+			.run_command(doc! { "updateZoneKeyRange": namespace, "min": min, "max": max, "zone": null }, None)
+			.await?;
+		Ok(())
+	}
+
+	/// Set the feature compatibility version of the cluster, like `"6.0"`.
+	///
+	/// This waits until the upgrade or downgrade is complete.
+	/// It can be used to test compatibility behavior of a newer binary that runs with the feature set of an older version.
+	pub async fn set_fcv(&self, version: &str) -> Result<(), Error> {
+		set_feature_compatibility_version(&self.client, version).await
+	}
+
+	/// Get the feature compatibility version of the cluster.
+	pub async fn fcv(&self) -> mongodb::error::Result<String> {
+		feature_compatibility_version(&self.client).await
+	}
+
+	/// Enable or disable clean-up of the temporary directory when this object is dropped.
+	pub fn set_clean_on_drop(&mut self, clean_on_drop: bool) {
+		self.tempdir.set_clean_on_drop(clean_on_drop);
+		self.config_servers.set_clean_on_drop(clean_on_drop);
+		for shard in &mut self.shards {
+			shard.set_clean_on_drop(clean_on_drop);
+		}
+	}
+
+	/// Kill all processes and remove the temporary state directory on the filesystem.
+	///
+	/// Note that all processes will also be killed when this object is dropped,
+	/// and unless disabled, the temporary state directory will be removed by the [`Drop`] implementation too.
+	///
+	/// This function ignores the value of `clean_on_drop`.
+	/// It also allows for better error handling compared to just dropping the object.
+	#[cfg_attr(feature = "tracing", tracing::instrument(target = "temp_mongo::cleanup", skip_all))]
+	pub async fn kill_and_clean(mut self) -> Result<(), Error> {
+		self.kill_routers().await?;
+		for shard in self.shards {
+			shard.kill_and_clean().await?;
+		}
+		self.config_servers.kill_and_clean().await?;
+
+		let path = self.tempdir.path().to_owned();
+		event!(debug, target: "temp_mongo::cleanup", "removing temporary directory {}", path.display());
+		self.tempdir
+			.close()
+			.map_err(|e| ErrorInner::CleanDir(path, e))?;
+		Ok(())
+	}
+
+	/// Kill all processes, but leave the temporary state directory on the filesystem.
+	///
+	/// Note that all processes will also be killed when this object is dropped.
+	///
+	/// This function ignores the value of `clean_on_drop`.
+	/// It also allows for better error handling compared to just dropping the object.
+	pub async fn kill_no_clean(mut self) -> Result<(), Error> {
+		self.kill_routers().await?;
+		for shard in self.shards {
+			shard.kill_no_clean().await?;
+		}
+		self.config_servers.kill_no_clean().await?;
+		let _path = self.tempdir.into_path();
+		Ok(())
+	}
+
+	/// Shut down all clients and kill all `mongos` processes.
+	async fn kill_routers(&mut self) -> Result<(), Error> {
+		self.client.clone().shutdown_immediate().await;
+		for router in &mut self.routers {
+			router.client.clone().shutdown_immediate().await;
+			router.server.kill().map_err(ErrorInner::KillServer)?;
+			router.server.wait().map_err(ErrorInner::KillServer)?;
+		}
+		Ok(())
+	}
+
+	/// Create a temporary directory, spawn all replica sets and routers, and register the shards.
+	#[cfg_attr(feature = "tracing", tracing::instrument(name = "spawn_sharded", target = "temp_mongo::spawn", skip_all, fields(shards = builder.shards)))]
+	async fn from_builder(builder: &TempMongoShardedBuilder) -> Result<Self, Error> {
+		let tempdir = builder.make_temp_dir().map_err(ErrorInner::MakeTempDir)?;
+
+		// All members and routers of the cluster authenticate to each other with the same keyfile.
+		let keyfile = match &builder.credentials {
+			Some(_) => {
+				let path = tempdir.path().join("keyfile");
+				generate_keyfile(&path).map_err(|e| ErrorInner::WriteKeyfile(path.clone(), e))?;
+				Some(path)
+			},
+			None => None,
+		};
+
+		let config_builder = builder
+			.replica_set_builder(tempdir.path(), "config", builder.config_members, keyfile.as_deref())
+			.cluster_role(ClusterRole::ConfigServer);
+		let shard_builders: Vec<_> = (0..builder.shards)
+			.map(|index| {
+				builder
+					.replica_set_builder(tempdir.path(), &format!("shard{index}"), builder.shard_members, keyfile.as_deref())
+					.cluster_role(ClusterRole::ShardServer)
+			})
+			.collect();
+		let (config_servers, shards) = futures_util::try_join!(
+			config_builder.spawn(),
+			try_join_all(shard_builders.iter().map(TempMongoReplicaSetBuilder::spawn)),
+		)?;
+
+		let mut routers = Vec::with_capacity(builder.routers.max(1));
+		for index in 0..builder.routers.max(1) {
+			routers.push(Mongos::spawn(builder, tempdir.path(), index, &config_servers, keyfile.as_deref()).await?);
+		}
+		let ports: Vec<_> = routers.iter().map(|router| router.port).collect();
+		let client = router_client(&ports, builder.credentials.as_ref())?;
+
+		let admin = client.database("admin");
+		for shard in &shards {
+			let address = format!("{}/{}", shard.name(), shard.hosts());
+			Self::add_shard(&admin, &address)
+				.await
+				.map_err(|e| ErrorInner::AddShard(address.clone(), e))?;
+		}
+		for database in &builder.sharded_databases {
+			admin
+				.run_command(doc! { "enableSharding": database }, None)
+				.await
+				.map_err(|e| ErrorInner::EnableSharding(database.clone(), e))?;
+		}
+
+		Ok(Self {
+			config_servers,
+			shards,
+			routers,
+			client,
+			credentials: builder.credentials.clone(),
+			tempdir,
+		})
+	}
+
+	/// Add a shard to the cluster.
+	///
+	/// Right after startup, `mongos` may not yet have loaded the cluster metadata from the config servers,
+	/// so the command is retried a few times.
+	async fn add_shard(admin: &mongodb::Database, address: &str) -> mongodb::error::Result<()> {
+		let mut attempts = 0;
+		loop {
+			match admin.run_command(doc! { "addShard": address }, None).await {
+				Ok(_) => return Ok(()),
+				Err(e) if attempts >= 50 => return Err(e),
+				Err(_) => attempts += 1,
+			}
+			sleep(Duration::from_millis(100)).await;
+		}
+	}
+}
+
+impl Mongos {
+	/// Spawn a `mongos` process that uses the given config servers.
+	///
+	/// The root user of the cluster is stored on the config servers, so the router does not create it.
+	async fn spawn(builder: &TempMongoShardedBuilder, directory: &Path, index: usize, config_servers: &TempMongoReplicaSet, keyfile: Option<&Path>) -> Result<Self, Error> {
+		let log_path = directory.join(format!("mongos-{index}.log"));
+		let output_path = directory.join(format!("mongos-{index}.out"));
+
+		let make_args = |port: u16| {
+			let mut args: Vec<OsString> = vec![
+				"--bind_ip".into(),
+				"127.0.0.1".into(),
+				"--port".into(),
+				port.to_string().into(),
+				"--logpath".into(),
+				log_path.clone().into(),
+				"--configdb".into(),
+				format!("{}/{}", config_servers.name(), config_servers.hosts()).into(),
+			];
+			// The default socket that mongos derives from the port is moved into the temporary directory,
+			// so it is removed with the directory and can not collide with other instances.
+			#[cfg(unix)]
+			{
+				args.extend(["--unixSocketPrefix".into(), directory.into()]);
+			}
+			if let Some(keyfile) = keyfile {
+				args.extend(["--keyFile".into(), keyfile.into()]);
+			}
+			args.extend(verbosity_args(builder.quiet, builder.verbosity));
+			if builder.no_scripting {
+				args.push("--noscripting".into());
+			}
+			args
+		};
+		let (port, server) = spawn_on_free_port(builder.get_mongos_command(), &output_path, &log_path, &builder.resource_limits, builder.port_retries, make_args).await?;
+		let client = router_client(&[port], builder.credentials.as_ref())?;
+
+		Ok(Self {
+			port,
+			log_path,
+			server,
+			client,
+		})
+	}
+}
+
+/// Create a client that connects to the `mongos` routers listening on the given ports.
+fn router_client(ports: &[u16], credentials: Option<&Credentials>) -> Result<mongodb::Client, Error> {
+	let hosts: Vec<_> = ports
+		.iter()
+		.map(|&port| ServerAddress::Tcp {
+			host: "127.0.0.1".into(),
+			port: Some(port),
+		})
+		.collect();
+	let client_options = ClientOptions::builder()
+		.hosts(hosts)
+		.credential(credentials.map(Credentials::to_credential))
+		.connect_timeout(Duration::from_millis(100))
+		.build();
+	let address = ports.iter().map(|&port| host(port)).collect::<Vec<_>>().join(",");
+	let client = mongodb::Client::with_options(client_options)
+		.map_err(|e| ErrorInner::Connect(address, e))?;
+	Ok(client)
+}
+
+/// Get the connection string for the `mongos` routers at the given comma separated `host:port` addresses.
+fn router_uri(hosts: &str, credentials: Option<&Credentials>) -> String {
+	match credentials {
+		Some(credentials) => format!("mongodb://{}{hosts}/?authSource=admin", credentials.to_user_info()),
+		None => format!("mongodb://{hosts}/"),
+	}
+}
+
+/// Get the `host:port` address of a process listening on the loopback adapter.
+fn host(port: u16) -> String {
+	format!("127.0.0.1:{port}")
+}
+
+/// Builder for customizing your [`TempMongoSharded`] object.
+///
+/// After configuring the desired options, run [`TempMongoShardedBuilder::spawn()`].
+#[derive(Debug)]
+pub struct TempMongoShardedBuilder {
+	/// The parent directory for the temporary directory.
+	///
+	/// Use the system default if set to `None`.
+	parent_directory: Option<PathBuf>,
+
+	/// Clean up the temprorary directory when the [`TempMongoSharded`] object is dropped.
+	clean_on_drop: bool,
+
+	/// The mongod command to execute.
+	mongod_command: Option<OsString>,
+
+	/// The mongos command to execute.
+	mongos_command: Option<OsString>,
+
+	/// The number of shards.
+	shards: usize,
+
+	/// The number of members of each shard replica set.
+	shard_members: usize,
+
+	/// The number of members of the config server replica set.
+	config_members: usize,
+
+	/// The number of `mongos` routers.
+	routers: usize,
+
+	/// The databases to enable sharding on.
+	sharded_databases: Vec<String>,
+
+	/// The maximum time to wait for each replica set to elect a primary.
+	timeout: Duration,
+
+	/// The maximum size of the WiredTiger cache of each `mongod` in gigabytes.
+	///
+	/// Use the `mongod` default if set to `None`.
+	wired_tiger_cache_size_gb: Option<f64>,
+
+	/// The storage engine of each `mongod`.
+	///
+	/// Use the `mongod` default if set to `None`.
+	storage_engine: Option<String>,
+
+	/// Suppress most log output of each process.
+	quiet: bool,
+
+	/// The log verbosity level of each process, from 0 to 5.
+	verbosity: u8,
+
+	/// Disable server-side JavaScript execution.
+	no_scripting: bool,
+
+	/// The server parameters to set on each `mongod` with `--setParameter`.
+	parameters: BTreeMap<String, String>,
+
+	/// The resource limits for each `mongod` and `mongos` process.
+	resource_limits: ResourceLimits,
+
+	/// The number of times to restart a process on a new port if its port was taken.
+	port_retries: u32,
+
+	/// The root user to create, which also enables authentication.
+	credentials: Option<Credentials>,
+}
+
+impl TempMongoShardedBuilder {
+	/// Create a new builder for a cluster with two single-member shards.
+	pub fn new() -> Self {
+		Self {
+			parent_directory: None,
+			clean_on_drop: true,
+			mongod_command: None,
+			mongos_command: std::env::var_os("TEMP_MONGO_MONGOS").filter(|command| !command.is_empty()),
+			shards: 2,
+			shard_members: 1,
+			config_members: 1,
+			routers: 1,
+			sharded_databases: Vec::new(),
+			timeout: Duration::from_secs(30),
+			wired_tiger_cache_size_gb: None,
+			storage_engine: None,
+			quiet: false,
+			verbosity: 0,
+			no_scripting: false,
+			parameters: BTreeMap::new(),
+			resource_limits: ResourceLimits::new(),
+			port_retries: DEFAULT_PORT_RETRIES,
+			credentials: default_credentials(),
+		}
+	}
+
+	/// Spawn the cluster and connect to the `mongos` router.
+	pub async fn spawn(&self) -> Result<TempMongoSharded, Error> {
+		TempMongoSharded::from_builder(self).await
+	}
+
+	/// Set the number of shards.
+	pub fn shards(mut self, shards: usize) -> Self {
+		self.shards = shards;
+		self
+	}
+
+	/// Set the number of members of each shard replica set.
+	pub fn shard_members(mut self, members: usize) -> Self {
+		self.shard_members = members;
+		self
+	}
+
+	/// Set the number of members of the config server replica set.
+	pub fn config_members(mut self, members: usize) -> Self {
+		self.config_members = members;
+		self
+	}
+
+	/// Set the number of `mongos` routers.
+	///
+	/// Each router gets its own port, so router failover and load balanced connection strings can be tested.
+	/// At least one router is always spawned.
+	pub fn routers(mut self, routers: usize) -> Self {
+		self.routers = routers;
+		self
+	}
+
+	/// Enable sharding on a database after the cluster is started.
+	///
+	/// Can be called multiple times to enable sharding on multiple databases.
+	pub fn enable_sharding(mut self, database: impl Into<String>) -> Self {
+		self.sharded_databases.push(database.into());
+		self
+	}
+
+	/// Enable or disable cleaning of the temporary state directory when the [`TempMongoSharded`] object is dropped.
+	///
+	/// This can also be changed after creation with [`TempMongoSharded::set_clean_on_drop()`].
+	pub fn clean_on_drop(mut self, clean_on_drop: bool) -> Self {
+		self.clean_on_drop = clean_on_drop;
+		self
+	}
+
+	/// Overwrite the `mongod` command to run.
+	///
+	/// Can be used to run a `mongod` binary from an alternative location.
+	/// Defaults to the value of the `TEMP_MONGO_MONGOD` environment variable if it is set, or `mongod` otherwise.
+	pub fn mongod_command(mut self, command: impl Into<OsString>) -> Self {
+		self.mongod_command = Some(command.into());
+		self
+	}
+
+	/// Overwrite the `mongos` command to run.
+	///
+	/// Can be used to run a `mongos` binary from an alternative location.
+	/// Defaults to the value of the `TEMP_MONGO_MONGOS` environment variable if it is set, or `mongos` otherwise.
+	pub fn mongos_command(mut self, command: impl Into<OsString>) -> Self {
+		self.mongos_command = Some(command.into());
+		self
+	}
+
+	/// Set the maximum time to wait for each replica set to elect a primary.
+	pub fn timeout(mut self, timeout: Duration) -> Self {
+		self.timeout = timeout;
+		self
+	}
+
+	/// Set the maximum size of the WiredTiger cache of each `mongod` process in gigabytes.
+	///
+	/// By default, `mongod` uses half of the system memory minus one gigabyte,
+	/// which is wasteful when many temporary instances run in parallel and can cause them to be killed for running out of memory.
+	/// The minimum is 0.25 gigabytes.
+	pub fn wired_tiger_cache_size_gb(mut self, size: f64) -> Self {
+		self.wired_tiger_cache_size_gb = Some(size);
+		self
+	}
+
+	/// Set the storage engine of each `mongod` process, like `inMemory` or `ephemeralForTest`.
+	///
+	/// A purely in-memory storage engine makes temporary instances faster,
+	/// but availability depends on the `mongod` build:
+	/// `inMemory` is only available in MongoDB Enterprise and Percona Server,
+	/// and `ephemeralForTest` was removed in MongoDB 7.0.
+	pub fn storage_engine(mut self, engine: impl Into<String>) -> Self {
+		self.storage_engine = Some(engine.into());
+		self
+	}
+
+	/// Suppress most log output of each `mongod` and `mongos` process, by passing `--quiet`.
+	///
+	/// This keeps the log files small when many instances run in a test suite.
+	pub fn quiet(mut self, quiet: bool) -> Self {
+		self.quiet = quiet;
+		self
+	}
+
+	/// Set the log verbosity level of each `mongod` and `mongos` process, from 0 to 5.
+	///
+	/// A level above 0 passes `-v` flags, which is useful for debugging the server itself.
+	/// Levels above 5 are treated as 5.
+	pub fn verbosity(mut self, level: u8) -> Self {
+		self.verbosity = level;
+		self
+	}
+
+	/// Disable server-side JavaScript execution of each `mongod` and `mongos` process, by passing `--noscripting`.
+	///
+	/// Many production deployments use this hardened configuration.
+	/// Operations that run JavaScript on the server, like `$where`, `$function` and `$accumulator`, fail with this option.
+	pub fn no_scripting(mut self, no_scripting: bool) -> Self {
+		self.no_scripting = no_scripting;
+		self
+	}
+
+	/// Set a server parameter of each `mongod`, passed as `--setParameter <key>=<value>`.
+	///
+	/// Can be called multiple times to set multiple parameters, like `enableTestCommands=1` or `ttlMonitorSleepSecs=1`.
+	/// Setting the same parameter again replaces the previous value.
+	/// The parameters are not passed to the `mongos` routers, which support a different set of parameters.
+	pub fn set_parameter(mut self, key: impl Into<String>, value: impl ToString) -> Self {
+		self.parameters.insert(key.into(), value.to_string());
+		self
+	}
+
+	/// Enable authentication and create a root user with the given credentials.
+	///
+	/// All members and routers authenticate to each other with one keyfile that is generated in the temporary state directory.
+	/// The root user is created on the config servers, which store the users of the cluster,
+	/// and on each shard, so the shards can also be accessed directly.
+	/// All clients of the cluster authenticate as the root user.
+	///
+	/// With the `auth-by-default` feature, authentication is enabled with random credentials by default.
+	pub fn auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+		self.credentials = Some(Credentials::new(username, password));
+		self
+	}
+
+	/// Disable authentication, even with the `auth-by-default` feature.
+	pub fn without_auth(mut self) -> Self {
+		self.credentials = None;
+		self
+	}
+
+	/// Set resource limits for each `mongod` and `mongos` process.
+	///
+	/// Resource limits are only supported on Unix, they are ignored on other platforms.
+	pub fn resource_limits(mut self, limits: ResourceLimits) -> Self {
+		self.resource_limits = limits;
+		self
+	}
+
+	/// Set the number of times to restart a `mongod` or `mongos` process on a new port if its port was taken by another process.
+	///
+	/// A free port is selected before each process is started, so another process may bind to it first.
+	/// Defaults to 3.
+	pub fn port_retries(mut self, retries: u32) -> Self {
+		self.port_retries = retries;
+		self
+	}
+
+	/// Get the command to execute to run `mongos`.
+	pub fn get_mongos_command(&self) -> &OsStr {
+		self.mongos_command.as_deref().unwrap_or("mongos".as_ref())
+	}
+
+	/// Get the command to execute to run `mongos` as a string, for diagnostic purposes.
+	pub fn get_mongos_command_string(&self) -> String {
+		self.get_mongos_command().to_string_lossy().into()
+	}
+
+	/// Create a builder for one of the replica sets of the cluster.
+	fn replica_set_builder(&self, directory: &Path, name: &str, members: usize, keyfile: Option<&Path>) -> TempMongoReplicaSetBuilder {
+		let mut builder = TempMongoReplicaSetBuilder::new()
+			.parent_directory(directory)
+			.name(name)
+			.members(members)
+			.clean_on_drop(self.clean_on_drop)
+			.timeout(self.timeout);
+		builder = match (&self.credentials, keyfile) {
+			(Some(credentials), Some(keyfile)) => builder
+				.auth(&credentials.username, credentials.password.expose())
+				.keyfile(keyfile),
+			_ => builder.without_auth(),
+		};
+		if let Some(command) = &self.mongod_command {
+			builder = builder.mongod_command(command);
+		}
+		if let Some(size) = self.wired_tiger_cache_size_gb {
+			builder = builder.wired_tiger_cache_size_gb(size);
+		}
+		if let Some(engine) = &self.storage_engine {
+			builder = builder.storage_engine(engine);
+		}
+		builder = builder
+			.quiet(self.quiet)
+			.verbosity(self.verbosity)
+			.no_scripting(self.no_scripting)
+			.port_retries(self.port_retries)
+			.resource_limits(self.resource_limits.clone());
+		for (key, value) in &self.parameters {
+			builder = builder.set_parameter(key, value);
+		}
+		builder
+	}
+
+	/// Create a temporary directory according to the configuration of the builder.
+	fn make_temp_dir(&self) -> std::io::Result<TempDir> {
+		match &self.parent_directory {
+			Some(dir) => TempDir::new_in(dir, self.clean_on_drop),
+			None => TempDir::new(self.clean_on_drop),
+		}
+	}
+}
+
+impl Default for TempMongoShardedBuilder {
+	fn default() -> Self {
+		Self::new()
+	}
+}
@@ -0,0 +1,172 @@
+use crate::error::ErrorInner;
+use crate::{Error, TempMongo};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tokio::sync::OnceCell;
+
+/// The server shared by all tests in the process, started by the first call to [`TempMongo::shared()`].
+///
+/// The server is never dropped, since statics are not dropped when the process exits.
+/// It is cleaned up by the exit hook instead.
+static SHARED: OnceCell<TempMongo> = OnceCell::const_new();
+
+/// The number of databases handed out so far, to give each handle a unique database.
+static DATABASE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// The databases of dropped guards, which are removed by the next call to [`TempMongo::shared()`].
+static ABANDONED_DATABASES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// A handle to the server shared by all tests in the process, with a database that is unique to this handle.
+///
+/// Get a handle with [`TempMongo::shared()`].
+/// Each handle has its own client, created in the async runtime of the caller,
+/// so handles work in tests that each run their own runtime.
+#[derive(Debug)]
+pub struct SharedMongo {
+	client: mongodb::Client,
+	database: DatabaseGuard,
+}
+
+impl SharedMongo {
+	/// Get the client connected to the shared server.
+	pub fn client(&self) -> &mongodb::Client {
+		&self.client
+	}
+
+	/// Get the database that is unique to this handle.
+	pub fn database(&self) -> &DatabaseGuard {
+		&self.database
+	}
+
+	/// Take the database that is unique to this handle, to drop it explicitly with [`DatabaseGuard::drop_database()`].
+	pub fn into_database(self) -> DatabaseGuard {
+		self.database
+	}
+
+	/// Get a connection string for the shared server, see [`TempMongo::uri()`].
+	pub fn uri(&self) -> String {
+		shared_server().map(TempMongo::uri).unwrap_or_default()
+	}
+}
+
+/// A database that is unique to a [`SharedMongo`] handle, and that is removed when the guard is dropped.
+///
+/// Since dropping a database is asynchronous, a dropped guard only marks the database as abandoned,
+/// and the next call to [`TempMongo::shared()`] removes it.
+/// Use [`Self::drop_database()`] to remove the database immediately instead.
+/// The guard dereferences to the [`mongodb::Database`].
+#[derive(Debug)]
+pub struct DatabaseGuard {
+	database: Option<mongodb::Database>,
+}
+
+impl DatabaseGuard {
+	/// Get the name of the database.
+	pub fn name(&self) -> &str {
+		self.database().name()
+	}
+
+	/// Drop the database now.
+	pub async fn drop_database(mut self) -> Result<(), Error> {
+		let database = self.database.take().expect("database guard is not consumed yet");
+		database
+			.drop(None)
+			.await
+			.map_err(|e| ErrorInner::DropDatabase(database.name().into(), e))?;
+		Ok(())
+	}
+
+	/// Get the guarded database.
+	fn database(&self) -> &mongodb::Database {
+		self.database.as_ref().expect("database guard is not consumed yet")
+	}
+}
+
+impl std::ops::Deref for DatabaseGuard {
+	type Target = mongodb::Database;
+
+	fn deref(&self) -> &Self::Target {
+		self.database()
+	}
+}
+
+impl Drop for DatabaseGuard {
+	fn drop(&mut self) {
+		if let Some(database) = self.database.take() {
+			if let Ok(mut abandoned) = ABANDONED_DATABASES.lock() {
+				abandoned.push(database.name().into());
+			}
+		}
+	}
+}
+
+/// Get a handle to the shared server, starting it if this is the first call.
+pub(crate) async fn shared() -> Result<SharedMongo, Error> {
+	let mongo = SHARED
+		.get_or_try_init(|| async {
+			let mongo = TempMongo::new().await?;
+			exit_hook::register();
+			Ok::<_, Error>(mongo)
+		})
+		.await?;
+
+	// The client of the server itself belongs to the runtime of the first caller, which may be gone by now.
+	let mut options = mongo.client_options().clone();
+	options.command_event_handler = None;
+	options.cmap_event_handler = None;
+	options.sdam_event_handler = None;
+	let client = mongodb::Client::with_options(options).map_err(|e| ErrorInner::Connect(mongo.uri(), e))?;
+
+	let abandoned = ABANDONED_DATABASES.lock().map(|mut abandoned| std::mem::take(&mut *abandoned)).unwrap_or_default();
+	for name in abandoned {
+		// Removing abandoned databases is best effort, the server removes them all when it is cleaned up anyway.
+		client.database(&name).drop(None).await.ok();
+	}
+
+	let number = DATABASE_COUNT.fetch_add(1, Ordering::Relaxed);
+	let database = client.database(&format!("shared_{number}"));
+	Ok(SharedMongo {
+		client,
+		database: DatabaseGuard { database: Some(database) },
+	})
+}
+
+/// Get the shared server, if it was started.
+fn shared_server() -> Option<&'static TempMongo> {
+	SHARED.get()
+}
+
+#[cfg(unix)]
+mod exit_hook {
+	/// Register a hook that kills the shared server and removes its temporary directory when the process exits.
+	pub fn register() {
+		// SAFETY: The hook is a plain function without captured state.
+		unsafe {
+			libc::atexit(clean_up);
+		}
+	}
+
+	/// Kill the shared server and remove its temporary directory.
+	///
+	/// This runs after `main()` returns, so it must not rely on an async runtime.
+	extern "C" fn clean_up() {
+		let Some(mongo) = super::shared_server() else {
+			return;
+		};
+		let pid = mongo.process_id() as libc::pid_t;
+		// SAFETY: The PID belongs to a child of this process that has not been reaped yet, so it can not be reused.
+		unsafe {
+			libc::kill(pid, libc::SIGKILL);
+			libc::waitpid(pid, std::ptr::null_mut(), 0);
+		}
+		std::fs::remove_dir_all(mongo.directory()).ok();
+	}
+}
+
+#[cfg(not(unix))]
+mod exit_hook {
+	/// Do nothing, since the server is already killed with the process on Windows.
+	///
+	/// The temporary directory is left behind.
+	pub fn register() {}
+}
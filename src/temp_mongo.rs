@@ -1,13 +1,33 @@
+use crate::download::default_data_dir;
 use crate::error::ErrorInner;
-use crate::util::{DataSeeder, KillOnDrop, PortGenerator, TempDir};
-use crate::Error;
+use crate::hooks::{run_hooks, LifecycleHooks};
+use crate::shared::shared;
+use crate::util::{
+	current_ops, default_credentials, disk_usage, event, fast_startup_args,
+	feature_compatibility_version, isolated_database_name, last_lines, measure_latency,
+	operation_summary, profiling_args, random_password, record, redact_uri, server_output_tail,
+	set_feature_compatibility_version, set_parameter_args, slow_query_report, spawn_on_free_port,
+	spawn_server, startup_warnings, stream_log_lines, update_password, verbosity_args, BindIp,
+	CloneOptions, CommandRecorder, ConfigSource, Credentials, CurrentOp, DataSeeder, DatabaseDiff,
+	DiffOptions, ExecutedCommand, FailureReport, Heartbeat, HeartbeatMonitor, KillOnDrop,
+	LatencyStatistics, LogForwarder, Manifest, MongodConfig, OperationCounts, PoolMonitor,
+	PoolStatistics, ProfiledOperation, ProfilingLevel, ResourceLimits, RetryPolicy, SavedLog,
+	SecretString, ServerStatus, StartupMetrics, Stopwatch, TempDir, TlsCertificates, ViewSeeder,
+	CLIENT_USERNAME, DEFAULT_PORT_RETRIES,
+};
+use crate::{CaseRunner, Error, FromTempMongo, MongoDownloader, QueryAnalysis, SharedMongo, TempMongoCryptd};
+use futures_util::future::{BoxFuture, FutureExt};
 use futures_util::stream::TryStreamExt;
-use mongodb::bson::Document;
-use mongodb::options::{ClientOptions, ServerAddress};
+use mongodb::bson::{doc, Bson, DateTime, Document};
+use mongodb::options::{AuthMechanism, ClientOptions, Credential, ServerAddress, Tls, TlsOptions};
+use std::collections::BTreeMap;
 use std::ffi::{OsStr, OsString};
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
-use std::time::Duration;
+use std::process::ExitStatus;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
 
 /// A temporary MongoDB instance.
@@ -15,18 +35,47 @@ use tokio::time::sleep;
 /// All state of the MongoDB instance is stored in a temporary directory.
 /// Unless disabled, the temporary directory is deleted when this object is dropped.
 pub struct TempMongo {
-	tempdir: TempDir,
+	keep_temp_dir: bool,
+	reused_data: bool,
+	db_path: PathBuf,
+	port: Option<u16>,
 	socket_path: PathBuf,
+	tls: Option<TlsCertificates>,
+	credentials: Option<Credentials>,
 	log_path: PathBuf,
+	audit_log_path: Option<PathBuf>,
 	client: mongodb::Client,
+	client_options: Box<ClientOptions>,
+	query_analysis: Option<QueryAnalysis>,
+	cryptd: Option<TempMongoCryptd>,
 	server: KillOnDrop,
 	seed: DataSeeder,
+	profiles: BTreeMap<String, Manifest>,
+
+	/// The copy of the log that is saved on clean-up or when dropped during a panic.
+	///
+	/// Declared after the server, so the server is killed before the log is copied.
+	saved_log: Option<SavedLog>,
+	server_status_path: Option<PathBuf>,
+	startup_metrics: StartupMetrics,
+	startup_warnings: Vec<String>,
+	ready_at: DateTime,
+	hooks: LifecycleHooks,
+	command_recorder: Option<Arc<CommandRecorder>>,
+	pool_monitor: Arc<PoolMonitor>,
+	heartbeat_monitor: Arc<HeartbeatMonitor>,
+
+	/// The temporary state directory.
+	///
+	/// Declared last, so it is only removed after the server is killed.
+	tempdir: TempDir,
 }
 
 impl std::fmt::Debug for TempMongo {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		f.debug_struct("TempMongo")
 			.field("tempdir", &self.tempdir.path())
+			.field("db_path", &self.db_path())
 			.field("socket_path", &self.socket_path())
 			.field("log_path", &self.log_path())
 			.field("server_pid", &self.server.id())
@@ -40,6 +89,13 @@ impl TempMongo {
 		Self::from_builder(&TempMongoBuilder::new()).await
 	}
 
+	/// Spawn a new MongoDB instance with default options, recreating it if it fails to start because of the infrastructure.
+	///
+	/// See [`TempMongoBuilder::spawn_with_retries()`] for details.
+	pub async fn new_with_retries(policy: RetryPolicy) -> Result<Self, Error> {
+		TempMongoBuilder::new().spawn_with_retries(policy).await
+	}
+
 	/// Create a builder to customize your [`TempMongo`].
 	///
 	/// After configuring the desirec options, run [`TempMongoBuilder::spawn()`].
@@ -47,26 +103,409 @@ impl TempMongo {
 		TempMongoBuilder::new()
 	}
 
+	/// Run a test against each of the given MongoDB versions in turn.
+	///
+	/// For each version, the binaries are downloaded with [`MongoDownloader`] and a new instance with default options is spawned and passed to the test.
+	/// The instance is cleaned up when the test drops it.
+	/// See [`TempMongoBuilder::for_each_version()`] for details.
+	///
+	/// ```no_run
+	/// # #[cfg_attr(feature = "tokio-runtime", tokio::main)]
+	/// # #[cfg_attr(feature = "async-std-runtime", async_std::main)]
+	/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+	/// use temp_mongo::TempMongo;
+	///
+	/// TempMongo::for_each_version(&["6.0.13", "7.0.5"], |mongo| async move {
+	///     let databases = mongo.client().list_database_names(None, None).await.unwrap();
+	///     assert!(databases.contains(&"admin".to_string()));
+	/// }).await?;
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub async fn for_each_version<F, Fut>(versions: &[&str], test: F) -> Result<(), Error>
+	where
+		F: FnMut(TempMongo) -> Fut,
+		Fut: Future<Output = ()>,
+	{
+		TempMongoBuilder::new().for_each_version(versions, test).await
+	}
+
+	/// Spawn a new instance with default options, run a closure with it, and always clean it up afterwards.
+	///
+	/// The instance is cleaned up with [`Self::kill_and_clean()`] when the future of the closure finishes,
+	/// even if it panics, after which the panic is resumed.
+	/// This is the safest way to make sure the state directory is removed.
+	/// See [`TempMongoBuilder::with()`] for details.
+	///
+	/// ```no_run
+	/// # #[cfg_attr(feature = "tokio-runtime", tokio::main)]
+	/// # #[cfg_attr(feature = "async-std-runtime", async_std::main)]
+	/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+	/// use temp_mongo::TempMongo;
+	///
+	/// let databases = TempMongo::with(|mongo| Box::pin(async move {
+	///     mongo.client().list_database_names(None, None).await
+	/// })).await??;
+	/// assert!(databases.contains(&"admin".to_string()));
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub async fn with<F, T>(test: F) -> Result<T, Error>
+	where
+		F: for<'a> FnOnce(&'a TempMongo) -> BoxFuture<'a, T>,
+	{
+		TempMongoBuilder::new().with(test).await
+	}
+
+	/// Get a handle to a server that is shared by all tests in the process, with a database that is unique to the handle.
+	///
+	/// The first call spawns the server with default options, later calls reuse it.
+	/// This turns the startup of a server for each test into a single startup for the whole test binary.
+	/// Tests should only use the database of their handle, see [`SharedMongo::database()`], so they do not interfere with each other.
+	///
+	/// On Unix, the server is killed and its temporary directory is removed when the process exits.
+	/// On Windows, the server is killed with the process, but the temporary directory is left behind.
+	pub async fn shared() -> Result<SharedMongo, Error> {
+		shared().await
+	}
+
+	/// Spawn a server tuned for benchmarks, that is created synchronously and owns its runtime.
+	///
+	/// See [`BenchmarkMongo`] for details.
+	pub fn for_benchmarks() -> Result<crate::BenchmarkMongo, Error> {
+		crate::BenchmarkMongo::new()
+	}
+
 	/// Get the PID of the MongoDB process.
 	pub fn process_id(&self) -> u32 {
 		self.server.id()
 	}
 
+	/// Check if the MongoDB process has exited, without waiting for it.
+	///
+	/// Returns `None` while the process is running.
+	/// Use this at the end of a test to assert that the server did not crash or get killed while the test ran.
+	/// On Linux, a server that was killed by the out-of-memory killer exits due to signal 9 (`SIGKILL`),
+	/// see [`std::os::unix::process::ExitStatusExt::signal()`].
+	pub fn exit_status(&mut self) -> Result<Option<ExitStatus>, Error> {
+		Ok(self.server.try_wait().map_err(ErrorInner::WaitServer)?)
+	}
+
 	/// Get the path of the temporary state directory.
 	pub fn directory(&self) -> &Path {
 		self.tempdir.path()
 	}
 
+	/// Get the path of the data directory of the MongoDB instance.
+	///
+	/// This is a subdirectory of the temporary state directory,
+	/// unless a data directory was set with [`TempMongoBuilder::data_dir()`] or [`TempMongoBuilder::persistent()`].
+	pub fn db_path(&self) -> &Path {
+		&self.db_path
+	}
+
+	/// Get the disk usage of the data directory in bytes.
+	///
+	/// This is the total size of all files in [`Self::db_path()`], including indexes and the journal.
+	/// The storage engine writes data to disk with a delay, so recent writes may not be included yet.
+	/// Use the `dbStats` command for the logical size of the data in a database.
+	pub fn data_size(&self) -> Result<u64, Error> {
+		disk_usage(&self.db_path)
+	}
+
+	/// Check if the data directory already contained data from a previous run when the server was started.
+	///
+	/// This is always `false`, unless a data directory was set with [`TempMongoBuilder::data_dir()`] or [`TempMongoBuilder::persistent()`].
+	pub fn reused_data(&self) -> bool {
+		self.reused_data
+	}
+
 	/// Get the path of the listening socket of the MongoDB instance.
 	pub fn socket_path(&self) -> &Path {
 		&self.socket_path
 	}
 
+	/// Get the TCP port the MongoDB instance is listening on.
+	///
+	/// This is `None` if the instance only listens on a Unix socket,
+	/// which is the case on Unix unless TLS is enabled with [`TempMongoBuilder::tls()`],
+	/// or the interfaces are set with [`TempMongoBuilder::bind_ip()`].
+	pub fn port(&self) -> Option<u16> {
+		self.port
+	}
+
+	/// Get the path of the certificate of the certificate authority that signed the server certificate.
+	///
+	/// This is `None` unless TLS is enabled with [`TempMongoBuilder::tls()`].
+	/// Pass this file as trusted certificate authority to clients, like with the `tlsCAFile` connection string option.
+	pub fn tls_ca_file(&self) -> Option<&Path> {
+		self.tls.as_ref().map(TlsCertificates::ca_file)
+	}
+
+	/// Get the path of the PEM file with the certificate and private key of the x.509 client.
+	///
+	/// This is `None` unless x.509 authentication is enabled with [`TempMongoBuilder::x509_auth()`].
+	/// Pass this file to other clients to authenticate as the x.509 user, like with the `tlsCertificateKeyFile` connection string option.
+	pub fn tls_client_pem(&self) -> Option<&Path> {
+		self.tls.as_ref().and_then(TlsCertificates::client_pem)
+	}
+
+	/// Get the name of the `$external` user that authenticates with the x.509 client certificate.
+	///
+	/// This is `None` unless x.509 authentication is enabled with [`TempMongoBuilder::x509_auth()`].
+	pub fn x509_username(&self) -> Option<&str> {
+		self.tls_client_pem().map(|_| CLIENT_USERNAME)
+	}
+
+	/// Get the credentials of the root user.
+	///
+	/// This is `None` unless random credentials are enabled with [`TempMongoBuilder::with_random_credentials()`] or the `auth-by-default` feature.
+	pub fn credentials(&self) -> Option<&Credentials> {
+		self.credentials.as_ref()
+	}
+
+	/// Get the path of the audit log of the MongoDB instance.
+	///
+	/// This is `None` unless auditing is enabled with [`TempMongoBuilder::audit_log()`].
+	pub fn audit_log_path(&self) -> Option<&Path> {
+		self.audit_log_path.as_deref()
+	}
+
+	/// Get the path the log of the MongoDB instance is saved to on clean-up.
+	///
+	/// This is `None` unless enabled with [`TempMongoBuilder::save_logs_to()`].
+	pub fn saved_log_path(&self) -> Option<&Path> {
+		self.saved_log.as_ref().map(SavedLog::destination)
+	}
+
+	/// Get the path the `serverStatus` snapshot is written to on clean-up.
+	///
+	/// This is `None` unless enabled with [`TempMongoBuilder::save_server_status_to()`].
+	pub fn server_status_path(&self) -> Option<&Path> {
+		self.server_status_path.as_deref()
+	}
+
+	/// Take a snapshot of the output of the `serverStatus` command.
+	///
+	/// Take the snapshot at the end of a test to check for resource issues, like leaked connections.
+	pub async fn server_status(&self) -> Result<ServerStatus, Error> {
+		ServerStatus::fetch(&self.client).await
+	}
+
+	/// Get the commands executed by the client of [`Self::client()`] so far, in the order they finished.
+	///
+	/// Commands are only recorded if enabled with [`TempMongoBuilder::record_commands()`], otherwise this returns an empty list.
+	/// The commands executed while spawning the server are not included.
+	/// Clones of the client record their commands too, but other clients do not.
+	pub fn commands_executed(&self) -> Vec<ExecutedCommand> {
+		self.command_recorder.as_ref().map(|recorder| recorder.executed()).unwrap_or_default()
+	}
+
+	/// Get the statistics of the connection pool of the client of [`Self::client()`], like checkouts, timeouts and created connections.
+	///
+	/// This can be used to detect connection leaks or pool exhaustion in application code,
+	/// for example by asserting that no connections are still checked out at the end of a test.
+	/// The counters include the connections used while spawning the server.
+	/// Clones of the client share the pool, but other clients do not.
+	pub fn pool_statistics(&self) -> PoolStatistics {
+		self.pool_monitor.statistics()
+	}
+
+	/// Get the most recent heartbeats of the server monitor of the client of [`Self::client()`], oldest first.
+	///
+	/// The client sends a heartbeat every 10 seconds by default, see [`TempMongoBuilder::heartbeat_frequency()`].
+	/// Since MongoDB 4.4, most heartbeats are awaited with the streaming protocol, so their duration is not a round trip time.
+	/// Use [`Self::measure_latency()`] to measure the round trip time instead.
+	pub fn heartbeats(&self) -> Vec<Heartbeat> {
+		self.heartbeat_monitor.heartbeats()
+	}
+
+	/// Measure the round trip time to the server with `samples` consecutive `ping` commands.
+	///
+	/// This allows tests that inject latency or network partitions to assert the measured effect, instead of relying on sleeps.
+	pub async fn measure_latency(&self, samples: usize) -> Result<LatencyStatistics, Error> {
+		measure_latency(&self.client, samples).await
+	}
+
+	/// Forget the commands executed so far, so [`Self::commands_executed()`] only returns commands executed after this call.
+	pub fn clear_commands_executed(&self) {
+		if let Some(recorder) = &self.command_recorder {
+			recorder.clear();
+		}
+	}
+
+	/// Get the operations in progress that match a filter, using the `currentOp` command.
+	///
+	/// The filter uses the fields of the output, like `doc! { "ns": "test.animals", "op": "query" }`.
+	/// Pass an empty document to get all active operations, or `doc! { "$all": true }` to include idle connections.
+	/// This can be used to check that a long-running operation is actually in flight, or to debug hangs.
+	pub async fn current_ops(&self, filter: Document) -> Result<Vec<CurrentOp>, Error> {
+		current_ops(&self.client, filter).await
+	}
+
+	/// Get the slowest operations recorded by the database profiler in all databases, slowest first.
+	///
+	/// At most `limit` operations are returned.
+	/// The profiler must be enabled with [`TempMongoBuilder::enable_profiling()`], otherwise the report is empty.
+	pub async fn slow_query_report(&self, limit: usize) -> Result<Vec<ProfiledOperation>, Error> {
+		slow_query_report(&self.client, limit).await
+	}
+
+	/// Count the operations on each collection since the server was ready, keyed by namespace like `test.animals`.
+	///
+	/// This allows assertions like "no collection scans happened" or "exactly one insert into `test.animals`".
+	/// Operations from seeding the server are not included.
+	/// All operations are only recorded if enabled with [`TempMongoBuilder::count_operations()`],
+	/// otherwise only the operations recorded by the profiler settings of [`TempMongoBuilder::enable_profiling()`] are counted.
+	pub async fn operation_summary(&self) -> Result<BTreeMap<String, OperationCounts>, Error> {
+		operation_summary(&self.client, self.ready_at).await
+	}
+
+	/// Read and parse all events from the audit log so far.
+	///
+	/// Each event is a JSON object in the audit message format of MongoDB, with fields like `atype`, `param` and `result`.
+	/// The events are returned as plain JSON rather than BSON documents,
+	/// since the server writes some values in the legacy extended JSON format.
+	/// The server may write events with a short delay after the operations that caused them.
+	///
+	/// Returns an empty list if auditing is not enabled with [`TempMongoBuilder::audit_log()`].
+	pub fn audit_events(&self) -> Result<Vec<serde_json::Value>, Error> {
+		let Some(path) = &self.audit_log_path else {
+			return Ok(Vec::new());
+		};
+		let log = match std::fs::read_to_string(path) {
+			Ok(log) => log,
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+			Err(e) => return Err(ErrorInner::ReadAuditLog(path.clone(), e).into()),
+		};
+		log.lines()
+			.filter(|line| !line.trim().is_empty())
+			.map(|line| serde_json::from_str(line).map_err(|e| ErrorInner::ParseAuditLog(path.clone(), e).into()))
+			.collect()
+	}
+
+	/// Change the password of a user in the `admin` database to a new random password.
+	///
+	/// Returns the new password.
+	/// If the user is the root user from [`Self::credentials()`], the stored credentials are updated,
+	/// and [`Self::client()`] is replaced by a client that authenticates with the new password.
+	/// Clones of the previous client keep their open connections, but new connections fail to authenticate.
+	/// This can be used to test the credential refresh logic of an application.
+	pub async fn rotate_password(&mut self, username: &str) -> Result<String, Error> {
+		let password = random_password();
+		update_password(&self.client, username, &password).await?;
+		if let Some(credentials) = self.credentials.as_mut().filter(|credentials| credentials.username == username) {
+			credentials.password = SecretString::new(password.clone());
+			self.client_options.credential = Some(credentials.to_credential());
+			self.client = mongodb::Client::with_options(self.client_options.as_ref().clone())
+				.map_err(|e| ErrorInner::Connect(self.socket_path.display().to_string(), e))?;
+		}
+		Ok(password)
+	}
+
 	/// Get the path of the log file of the MongoDB instance.
 	pub fn log_path(&self) -> &Path {
 		&self.log_path
 	}
 
+	/// Get a connection string for the MongoDB instance.
+	///
+	/// The connection string uses a direct connection to the same address as [`Self::client()`].
+	/// If authentication with a password is enabled, it includes the credentials of the root user.
+	/// With TLS, it refers to the certificate files in the temporary directory.
+	pub fn uri(&self) -> String {
+		let host = match self.client_options.hosts.first() {
+			#[cfg(unix)]
+			Some(ServerAddress::Unix { path }) => path.display().to_string().replace('/', "%2F"),
+			Some(address) => address.to_string(),
+			None => String::new(),
+		};
+		let mut options = vec!["directConnection=true".to_owned()];
+		let user_info = match &self.credentials {
+			Some(credentials) => {
+				options.push("authSource=admin".into());
+				credentials.to_user_info()
+			},
+			None => String::new(),
+		};
+		if let Some(tls) = &self.tls {
+			options.push("tls=true".into());
+			options.push(format!("tlsCAFile={}", tls.ca_file().display()));
+			if let Some(pem) = tls.client_pem() {
+				options.push(format!("tlsCertificateKeyFile={}", pem.display()));
+			}
+		}
+		format!("mongodb://{user_info}{host}/?{}", options.join("&"))
+	}
+
+	/// Gather diagnostics of the server in one report, for example in the failure hook of a test harness.
+	///
+	/// The report contains the connection string with the password redacted, the state of the server process,
+	/// the last `log_lines` lines of the log and the output of the `serverStatus` command.
+	/// Gathering the report does not fail: if the server does not respond within a second, the error is included in the report instead.
+	pub async fn failure_report(&mut self, log_lines: usize) -> FailureReport {
+		let exit_status = match self.server.try_wait() {
+			Ok(status) => status.map(|status| status.to_string()),
+			Err(e) => Some(format!("unknown: {e}")),
+		};
+
+		let mut client_options = self.client_options.as_ref().clone();
+		client_options.server_selection_timeout = Some(Duration::from_secs(1));
+		let server_status = match mongodb::Client::with_options(client_options) {
+			Ok(client) => {
+				let status = ServerStatus::fetch(&client).await;
+				client.shutdown_immediate().await;
+				status
+			},
+			Err(e) => Err(ErrorInner::ServerStatus(e).into()),
+		};
+		let (server_status, server_status_error) = match server_status {
+			Ok(status) => (Some(Bson::Document(status.raw().clone()).into_relaxed_extjson()), None),
+			Err(e) => (None, Some(e.to_string())),
+		};
+
+		FailureReport {
+			uri: redact_uri(&self.uri()).into_owned(),
+			process_id: self.server.id(),
+			exit_status,
+			directory: self.tempdir.path().to_owned(),
+			log_path: self.log_path.clone(),
+			log_tail: last_lines(&self.log_path, log_lines),
+			server_status,
+			server_status_error,
+		}
+	}
+
+	/// Get the warnings the server logged at startup, like a missing XFS filesystem or low resource limits.
+	///
+	/// Each warning is a log line of the server, which is a JSON document since MongoDB 4.4.
+	/// The warnings are read with the `getLog` command once the server is ready.
+	/// With the `tracing` or `log` feature, they are also emitted as warning events with target `temp_mongo::spawn`.
+	pub fn startup_warnings(&self) -> &[String] {
+		&self.startup_warnings
+	}
+
+	/// Get the time spent in each phase of starting the server.
+	pub fn startup_metrics(&self) -> StartupMetrics {
+		self.startup_metrics
+	}
+
+	/// Stream the lines of the log of the MongoDB instance as they are written.
+	///
+	/// The stream starts with the first line of the log and waits for new lines when it reaches the end.
+	/// It never ends by itself, unless reading the log fails.
+	pub fn stream_logs(&self) -> impl futures_util::stream::Stream<Item = std::io::Result<String>> {
+		stream_log_lines(&self.log_path)
+	}
+
+	/// Print the log of the MongoDB instance to standard output as it is written, with a prefix before each line.
+	///
+	/// The lines are printed from a background thread, interleaved with the output of the test.
+	/// Forwarding stops when the returned [`LogForwarder`] is dropped.
+	pub fn forward_logs(&self, prefix: impl Into<String>) -> LogForwarder {
+		LogForwarder::start(&self.log_path, prefix.into())
+	}
+
 	/// Prepare seed document row with &str for db name and collection name into mongoDB database instance
 	pub fn prepare_seed_document(
 		&self,
@@ -95,6 +534,97 @@ impl TempMongo {
 		seed_data.seed_document(&self.client).await
 	}
 
+	/// Verify that the seeded documents are present in the MongoDB database
+	/// # Arguments
+	/// * `seed_data` - The seed data to look for in the database
+	pub async fn verify_document(&self, seed_data: &DataSeeder) -> mongodb::error::Result<bool> {
+		seed_data.verify_document(&self.client).await
+	}
+
+	/// Create a read-only view in the MongoDB database
+	/// # Arguments
+	/// * `database_name` - The name of the database to create the view in
+	/// * `view_name` - The name of the view
+	/// * `source` - The name of the collection or view the view is defined on
+	/// * `pipeline` - The aggregation pipeline that defines the view
+	pub async fn create_view(
+		&self,
+		database_name: &str,
+		view_name: &str,
+		source: &str,
+		pipeline: Vec<Document>,
+	) -> mongodb::error::Result<()> {
+		ViewSeeder::new(database_name, view_name, source, pipeline)
+			.seed_view(&self.client)
+			.await
+	}
+
+	/// Seed all documents and create all views described by a manifest
+	/// # Arguments
+	/// * `manifest` - The manifest to load into the database
+	pub async fn load_manifest(&self, manifest: &Manifest) -> mongodb::error::Result<()> {
+		manifest.load(&self.client).await
+	}
+
+	/// Compare the contents of a database with the seeds of a manifest, ignoring the `_id` field
+	/// # Arguments
+	/// * `db_name` - The name of the database to compare
+	/// * `expected` - The manifest describing the expected documents
+	pub async fn diff_database(
+		&self,
+		db_name: &str,
+		expected: &Manifest,
+	) -> mongodb::error::Result<DatabaseDiff> {
+		self.diff_database_with(db_name, expected, &DiffOptions::new()).await
+	}
+
+	/// Compare the contents of a database with the seeds of a manifest
+	/// # Arguments
+	/// * `db_name` - The name of the database to compare
+	/// * `expected` - The manifest describing the expected documents
+	/// * `options` - Which fields to ignore in the comparison
+	pub async fn diff_database_with(
+		&self,
+		db_name: &str,
+		expected: &Manifest,
+		options: &DiffOptions,
+	) -> mongodb::error::Result<DatabaseDiff> {
+		DatabaseDiff::compute(&self.client, db_name, expected, options).await
+	}
+
+	/// Clone documents and indexes from a remote MongoDB deployment into the MongoDB database
+	/// # Arguments
+	/// * `uri` - The connection string of the remote deployment
+	/// * `databases` - The names of the databases to clone
+	/// * `options` - Which collections and documents to clone
+	pub async fn clone_from(
+		&self,
+		uri: &str,
+		databases: &[&str],
+		options: &CloneOptions,
+	) -> mongodb::error::Result<()> {
+		let source = mongodb::Client::with_uri_str(uri).await?;
+		let result = options.clone_databases(&source, &self.client, databases).await;
+		source.shutdown().await;
+		result
+	}
+
+	/// Seed the data of a seed profile registered with [`TempMongoBuilder::seed_profile()`]
+	/// # Arguments
+	/// * `name` - The name of the seed profile
+	#[cfg_attr(feature = "tracing", tracing::instrument(target = "temp_mongo::seed", skip(self)))]
+	pub async fn load_profile(&self, name: &str) -> Result<(), Error> {
+		let manifest = self
+			.profiles
+			.get(name)
+			.ok_or_else(|| ErrorInner::UnknownProfile(name.to_string()))?;
+		manifest
+			.load(&self.client)
+			.await
+			.map_err(|e| ErrorInner::SeedProfile(name.to_string(), e))?;
+		Ok(())
+	}
+
 	/// Get a client for the MongDB instance.
 	///
 	/// This returns a client by reference,
@@ -103,6 +633,176 @@ impl TempMongo {
 		&self.client
 	}
 
+	/// Remove all data from the server, by dropping all databases except `admin`, `config` and `local`.
+	///
+	/// This allows reusing the server for another test, see [`crate::InstancePool`].
+	/// Users and other settings in the `admin` database are kept.
+	pub async fn reset(&self) -> Result<(), Error> {
+		let names = self.client.list_database_names(None, None).await.map_err(ErrorInner::ListDatabases)?;
+		for name in names {
+			if matches!(name.as_str(), "admin" | "config" | "local") {
+				continue;
+			}
+			self.client
+				.database(&name)
+				.drop(None)
+				.await
+				.map_err(|e| ErrorInner::DropDatabase(name, e))?;
+		}
+		Ok(())
+	}
+
+	/// Get a database that is unique to the current test, for tests that share one server, see [`isolated_database_name()`].
+	///
+	/// The name is derived from the run ID and the name of the current test.
+	/// Use a run ID that is unique to the test run, like the ID of a CI job, when several runs share the server.
+	/// Returns an error if the database already exists on the server, which means another run used the same run ID.
+	pub async fn isolated_database(&self, run_id: &str) -> Result<mongodb::Database, Error> {
+		let name = isolated_database_name(run_id);
+		let existing = self.client.list_database_names(None, None).await.map_err(ErrorInner::ListDatabases)?;
+		if existing.contains(&name) {
+			return Err(ErrorInner::DatabaseExists(name).into());
+		}
+		Ok(self.client.database(&name))
+	}
+
+	/// Create the value that a web application needs to connect to the server, like a client or a connection string.
+	///
+	/// See [`FromTempMongo`] for the supported values.
+	pub fn app_state<S: FromTempMongo>(&self) -> S {
+		S::from_temp_mongo(self)
+	}
+
+	/// Run a test against a web application, and clean up the server after the application is shut down.
+	///
+	/// The application gets its state from [`Self::app_state()`].
+	/// The future should run the test and shut down the application, like with the graceful shutdown of axum or actix.
+	/// When it finishes, the state is dropped, so the application closed its connections before the server is cleaned up
+	/// with [`Self::kill_and_clean()`].
+	/// If cleaning up fails, the error is returned instead of the output of the future.
+	pub async fn run_app<S, F, Fut>(self, app: F) -> Result<Fut::Output, Error>
+	where
+		S: FromTempMongo,
+		F: FnOnce(S) -> Fut,
+		Fut: Future,
+	{
+		let output = app(self.app_state()).await;
+		self.kill_and_clean().await?;
+		Ok(output)
+	}
+
+	/// Create a runner for the generated cases of a property test, that resets the data before each case.
+	///
+	/// See [`CaseRunner`] for details.
+	pub fn case_runner(&self) -> CaseRunner<'_> {
+		CaseRunner::new(self)
+	}
+
+	/// Get the options of the client, to create more clients for the same server.
+	pub(crate) fn client_options(&self) -> &ClientOptions {
+		&self.client_options
+	}
+
+	/// Get the temporary `mongocryptd` process for automatic encryption.
+	///
+	/// This is `None` unless automatic encryption is enabled with [`TempMongoBuilder::with_csfle()`] using [`QueryAnalysis::Mongocryptd`].
+	pub fn cryptd(&self) -> Option<&TempMongoCryptd> {
+		self.cryptd.as_ref()
+	}
+
+	/// Get the extra options for the auto encryption options of a client.
+	///
+	/// These tell the driver where to find the query analysis component configured with [`TempMongoBuilder::with_csfle()`]:
+	/// the connection string of the temporary `mongocryptd` process, or the path of the `crypt_shared` library.
+	/// The driver is never allowed to spawn `mongocryptd` by itself.
+	///
+	/// This is `None` unless automatic encryption is enabled.
+	pub fn auto_encryption_extra_options(&self) -> Option<Document> {
+		match self.query_analysis.as_ref()? {
+			QueryAnalysis::Mongocryptd => Some(doc! {
+				"mongocryptdURI": self.cryptd.as_ref()?.uri(),
+				"mongocryptdBypassSpawn": true,
+			}),
+			QueryAnalysis::CryptShared(path) => Some(doc! {
+				"cryptSharedLibPath": path.display().to_string(),
+				"cryptSharedRequired": true,
+			}),
+		}
+	}
+
+	/// Create a client with automatic encryption that connects to the MongoDB instance.
+	///
+	/// The client uses the same connection options as [`Self::client()`],
+	/// and the query analysis component from [`Self::auto_encryption_extra_options()`] if automatic encryption is enabled.
+	/// The schema map specifies which fields to encrypt, by namespace.
+	/// If it is empty, the schemas are obtained from the server.
+	///
+	/// Only available with the `csfle` feature, which requires the `libmongocrypt` library.
+	#[cfg(feature = "csfle")]
+	pub async fn encrypted_client(
+		&self,
+		key_vault_namespace: mongodb::Namespace,
+		kms_providers: impl IntoIterator<Item = (mongocrypt::ctx::KmsProvider, Document, Option<TlsOptions>)>,
+		schema_map: impl IntoIterator<Item = (String, Document)>,
+	) -> mongodb::error::Result<mongodb::Client> {
+		let schema_map: Vec<_> = schema_map.into_iter().collect();
+		mongodb::Client::encrypted_builder(self.client_options.as_ref().clone(), key_vault_namespace, kms_providers)?
+			.extra_options(self.auto_encryption_extra_options())
+			.schema_map(schema_map)
+			.build()
+			.await
+	}
+
+	/// Set up a local KMS provider with a random master key and create data keys in a key vault of the MongoDB instance.
+	///
+	/// The key vault collection `encryption.__keyVault` is created with a unique index on the key alternative names.
+	/// The returned [`LocalKms`][crate::LocalKms] holds everything needed to construct a client with automatic encryption,
+	/// see [`Self::queryable_encryption_client()`].
+	///
+	/// Only available with the `csfle` feature, which requires the `libmongocrypt` library.
+	#[cfg(feature = "csfle")]
+	pub async fn local_kms(&self, data_keys: usize) -> Result<crate::LocalKms, Error> {
+		crate::LocalKms::create(&self.client, data_keys).await
+	}
+
+	/// Create a client with automatic Queryable Encryption that connects to the MongoDB instance.
+	///
+	/// The client uses the key vault and local KMS provider of `kms`,
+	/// and the query analysis component from [`Self::auto_encryption_extra_options()`] if automatic encryption is enabled.
+	/// The encrypted fields map specifies the encrypted fields of each collection by namespace,
+	/// see [`LocalKms::encrypted_fields()`][crate::LocalKms::encrypted_fields].
+	///
+	/// Note that Queryable Encryption requires MongoDB 7.0 or newer, and a replica set or sharded cluster.
+	/// Against a standalone server like this one, only the key vault and explicit encryption can be tested.
+	///
+	/// Only available with the `csfle` feature, which requires the `libmongocrypt` library.
+	#[cfg(feature = "csfle")]
+	pub async fn queryable_encryption_client(
+		&self,
+		kms: &crate::LocalKms,
+		encrypted_fields_map: impl IntoIterator<Item = (String, Document)>,
+	) -> mongodb::error::Result<mongodb::Client> {
+		let encrypted_fields_map: Vec<_> = encrypted_fields_map.into_iter().collect();
+		mongodb::Client::encrypted_builder(self.client_options.as_ref().clone(), kms.key_vault_namespace().clone(), kms.kms_providers())?
+			.extra_options(self.auto_encryption_extra_options())
+			.encrypted_fields_map(encrypted_fields_map)
+			.build()
+			.await
+	}
+
+	/// Set the feature compatibility version of the server, like `"6.0"`.
+	///
+	/// This waits until the upgrade or downgrade is complete.
+	/// It can be used to test compatibility behavior of a newer binary that runs with the feature set of an older version.
+	pub async fn set_fcv(&self, version: &str) -> Result<(), Error> {
+		set_feature_compatibility_version(&self.client, version).await
+	}
+
+	/// Get the feature compatibility version of the server.
+	pub async fn fcv(&self) -> mongodb::error::Result<String> {
+		feature_compatibility_version(&self.client).await
+	}
+
 	/// Enable or disable clean-up of the temporary directory when this object is dropped.
 	pub fn set_clean_on_drop(&mut self, clean_on_drop: bool) {
 		self.tempdir.set_clean_on_drop(clean_on_drop);
@@ -115,18 +815,65 @@ impl TempMongo {
 	///
 	/// This function ignores the value of `clean_on_drop`.
 	/// It also allows for better error handling compared to just dropping the object.
+	///
+	/// If [`TempMongoBuilder::keep_temp_dir()`] was enabled, the temporary state directory is left on the filesystem.
+	#[cfg_attr(feature = "tracing", tracing::instrument(target = "temp_mongo::cleanup", skip_all, fields(directory = %self.tempdir.path().display(), process.pid = self.server.id())))]
 	pub async fn kill_and_clean(mut self) -> Result<(), Error> {
+		self.before_cleanup().await;
+		if let Some(cryptd) = self.cryptd.take() {
+			cryptd.kill_and_clean().await?;
+		}
 		self.client.shutdown_immediate().await;
 		self.server.kill().map_err(ErrorInner::KillServer)?;
+		self.server.wait().map_err(ErrorInner::KillServer)?;
 		sleep(Duration::from_millis(50)).await;
 
+		if let Some(saved_log) = &mut self.saved_log {
+			saved_log.save()?;
+		}
+
+		if self.keep_temp_dir {
+			let _path = self.tempdir.into_path();
+			event!(info, target: "temp_mongo::cleanup", "keeping temporary directory {}", _path.display());
+			return Ok(());
+		}
+
 		let path = self.tempdir.path().to_owned();
+		event!(debug, target: "temp_mongo::cleanup", "removing temporary directory {}", path.display());
 		self.tempdir
 			.close()
 			.map_err(|e| ErrorInner::CleanDir(path, e))?;
 		Ok(())
 	}
 
+	/// Shut down the server gracefully and remove the temporary state directory on the filesystem.
+	///
+	/// Instead of killing the server, this sends the `shutdown` command and waits for the server to exit,
+	/// so the storage engine is closed cleanly.
+	/// This avoids recovery and shutdown warnings when the data directory is reused, see [`TempMongoBuilder::persistent()`].
+	/// If the server has not exited after `timeout`, it is killed.
+	///
+	/// Like [`Self::kill_and_clean()`], this function ignores the value of `clean_on_drop`,
+	/// and leaves the temporary state directory on the filesystem if [`TempMongoBuilder::keep_temp_dir()`] was enabled.
+	pub async fn shutdown_and_clean(mut self, timeout: Duration) -> Result<(), Error> {
+		self.before_cleanup().await;
+		// The server closes the connection while shutting down, so the command is expected to fail.
+		self.client
+			.database("admin")
+			.run_command(doc! { "shutdown": 1 }, None)
+			.await
+			.ok();
+
+		let start = Instant::now();
+		while self.server.try_wait().map_err(ErrorInner::KillServer)?.is_none() {
+			if start.elapsed() > timeout {
+				break;
+			}
+			sleep(Duration::from_millis(50)).await;
+		}
+		self.kill_and_clean().await
+	}
+
 	/// Kill the server, but leave the temporary state directory on the filesystem.
 	///
 	/// Note that the server will also be killed when this object is dropped.
@@ -134,12 +881,84 @@ impl TempMongo {
 	/// This function ignores the value of `clean_on_drop`.
 	/// It also allows for better error handling compared to just dropping the object.
 	pub async fn kill_no_clean(mut self) -> Result<(), Error> {
+		self.before_cleanup().await;
 		let _path = self.tempdir.into_path();
 		self.client.shutdown_immediate().await;
 		self.server.kill().map_err(ErrorInner::KillServer)?;
 		Ok(())
 	}
 
+	/// Run the clean-up hooks and take the `serverStatus` snapshot, while the server is still running.
+	///
+	/// Both are only done once, even if this is called again.
+	/// The snapshot is best-effort: if the server crashed or does not respond, the error is logged,
+	/// so the server is still killed and the temporary directory is still removed.
+	async fn before_cleanup(&mut self) {
+		let hooks = std::mem::take(&mut self.hooks.on_before_cleanup);
+		run_hooks(&hooks, self).await;
+		if let Some(path) = self.server_status_path.take() {
+			if let Ok(Some(_status)) = self.server.try_wait() {
+				event!(warn, target: "temp_mongo::cleanup", "not saving server status to {}, the server already exited with {_status}", path.display());
+				return;
+			}
+			let snapshot = match self.server_status().await {
+				Ok(status) => status.write_to_file(&path),
+				Err(e) => Err(e),
+			};
+			if let Err(_e) = snapshot {
+				event!(warn, target: "temp_mongo::cleanup", "failed to save server status to {}: {_e}", path.display());
+			}
+		}
+	}
+
+	/// Export all documents in a collection to a pretty-printed JSON file
+	///
+	/// The file can be used as a fixture with [`DataSeeder::from_json_file()`].
+	/// # Arguments
+	/// * `db_name` - The name of the database
+	/// * `collection_name` - The name of the collection
+	/// * `path` - The path of the file to write
+	pub async fn export_collection(
+		&self,
+		db_name: &str,
+		collection_name: &str,
+		path: impl AsRef<Path>,
+	) -> Result<(), Error> {
+		crate::util::export_collection(&self.client, db_name, collection_name, path.as_ref()).await
+	}
+
+	/// Write collections of a database to a golden file, ignoring the `_id` field
+	///
+	/// The file contains canonical JSON with sorted keys and documents, as compared by [`Self::assert_matches_golden()`].
+	/// # Arguments
+	/// * `db_name` - The name of the database
+	/// * `collections` - The names of the collections to write
+	/// * `path` - The path of the file to write
+	pub async fn dump_golden(
+		&self,
+		db_name: &str,
+		collections: &[&str],
+		path: impl AsRef<Path>,
+	) -> Result<(), Error> {
+		crate::util::dump_golden(&self.client.database(db_name), collections, path.as_ref(), &DiffOptions::new()).await
+	}
+
+	/// Assert that collections of a database match a golden file written by [`Self::dump_golden()`]
+	///
+	/// See [`assertions::assert_matches_golden()`](crate::assertions::assert_matches_golden) for details.
+	/// # Arguments
+	/// * `db_name` - The name of the database
+	/// * `collections` - The names of the collections to compare
+	/// * `path` - The path of the golden file
+	pub async fn assert_matches_golden(
+		&self,
+		db_name: &str,
+		collections: &[&str],
+		path: impl AsRef<Path>,
+	) {
+		crate::assertions::assert_matches_golden(&self.client.database(db_name), collections, path).await
+	}
+
 	/// Advanced printing of documents in a collection
 	/// # Arguments
 	/// * `db_name` - The name of the database
@@ -179,109 +998,292 @@ impl TempMongo {
 	/// # Errors
 	/// This function can return errors related to creating temporary directories, starting the MongoDB
 	/// server, and configuring the MongoDB client.
+	#[cfg_attr(feature = "tracing", tracing::instrument(
+		name = "spawn",
+		target = "temp_mongo::spawn",
+		skip_all,
+		fields(command = %builder.get_command_string(), process.pid = tracing::field::Empty, server.address = tracing::field::Empty),
+	))]
 	async fn from_builder(builder: &TempMongoBuilder) -> Result<Self, Error> {
+		let mut stopwatch = Stopwatch::start();
+		let mut startup_metrics = StartupMetrics::default();
 		let tempdir = builder.make_temp_dir().map_err(ErrorInner::MakeTempDir)?;
-		let db_dir = tempdir.path().join("db");
+		let db_dir = match (&builder.data_dir, &builder.persistent) {
+			(Some(data_dir), _) => data_dir.clone(),
+			(None, Some(name)) => default_data_dir().ok_or(ErrorInner::NoCacheDir)?.join(name),
+			(None, None) => tempdir.path().join("db"),
+		};
 		let log_path = tempdir.path().join("mongod.log");
 		let seed = DataSeeder::new();
 
-		std::fs::create_dir(&db_dir).map_err(|e| ErrorInner::MakeDbDir(db_dir.clone(), e))?;
+		std::fs::create_dir_all(&db_dir).map_err(|e| ErrorInner::MakeDbDir(db_dir.clone(), e))?;
+		let reused_data = db_dir.join("WiredTiger").exists() || db_dir.join("storage.bson").exists();
 
-		let server_address: String;
-		let socket_path: PathBuf;
+		// On Unix, the server normally only listens on a Unix socket, so no TCP port needs to be allocated.
+		// With TLS, it listens on a TCP port on the loopback adapter instead,
+		// since the certificate of the server is verified against the host name.
+		// It also listens on TCP if the interfaces are set explicitly.
+		let use_tls = builder.tls || builder.x509_auth;
+		let use_tcp = cfg!(windows) || use_tls || builder.bind_ip.is_some();
+		let loopback = if cfg!(windows) { "localhost" } else { "127.0.0.1" };
+		let server_address = match (&builder.bind_ip, use_tcp) {
+			(Some(bind_ip), _) => bind_ip.client_address(loopback).to_string(),
+			(None, true) => loopback.to_string(),
+			(None, false) => tempdir.path().join("mongod.sock").display().to_string(),
+		};
+		let bind_ip = builder.bind_ip.clone().unwrap_or(BindIp::Addresses(vec![server_address.clone()]));
 
-		#[cfg(windows)]
-		{
-			server_address = "localhost".to_string();
-			socket_path = PathBuf::from(&server_address);
+		let mut tls = match use_tls {
+			true => Some(TlsCertificates::generate(tempdir.path())?),
+			false => None,
+		};
+		if let (Some(tls), true) = (&mut tls, builder.x509_auth) {
+			tls.generate_client()?;
+		}
+		let auth_enabled = builder.x509_auth || builder.credentials.is_some();
+
+		let mut extra_args: Vec<OsString> = Vec::new();
+		if let Some(config) = &builder.config {
+			extra_args.extend(["--config".into(), config.resolve(tempdir.path())?.into()]);
+		}
+		if builder.fast_startup {
+			extra_args.extend(fast_startup_args(builder.get_command(), false, &builder.parameters));
+		}
+		if let Some(size) = builder.wired_tiger_cache_size_gb {
+			extra_args.extend(["--wiredTigerCacheSizeGB".into(), size.to_string().into()]);
+		}
+		if let Some(engine) = &builder.storage_engine {
+			extra_args.extend(["--storageEngine".into(), engine.into()]);
+		}
+		extra_args.extend(verbosity_args(builder.quiet, builder.verbosity));
+		if builder.no_scripting {
+			extra_args.push("--noscripting".into());
+		}
+		if let Some((level, slow_ms)) = builder.profiling {
+			extra_args.extend(profiling_args(level, slow_ms));
+		}
+		extra_args.extend(set_parameter_args(&builder.parameters));
+		if let Some(tls) = &tls {
+			extra_args.extend(tls.server_args());
+		}
+		let audit_log_path = builder.audit_log.then(|| tempdir.path().join("audit.json"));
+		if let Some(path) = &audit_log_path {
+			extra_args.extend(["--auditDestination".into(), "file".into(), "--auditFormat".into(), "JSON".into(), "--auditPath".into(), path.into()]);
 		}
-		#[cfg(unix)]
-		{
-			// For Unix-based systems: Use Unix socket for MongoDB
-			server_address = tempdir.path().join("mongod.sock").display().to_string();
-			socket_path = PathBuf::from(&server_address);
-		}
-
-		let mut port_generator = PortGenerator::new();
-		let random_port = port_generator.generate();
-
-		let mongodb_port = random_port.selected_port().ok_or_else(|| {
-			let error: ErrorInner = ErrorInner::Port.into();
-			eprintln!("Error: {}", error);
-			error
-		})?;
-
-		//TODO: Add some error handling when spawning the service
-		//We might need to hide away the spawning of the server in a new class
-		let server = Command::new(builder.get_command())
-			.arg("--bind_ip")
-			.arg(&server_address)
-			.arg("--dbpath")
-			.arg(&db_dir)
-			.arg("--logpath")
-			.arg(&log_path)
-			.arg("--noauth")
-			.arg("--port")
-			.arg(mongodb_port.to_string())
-			.stdout(Stdio::piped())
-			.stderr(Stdio::piped())
-			.spawn()
-			.map_err(|e| ErrorInner::SpawnServer(builder.get_command_string(), e))?;
-
-		let server = KillOnDrop::new(server);
-
-		let mut hosts = Vec::new();
 
+		// The default socket that mongod derives from the port is moved into the temporary directory,
+		// so it can not collide with other instances.
 		#[cfg(unix)]
 		{
-			// For Unix-like systems, use a Unix socket
-			hosts.push(ServerAddress::Unix {
+			extra_args.extend(["--unixSocketPrefix".into(), tempdir.path().into()]);
+		}
+
+		let mut args = bind_ip.args(loopback, false);
+		args.extend([
+			"--dbpath".into(),
+			db_dir.clone().into(),
+			"--logpath".into(),
+			log_path.clone().into(),
+			if auth_enabled { "--auth" } else { "--noauth" }.into(),
+		]);
+		args.extend(extra_args);
+		let output_path = tempdir.path().join("mongod.out");
+
+		startup_metrics.prepare = stopwatch.lap();
+		let (port, mut server, host, socket_path) = if use_tcp {
+			// The TCP port may be taken by another process before the server binds to it.
+			let make_args = |port: u16| {
+				let mut args = args.clone();
+				args.extend(["--port".into(), port.to_string().into()]);
+				args
+			};
+			let (port, server) = spawn_on_free_port(builder.get_command(), &output_path, &log_path, &builder.resource_limits, builder.port_retries, make_args).await?;
+			let host = ServerAddress::Tcp {
+				host: server_address.clone(),
+				port: Some(port),
+			};
+			let socket_path = if cfg!(windows) {
+				PathBuf::from(&server_address)
+			} else {
+				tempdir.path().join(format!("mongodb-{port}.sock"))
+			};
+			(Some(port), server, host, socket_path)
+		} else {
+			let server = spawn_server(builder.get_command(), &args, &output_path, &builder.resource_limits)?;
+			let socket_path = PathBuf::from(&server_address);
+			let host = ServerAddress::Unix {
 				path: socket_path.clone(),
-			});
+			};
+			(None, server, host, socket_path)
+		};
 
-			// Debugging: Print the Unix socket path
-			println!(
-				"Using Unix socket for MongoDB connection: {:?}",
-				socket_path
-			);
-		}
+		startup_metrics.spawn = stopwatch.lap();
+		record!("process.pid", server.id());
+		record!("server.address", host.to_string().as_str());
 
-		#[cfg(windows)]
-		{
-			hosts.push(ServerAddress::Tcp {
-				host: "localhost".parse().unwrap(),
-				port: Some(mongodb_port),
-			});
-		}
+		let tls_options = tls.as_ref().map(|tls| {
+			TlsOptions::builder()
+				.ca_file_path(tls.ca_file().to_owned())
+				.cert_key_file_path(tls.client_pem().map(Path::to_owned))
+				.build()
+		});
+		let client_options = |credential: Option<Credential>| {
+			ClientOptions::builder()
+				.hosts(vec![host.clone()])
+				.tls(tls_options.clone().map(Tls::Enabled))
+				.credential(credential)
+				.connect_timeout(Duration::from_millis(100))
+				.direct_connection(true)
+				.build()
+		};
+		// A server that exited, like on an invalid argument, is reported with its exit status, so the startup is not retried.
+		let with_server_output = |server: &mut KillOnDrop, e: Error| e.with_server_output(server_output_tail(&output_path, &log_path), server.try_wait().ok().flatten());
+		let connect_error = |server: &mut KillOnDrop, e| with_server_output(server, ErrorInner::Connect(server_address.clone(), e).into());
 
-		let client_options = ClientOptions::builder()
-			.hosts(hosts)
-			.connect_timeout(Duration::from_millis(100))
-			.direct_connection(true)
-			.build();
+		let x509_credential = builder.x509_auth.then(|| {
+			Credential::builder()
+				.mechanism(AuthMechanism::MongoDbX509)
+				.build()
+		});
 
-		let client = mongodb::Client::with_options(client_options.clone())
-			.map_err(|e| ErrorInner::Connect(server_address.clone(), e))?;
+		// The first user is created with the localhost exception, which only allows access until a user exists.
+		// The second user is created by the first one.
+		let mut creator = None;
+		if let Some(credential) = &x509_credential {
+			create_root_user(client_options(creator), "$external", CLIENT_USERNAME, None).await.map_err(|e| with_server_output(&mut server, e))?;
+			creator = Some(credential.clone());
+		}
+		if let Some(credentials) = &builder.credentials {
+			create_root_user(client_options(creator), "admin", &credentials.username, Some(credentials.password.expose())).await.map_err(|e| with_server_output(&mut server, e))?;
+		}
+
+		// Prefer the password credentials, so the client exercises the most common authentication mechanism.
+		let credential = builder.credentials.as_ref().map(Credentials::to_credential).or(x509_credential);
+		let mut client_options = client_options(credential);
+		let command_recorder = builder.record_commands.then(|| Arc::new(CommandRecorder::default()));
+		if let Some(recorder) = &command_recorder {
+			client_options.command_event_handler = Some(recorder.clone());
+		}
+		let pool_monitor = Arc::new(PoolMonitor::default());
+		client_options.cmap_event_handler = Some(pool_monitor.clone());
+		let heartbeat_monitor = Arc::new(HeartbeatMonitor::default());
+		client_options.sdam_event_handler = Some(heartbeat_monitor.clone());
+		client_options.heartbeat_freq = builder.heartbeat_frequency;
+		let client = mongodb::Client::with_options(client_options.clone()).map_err(|e| connect_error(&mut server, e))?;
 
+		event!(debug, target: "temp_mongo::spawn", "waiting for the server at {server_address} to accept connections");
 		client
 			.list_databases(None, None)
 			.await
-			.map_err(|e| ErrorInner::Connect(server_address, e))?;
+			.map_err(|e| connect_error(&mut server, e))?;
+		event!(info, target: "temp_mongo::spawn", "server at {server_address} is ready");
+		startup_metrics.ready = stopwatch.lap();
+
+		// The warnings are only diagnostics, so failing to read them does not fail the startup.
+		let startup_warnings = startup_warnings(&client).await.unwrap_or_default();
+		for _warning in &startup_warnings {
+			event!(warn, target: "temp_mongo::spawn", "startup warning: {_warning}");
+		}
+
+		// Saved files are named after the temporary directory, so they are unique.
+		let name = tempdir.path().file_name().unwrap_or_default().to_string_lossy();
+		let name = format!("mongod-{}", name.trim_start_matches('.'));
+		let saved_log = builder.save_logs_to.as_ref().map(|directory| SavedLog::new(&log_path, directory, &name));
+		let server_status_path = match &builder.save_server_status_to {
+			Some(directory) => {
+				std::fs::create_dir_all(directory).map_err(|e| ErrorInner::WriteServerStatus(directory.clone(), e))?;
+				Some(directory.join(format!("{name}-server-status.json")))
+			},
+			None => None,
+		};
 
-		Ok(Self {
+		let mut mongo = Self {
+			saved_log,
+			server_status_path,
+			startup_metrics,
+			startup_warnings,
+			ready_at: DateTime::now(),
+			hooks: builder.hooks.clone(),
+			command_recorder,
+			pool_monitor,
+			heartbeat_monitor,
 			tempdir,
+			keep_temp_dir: builder.keep_temp_dir,
+			reused_data,
+			db_path: db_dir,
+			port,
 			socket_path,
+			tls,
+			credentials: builder.credentials.clone(),
 			log_path,
+			audit_log_path,
 			server,
 			client,
+			client_options: Box::new(client_options),
+			query_analysis: builder.query_analysis.clone(),
+			cryptd: None,
 			seed,
-		})
+			profiles: builder.profiles.clone(),
+		};
+		run_hooks(&builder.hooks.on_created, &mongo).await;
+
+		if let Some(QueryAnalysis::Mongocryptd) = &builder.query_analysis {
+			let cryptd_builder = TempMongoCryptd::builder()
+				.clean_on_drop(builder.clean_on_drop && !builder.keep_temp_dir);
+			mongo.cryptd = Some(cryptd_builder.spawn().await?);
+		}
+		mongo.startup_metrics.cryptd = stopwatch.lap();
+
+		if let Some(name) = &builder.profile {
+			mongo.load_profile(name).await?;
+		}
+		mongo.startup_metrics.seed = stopwatch.lap();
+		mongo.startup_metrics.total = stopwatch.total();
+
+		run_hooks(&builder.hooks.on_ready, &mongo).await;
+		mongo.clear_commands_executed();
+		mongo.ready_at = DateTime::now();
+
+		Ok(mongo)
 	}
 }
+
+/// Create a user with the `root` role in the given authentication database.
+///
+/// Users without a password are created in the `$external` database, like the x.509 user.
+///
+/// If the data directory is reused, the user may already exist.
+/// Then the localhost exception no longer applies and creating the user is not authorized.
+/// Both errors are ignored: if the existing users do not match, the client fails to authenticate later.
+async fn create_root_user(client_options: ClientOptions, database: &str, username: &str, password: Option<&str>) -> Result<(), Error> {
+	/// The error code for a user that already exists.
+	const USER_EXISTS: i32 = 51003;
+
+	/// The error code for an unauthorized command.
+	const UNAUTHORIZED: i32 = 13;
+
+	let client = mongodb::Client::with_options(client_options)
+		.map_err(|e| ErrorInner::CreateUser(username.to_owned(), e))?;
+	let mut command = doc! {
+		"createUser": username,
+		"roles": [{ "role": "root", "db": "admin" }],
+	};
+	if let Some(password) = password {
+		command.insert("pwd", password);
+	}
+	let result = client.database(database).run_command(command, None).await;
+	client.shutdown_immediate().await;
+	match result {
+		Ok(_) => Ok(()),
+		Err(e) if matches!(&*e.kind, mongodb::error::ErrorKind::Command(command) if command.code == USER_EXISTS || command.code == UNAUTHORIZED) => Ok(()),
+		Err(e) => Err(ErrorInner::CreateUser(username.to_owned(), e).into()),
+	}
+}
+
 /// Builder for customizing your [`TempMongo`] object.
 ///
 /// After configuring the desirec options, run [`TempMongoBuilder::spawn()`].
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 
 pub struct TempMongoBuilder {
 	/// The parent directory for the temporary directory.
@@ -292,8 +1294,103 @@ pub struct TempMongoBuilder {
 	/// Clean up the temprorary directory when the [`TempMongo`] object is dropped.
 	clean_on_drop: bool,
 
+	/// Leave the temporary directory on the filesystem, even in [`TempMongo::kill_and_clean()`].
+	keep_temp_dir: bool,
+
+	/// The data directory to use instead of a subdirectory of the temporary directory.
+	data_dir: Option<PathBuf>,
+
+	/// The name of the persistent data directory to use instead of a subdirectory of the temporary directory.
+	persistent: Option<String>,
+
 	/// The mongdb command to execute.
 	command: Option<OsString>,
+
+	/// The registered seed profiles.
+	profiles: BTreeMap<String, Manifest>,
+
+	/// The seed profile to load after the server is started.
+	profile: Option<String>,
+
+	/// The configuration file to pass to `mongod`.
+	config: Option<ConfigSource>,
+
+	/// Pass extra arguments to `mongod` that make it start and run faster.
+	fast_startup: bool,
+
+	/// The maximum size of the WiredTiger cache in gigabytes.
+	///
+	/// Use the `mongod` default if set to `None`.
+	wired_tiger_cache_size_gb: Option<f64>,
+
+	/// The storage engine.
+	///
+	/// Use the `mongod` default if set to `None`.
+	storage_engine: Option<String>,
+
+	/// Suppress most log output of `mongod`.
+	quiet: bool,
+
+	/// The log verbosity level of `mongod`, from 0 to 5.
+	verbosity: u8,
+
+	/// Disable server-side JavaScript execution.
+	no_scripting: bool,
+
+	/// The server parameters to set with `--setParameter`.
+	parameters: BTreeMap<String, String>,
+
+	/// The resource limits for `mongod`.
+	resource_limits: ResourceLimits,
+
+	/// The number of times to restart `mongod` on a new port if its port was taken.
+	port_retries: u32,
+
+	/// Require TLS with generated certificates.
+	tls: bool,
+
+	/// Enable authentication with a generated x.509 client certificate.
+	x509_auth: bool,
+
+	/// Enable authentication and create a root user with these credentials.
+	credentials: Option<Credentials>,
+
+	/// The query analysis component for automatic encryption.
+	///
+	/// Automatic encryption is disabled if set to `None`.
+	query_analysis: Option<QueryAnalysis>,
+
+	/// Write audit events to a JSON file in the temporary directory.
+	audit_log: bool,
+
+	/// The network interfaces to listen on for TCP connections.
+	///
+	/// Only listen on a Unix socket on Unix, or on the loopback adapter otherwise, if set to `None`.
+	bind_ip: Option<BindIp>,
+
+	/// The directory to save the log of the server to on clean-up.
+	///
+	/// The log is not saved if set to `None`.
+	save_logs_to: Option<PathBuf>,
+
+	/// The directory to write a `serverStatus` snapshot to on clean-up.
+	///
+	/// No snapshot is taken if set to `None`.
+	save_server_status_to: Option<PathBuf>,
+
+	/// The level of the database profiler and the slow operation threshold in milliseconds.
+	///
+	/// Use the `mongod` defaults if set to `None`.
+	profiling: Option<(ProfilingLevel, u64)>,
+
+	/// The registered lifecycle hooks.
+	hooks: LifecycleHooks,
+
+	/// Record the commands executed by the client.
+	record_commands: bool,
+
+	/// The interval between heartbeats of the server monitor of the client.
+	heartbeat_frequency: Option<Duration>,
 }
 
 impl TempMongoBuilder {
@@ -301,8 +1398,35 @@ impl TempMongoBuilder {
 	pub fn new() -> Self {
 		Self {
 			parent_directory: None,
-			command: None,
+			command: std::env::var_os("TEMP_MONGO_MONGOD").filter(|command| !command.is_empty()),
 			clean_on_drop: true,
+			keep_temp_dir: false,
+			data_dir: None,
+			persistent: None,
+			profiles: BTreeMap::new(),
+			profile: None,
+			config: None,
+			fast_startup: false,
+			wired_tiger_cache_size_gb: None,
+			storage_engine: None,
+			quiet: false,
+			verbosity: 0,
+			no_scripting: false,
+			parameters: BTreeMap::new(),
+			resource_limits: ResourceLimits::new(),
+			port_retries: DEFAULT_PORT_RETRIES,
+			tls: false,
+			x509_auth: false,
+			credentials: default_credentials(),
+			query_analysis: None,
+			audit_log: false,
+			bind_ip: None,
+			save_logs_to: None,
+			save_server_status_to: None,
+			profiling: None,
+			hooks: LifecycleHooks::default(),
+			record_commands: false,
+			heartbeat_frequency: None,
 		}
 	}
 
@@ -311,6 +1435,73 @@ impl TempMongoBuilder {
 		TempMongo::from_builder(self).await
 	}
 
+	/// Spawn the MongoDB server and connect to it, recreating it if it fails to start because of the infrastructure.
+	///
+	/// Failures like a port taken by another process or a server that did not accept connections
+	/// are retried with a new temporary directory up to the number of attempts of the policy, see [`Error::is_infrastructure()`].
+	/// Other errors, like a missing seed profile, and the error of the last attempt are returned as is.
+	///
+	/// This reduces flaky test failures on shared CI machines, without hiding mistakes in the tests.
+	pub async fn spawn_with_retries(&self, policy: RetryPolicy) -> Result<TempMongo, Error> {
+		let mut attempt = 1;
+		loop {
+			match TempMongo::from_builder(self).await {
+				Err(e) if e.is_infrastructure() && attempt < policy.attempts() => {
+					let delay = policy.delay_after(attempt);
+					event!(warn, target: "temp_mongo::spawn", "attempt {attempt} to start the server failed, retrying in {delay:?}: {e}");
+					sleep(delay).await;
+					attempt += 1;
+				},
+				result => return result,
+			}
+		}
+	}
+
+	/// Run a test against each of the given MongoDB versions in turn, using the options of this builder.
+	///
+	/// For each version, the binaries are downloaded with [`MongoDownloader`], or taken from the binary cache if they were downloaded before.
+	/// Then a server is spawned with the downloaded `mongod` and passed to the test.
+	/// The server is cleaned up when the test drops it, before the next version is started.
+	///
+	/// Versions must be complete release versions, like `"7.0.5"`.
+	/// The command set with [`Self::mongod_command()`] is ignored.
+	///
+	/// Downloading blocks the current thread.
+	/// Stops at the first version that fails to download or start.
+	pub async fn for_each_version<F, Fut>(&self, versions: &[&str], mut test: F) -> Result<(), Error>
+	where
+		F: FnMut(TempMongo) -> Fut,
+		Fut: Future<Output = ()>,
+	{
+		for version in versions {
+			let binaries = MongoDownloader::new(*version).download()?;
+			let mongo = self.clone().mongod_command(binaries.mongod()).spawn().await?;
+			test(mongo).await;
+		}
+		Ok(())
+	}
+
+	/// Spawn a new instance, run a closure with it, and always clean it up afterwards.
+	///
+	/// The closure returns a boxed future, so it can borrow the [`TempMongo`] object, like `|mongo| Box::pin(async move { ... })`.
+	/// When the future finishes, the instance is cleaned up with [`TempMongo::kill_and_clean()`] and the output of the future is returned.
+	/// If the future panics, the instance is cleaned up before the panic is resumed.
+	///
+	/// Returns an error if the instance could not be spawned or cleaned up.
+	/// Errors of the closure itself can be returned as its output.
+	pub async fn with<F, T>(&self, test: F) -> Result<T, Error>
+	where
+		F: for<'a> FnOnce(&'a TempMongo) -> BoxFuture<'a, T>,
+	{
+		let mongo = self.spawn().await?;
+		let output = AssertUnwindSafe(test(&mongo)).catch_unwind().await;
+		let cleanup = mongo.kill_and_clean().await;
+		match output {
+			Ok(output) => cleanup.map(|()| output),
+			Err(panic) => std::panic::resume_unwind(panic),
+		}
+	}
+
 	/// Enable or disable cleaning of the temporary state directory when the [`TempMongo`] object is dropped.
 	///
 	/// This can also be changed after creation with [`TempMongo::set_clean_on_drop()`].
@@ -319,14 +1510,387 @@ impl TempMongoBuilder {
 		self
 	}
 
+	/// Keep the temporary state directory on the filesystem for inspection after the server is stopped.
+	///
+	/// When enabled, the directory is neither removed when the [`TempMongo`] object is dropped,
+	/// nor by [`TempMongo::kill_and_clean()`].
+	pub fn keep_temp_dir(mut self, keep_temp_dir: bool) -> Self {
+		self.keep_temp_dir = keep_temp_dir;
+		self
+	}
+
+	/// Use an existing directory as data directory, instead of a new subdirectory of the temporary directory.
+	///
+	/// The directory is created if it does not exist.
+	/// It is never removed, so it can be used to persist data between runs or to start from a prepared data set.
+	/// The log file and the socket are still placed in the temporary state directory.
+	///
+	/// This replaces any persistent data directory set with [`Self::persistent()`].
+	pub fn data_dir(mut self, path: impl Into<PathBuf>) -> Self {
+		self.data_dir = Some(path.into());
+		self.persistent = None;
+		self
+	}
+
+	/// Use a named persistent data directory that is reused across runs.
+	///
+	/// The directory is stored as `<name>` in the `temp-mongo-data` directory next to the binary cache, like `~/.cache/temp-mongo-data/<name>` on Linux,
+	/// so pruning the binary cache never removes it.
+	/// Like a directory set with [`Self::data_dir()`], it is created if it does not exist and it is never removed.
+	/// This can be used to test how an application handles existing data, or to keep a warm development database between sessions.
+	/// Use [`TempMongo::reused_data()`] to check if the directory contained data from a previous run.
+	///
+	/// Only one server can use a data directory at a time.
+	/// This replaces any data directory set with [`Self::data_dir()`].
+	pub fn persistent(mut self, name: impl Into<String>) -> Self {
+		self.persistent = Some(name.into());
+		self.data_dir = None;
+		self
+	}
+
 	/// Overwrite the `mongod` command to run.
 	///
 	/// Can be used to run a `mongod` binary from an alternative location.
+	/// Defaults to the value of the `TEMP_MONGO_MONGOD` environment variable if it is set, or `mongod` otherwise.
 	pub fn mongod_command(mut self, command: impl Into<OsString>) -> Self {
 		self.command = Some(command.into());
 		self
 	}
 
+	/// Pass an existing configuration file to `mongod`.
+	///
+	/// Options that are passed on the command line, like the data directory and the port, take precedence over the configuration file.
+	/// This replaces any configuration set with [`Self::config()`].
+	pub fn config_file(mut self, path: impl Into<PathBuf>) -> Self {
+		self.config = Some(ConfigSource::File(path.into()));
+		self
+	}
+
+	/// Pass a configuration to `mongod`, written to a file in the temporary directory.
+	///
+	/// Options that are passed on the command line, like the data directory and the port, take precedence over the configuration.
+	/// This replaces any configuration file set with [`Self::config_file()`].
+	pub fn config(mut self, config: MongodConfig) -> Self {
+		self.config = Some(ConfigSource::Inline(config));
+		self
+	}
+
+	/// Enable or disable the fast startup profile.
+	///
+	/// The profile disables periodic checkpoints and diagnostic data collection,
+	/// and disables journaling on `mongod` versions that still support it (before 6.1).
+	/// This makes the server start and run noticeably faster, at the cost of durability that tests do not need.
+	/// Parameters set with [`Self::set_parameter()`], like `diagnosticDataCollectionEnabled`, take precedence over the profile.
+	pub fn fast_startup(mut self, fast_startup: bool) -> Self {
+		self.fast_startup = fast_startup;
+		self
+	}
+
+	/// Set the maximum size of the WiredTiger cache in gigabytes.
+	///
+	/// By default, `mongod` uses half of the system memory minus one gigabyte,
+	/// which is wasteful when many temporary instances run in parallel and can cause them to be killed for running out of memory.
+	/// The minimum is 0.25 gigabytes.
+	pub fn wired_tiger_cache_size_gb(mut self, size: f64) -> Self {
+		self.wired_tiger_cache_size_gb = Some(size);
+		self
+	}
+
+	/// Set the storage engine, like `inMemory` or `ephemeralForTest`.
+	///
+	/// A purely in-memory storage engine makes temporary instances faster,
+	/// but availability depends on the `mongod` build:
+	/// `inMemory` is only available in MongoDB Enterprise and Percona Server,
+	/// and `ephemeralForTest` was removed in MongoDB 7.0.
+	pub fn storage_engine(mut self, engine: impl Into<String>) -> Self {
+		self.storage_engine = Some(engine.into());
+		self
+	}
+
+	/// Suppress most log output of `mongod`, by passing `--quiet`.
+	///
+	/// This keeps the log files small when many instances run in a test suite.
+	pub fn quiet(mut self, quiet: bool) -> Self {
+		self.quiet = quiet;
+		self
+	}
+
+	/// Set the log verbosity level of `mongod`, from 0 to 5.
+	///
+	/// A level above 0 passes `-v` flags, which is useful for debugging the server itself.
+	/// Levels above 5 are treated as 5.
+	pub fn verbosity(mut self, level: u8) -> Self {
+		self.verbosity = level;
+		self
+	}
+
+	/// Disable server-side JavaScript execution of `mongod`, by passing `--noscripting`.
+	///
+	/// Many production deployments use this hardened configuration.
+	/// Operations that run JavaScript on the server, like `$where`, `$function` and `$accumulator`, fail with this option.
+	pub fn no_scripting(mut self, no_scripting: bool) -> Self {
+		self.no_scripting = no_scripting;
+		self
+	}
+
+	/// Set a server parameter of `mongod`, passed as `--setParameter <key>=<value>`.
+	///
+	/// Can be called multiple times to set multiple parameters, like `enableTestCommands=1` or `ttlMonitorSleepSecs=1`.
+	/// Setting the same parameter again replaces the previous value.
+	pub fn set_parameter(mut self, key: impl Into<String>, value: impl ToString) -> Self {
+		self.parameters.insert(key.into(), value.to_string());
+		self
+	}
+
+	/// Set resource limits for the `mongod` process.
+	///
+	/// Resource limits are only supported on Unix, they are ignored on other platforms.
+	pub fn resource_limits(mut self, limits: ResourceLimits) -> Self {
+		self.resource_limits = limits;
+		self
+	}
+
+	/// Set the number of times to restart `mongod` on a new port if its port was taken by another process.
+	///
+	/// On Windows, and on Unix with TLS enabled, a free TCP port is selected before `mongod` is started, so another process may bind to it first.
+	/// Otherwise, the server only listens on a Unix socket in the temporary directory, so this option has no effect.
+	/// Defaults to 3.
+	pub fn port_retries(mut self, retries: u32) -> Self {
+		self.port_retries = retries;
+		self
+	}
+
+	/// Require TLS for all connections, with generated self-signed certificates.
+	///
+	/// A certificate authority and a server certificate for `localhost` and `127.0.0.1` are generated in the temporary directory,
+	/// using the `openssl` command, which must be available in `PATH`.
+	/// The server is started with `--tlsMode requireTLS` and listens on a TCP port on the loopback adapter, also on Unix.
+	/// The client of the [`TempMongo`] object trusts the certificate authority,
+	/// and its path is available from [`TempMongo::tls_ca_file()`] to configure other clients.
+	pub fn tls(mut self, tls: bool) -> Self {
+		self.tls = tls;
+		self
+	}
+
+	/// Enable authentication with a generated x.509 client certificate.
+	///
+	/// This implies [`Self::tls()`].
+	/// A client certificate is generated next to the server certificate,
+	/// and a `$external` user with the `root` role is created for its subject, see [`TempMongo::x509_username()`].
+	/// The server is started with authentication enabled,
+	/// and the client of the [`TempMongo`] object authenticates with the client certificate.
+	/// The path of the certificate is available from [`TempMongo::tls_client_pem()`] to configure other clients.
+	pub fn x509_auth(mut self, x509_auth: bool) -> Self {
+		self.x509_auth = x509_auth;
+		self
+	}
+
+	/// Enable authentication and create a root user with a random username and password.
+	///
+	/// The credentials are generated when this function is called,
+	/// so all servers spawned from this builder share them.
+	/// The client of the [`TempMongo`] object authenticates as the root user,
+	/// and the credentials are available from [`TempMongo::credentials()`] to configure other clients.
+	///
+	/// The user is created in the `admin` database, so other clients must use `admin` as authentication source.
+	/// Note that a reused persistent data directory keeps the users of the previous run,
+	/// so the new credentials can not be used to authenticate.
+	///
+	/// With the `auth-by-default` feature, this is the default.
+	pub fn with_random_credentials(mut self) -> Self {
+		self.credentials = Some(Credentials::random());
+		self
+	}
+
+	/// Configure the server like a locked-down production server.
+	///
+	/// This is a shortcut for these options:
+	/// * [`Self::with_random_credentials()`], unless credentials are already set,
+	/// * [`Self::tls()`],
+	/// * [`Self::no_scripting()`],
+	/// * [`Self::bind_ip()`] with [`BindIp::Loopback`].
+	///
+	/// Options set after this call override the profile.
+	pub fn production_like(mut self) -> Self {
+		if self.credentials.is_none() {
+			self = self.with_random_credentials();
+		}
+		self.tls(true)
+			.no_scripting(true)
+			.bind_ip(BindIp::Loopback)
+	}
+
+	/// Disable authentication with a root user, even with the `auth-by-default` feature.
+	///
+	/// Authentication may still be enabled with [`Self::x509_auth()`].
+	pub fn without_auth(mut self) -> Self {
+		self.credentials = None;
+		self
+	}
+
+	/// Set the network interfaces the server listens on for TCP connections.
+	///
+	/// By default, the server only listens on a Unix socket in the temporary directory on Unix,
+	/// and on the loopback adapter on Windows or with TLS enabled.
+	/// With this option, the server also listens on a free TCP port on the given interfaces on Unix, see [`TempMongo::port()`].
+	/// The client of the [`TempMongo`] object connects to the first address of [`BindIp::Addresses`], or to the loopback adapter otherwise.
+	///
+	/// Note that the generated TLS certificate is only valid for `localhost` and `127.0.0.1`.
+	pub fn bind_ip(mut self, bind_ip: BindIp) -> Self {
+		self.bind_ip = Some(bind_ip);
+		self
+	}
+
+	/// Enable auditing to a JSON file in the temporary directory.
+	///
+	/// All audit events are written to the file, without filter.
+	/// Use [`TempMongo::audit_events()`] to read the parsed events after the test operations ran.
+	/// Note that auditing requires MongoDB Enterprise or Percona Server for MongoDB:
+	/// the community server refuses to start with this option.
+	pub fn audit_log(mut self, audit_log: bool) -> Self {
+		self.audit_log = audit_log;
+		self
+	}
+
+	/// Save the log of the server to a file in the given directory when it is cleaned up.
+	///
+	/// The log is copied in [`TempMongo::kill_and_clean()`], or when the [`TempMongo`] object is dropped while the thread is panicking,
+	/// for example because an assertion in a test failed.
+	/// The file is named after the temporary directory, like `mongod-tmpAbC123.log`, see [`TempMongo::saved_log_path()`].
+	/// This is useful to upload the logs of failed tests as CI artifacts.
+	pub fn save_logs_to(mut self, directory: impl Into<PathBuf>) -> Self {
+		self.save_logs_to = Some(directory.into());
+		self
+	}
+
+	/// Write a snapshot of the `serverStatus` command to a file in the given directory just before the server is killed.
+	///
+	/// The snapshot is taken in [`TempMongo::kill_and_clean()`], and written as relaxed extended JSON
+	/// to a file named like the saved log, with a `-server-status.json` suffix, see [`TempMongo::server_status_path()`].
+	/// Use [`TempMongo::server_status()`] to inspect the statistics in the test itself.
+	pub fn save_server_status_to(mut self, directory: impl Into<PathBuf>) -> Self {
+		self.save_server_status_to = Some(directory.into());
+		self
+	}
+
+	/// Enable the database profiler for all databases.
+	///
+	/// With [`ProfilingLevel::SlowOperations`], only operations that take longer than `slow_ms` milliseconds are recorded.
+	/// The threshold also applies to the slow operations that are written to the log.
+	/// Use [`TempMongo::slow_query_report()`] to get the slowest recorded operations after the test operations ran.
+	pub fn enable_profiling(mut self, level: ProfilingLevel, slow_ms: u64) -> Self {
+		self.profiling = Some((level, slow_ms));
+		self
+	}
+
+	/// Enable the database profiler for all operations, to count them with [`TempMongo::operation_summary()`].
+	///
+	/// This is a shortcut for `enable_profiling(ProfilingLevel::All, 100)`, where 100 milliseconds is the default slow operation threshold.
+	/// Profiling all operations slows down the server, so only enable it for tests that assert on the operations.
+	pub fn count_operations(self) -> Self {
+		self.enable_profiling(ProfilingLevel::All, 100)
+	}
+
+	/// Enable or disable recording the commands executed by the client, see [`TempMongo::commands_executed()`].
+	///
+	/// This can be used to assert which commands a code path issues, like exactly one `find`.
+	pub fn record_commands(mut self, record_commands: bool) -> Self {
+		self.record_commands = record_commands;
+		self
+	}
+
+	/// Set the interval between heartbeats of the server monitor of the client, see [`TempMongo::heartbeats()`].
+	///
+	/// The driver default is 10 seconds, and the minimum is 500 milliseconds.
+	/// A shorter interval makes the client notice injected failures sooner.
+	pub fn heartbeat_frequency(mut self, frequency: Duration) -> Self {
+		self.heartbeat_frequency = Some(frequency);
+		self
+	}
+
+	/// Register a hook that is called as soon as the server accepts connections.
+	///
+	/// The hook is called before `mongocryptd` is spawned and before the seed profile is loaded.
+	/// Multiple hooks are called in the order they were registered.
+	///
+	/// ```no_run
+	/// # #[cfg_attr(feature = "tokio-runtime", tokio::main)]
+	/// # #[cfg_attr(feature = "async-std-runtime", async_std::main)]
+	/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+	/// use temp_mongo::TempMongo;
+	///
+	/// let mongo = TempMongo::builder()
+	///     .on_created(|mongo| Box::pin(async move {
+	///         println!("Server started in {}", mongo.directory().display());
+	///     }))
+	///     .spawn()
+	///     .await?;
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn on_created<F>(mut self, hook: F) -> Self
+	where
+		F: for<'a> Fn(&'a TempMongo) -> BoxFuture<'a, ()> + Send + Sync + 'static,
+	{
+		self.hooks.on_created.push(Arc::new(hook));
+		self
+	}
+
+	/// Register a hook that is called when the server is completely set up, right before [`Self::spawn()`] returns.
+	///
+	/// Multiple hooks are called in the order they were registered.
+	pub fn on_ready<F>(mut self, hook: F) -> Self
+	where
+		F: for<'a> Fn(&'a TempMongo) -> BoxFuture<'a, ()> + Send + Sync + 'static,
+	{
+		self.hooks.on_ready.push(Arc::new(hook));
+		self
+	}
+
+	/// Register a hook that is called in [`TempMongo::kill_and_clean()`], [`TempMongo::shutdown_and_clean()`] and [`TempMongo::kill_no_clean()`],
+	/// while the server is still running.
+	///
+	/// The hook is not called when the [`TempMongo`] object is dropped, since [`Drop`] can not run async code.
+	/// Multiple hooks are called in the order they were registered.
+	pub fn on_before_cleanup<F>(mut self, hook: F) -> Self
+	where
+		F: for<'a> Fn(&'a TempMongo) -> BoxFuture<'a, ()> + Send + Sync + 'static,
+	{
+		self.hooks.on_before_cleanup.push(Arc::new(hook));
+		self
+	}
+
+	/// Provision the query analysis component for automatic client-side field level encryption.
+	///
+	/// With [`QueryAnalysis::Mongocryptd`], a temporary `mongocryptd` process is spawned when the server is spawned,
+	/// and it is killed together with the server, see [`TempMongo::cryptd()`].
+	/// With [`QueryAnalysis::CryptShared`], clients load the `crypt_shared` library from the given path.
+	///
+	/// Use [`TempMongo::auto_encryption_extra_options()`] to configure a client for automatic encryption,
+	/// or `TempMongo::encrypted_client()` with the `csfle` feature.
+	/// Note that automatic encryption requires MongoDB Enterprise or Atlas.
+	pub fn with_csfle(mut self, query_analysis: QueryAnalysis) -> Self {
+		self.query_analysis = Some(query_analysis);
+		self
+	}
+
+	/// Register a named seed profile.
+	///
+	/// Seed profiles allow a test suite to trade startup time against data richness per test,
+	/// for example with a "minimal" and a "full" profile.
+	/// Select the profile to load with [`Self::with_profile()`],
+	/// or load it after creation with [`TempMongo::load_profile()`].
+	pub fn seed_profile(mut self, name: impl Into<String>, manifest: Manifest) -> Self {
+		self.profiles.insert(name.into(), manifest);
+		self
+	}
+
+	/// Select a registered seed profile to load when the server is spawned.
+	pub fn with_profile(mut self, name: impl Into<String>) -> Self {
+		self.profile = Some(name.into());
+		self
+	}
+
 	/// Get the command to execute to run MongoDB.
 	pub fn get_command(&self) -> &OsStr {
 		self.command.as_deref().unwrap_or("mongod".as_ref())
@@ -339,9 +1903,10 @@ impl TempMongoBuilder {
 
 	/// Create a temporary directory according to the configuration of the builder.
 	fn make_temp_dir(&self) -> std::io::Result<TempDir> {
+		let clean_on_drop = self.clean_on_drop && !self.keep_temp_dir;
 		match &self.parent_directory {
-			Some(dir) => TempDir::new_in(dir, self.clean_on_drop),
-			None => TempDir::new(self.clean_on_drop),
+			Some(dir) => TempDir::new_in(dir, clean_on_drop),
+			None => TempDir::new(clean_on_drop),
 		}
 	}
 }
@@ -0,0 +1,439 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::error::ErrorInner;
+use crate::util::{render_compose, ComposeService, Credentials};
+use crate::{Error, MemberOptions};
+use crate::{TempMongo, TempMongoBuilder};
+use crate::{TempMongoReplicaSet, TempMongoReplicaSetBuilder};
+use crate::{TempMongoSharded, TempMongoShardedBuilder};
+
+/// A declarative description of a temporary MongoDB deployment.
+///
+/// A topology can be constructed in code or loaded from a JSON file, for example:
+/// ```json
+/// {
+///   "type": "replica_set",
+///   "name": "rs0",
+///   "members": [
+///     { "tags": { "dc": "east" } },
+///     { "tags": { "dc": "west" } },
+///     { "hidden": true, "secondary_delay_secs": 60 }
+///   ],
+///   "arbiter": false
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Topology {
+	/// A single standalone `mongod` server.
+	Standalone(StandaloneTopology),
+
+	/// A replica set.
+	ReplicaSet(ReplicaSetTopology),
+
+	/// A sharded cluster.
+	Sharded(ShardedTopology),
+}
+
+/// Description of a standalone server.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct StandaloneTopology {
+	/// The `mongod` command to execute.
+	pub mongod_command: Option<String>,
+}
+
+/// Description of a replica set.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ReplicaSetTopology {
+	/// The `mongod` command to execute.
+	pub mongod_command: Option<String>,
+
+	/// The name of the replica set.
+	pub name: String,
+
+	/// The data bearing members of the replica set.
+	pub members: Vec<MemberTopology>,
+
+	/// Add an arbiter to the replica set.
+	pub arbiter: bool,
+
+	/// The root user to create, which also enables authentication.
+	pub auth: Option<Credentials>,
+}
+
+/// Description of a single data bearing replica set member.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MemberTopology {
+	/// The replica set tags of the member.
+	pub tags: BTreeMap<String, String>,
+
+	/// Hide the member from clients.
+	pub hidden: bool,
+
+	/// The election priority of the member.
+	pub priority: Option<f64>,
+
+	/// Delay replication to the member by this many seconds.
+	///
+	/// Spawned replica sets also support servers older than MongoDB 5.0,
+	/// but [`Topology::to_compose_yaml()`] always uses `secondaryDelaySecs`, which requires MongoDB 5.0 or newer.
+	pub secondary_delay_secs: Option<u64>,
+}
+
+/// Description of a sharded cluster.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ShardedTopology {
+	/// The `mongod` command to execute.
+	pub mongod_command: Option<String>,
+
+	/// The `mongos` command to execute.
+	pub mongos_command: Option<String>,
+
+	/// The number of shards.
+	pub shards: usize,
+
+	/// The number of members of each shard replica set.
+	pub shard_members: usize,
+
+	/// The number of members of the config server replica set.
+	pub config_members: usize,
+
+	/// The number of `mongos` routers.
+	pub routers: usize,
+
+	/// The databases to enable sharding on.
+	pub sharded_databases: Vec<String>,
+}
+
+/// A running deployment spawned from a [`Topology`].
+#[derive(Debug)]
+// A deployment is spawned once and rarely moved, so the size of the variants does not matter.
+#[allow(clippy::large_enum_variant)]
+pub enum TempMongoTopology {
+	/// A standalone server.
+	Standalone(TempMongo),
+
+	/// A replica set.
+	ReplicaSet(TempMongoReplicaSet),
+
+	/// A sharded cluster.
+	Sharded(TempMongoSharded),
+}
+
+impl Topology {
+	/// Parse a topology from a JSON string.
+	pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+		serde_json::from_str(json)
+	}
+
+	/// Read and parse a topology from a JSON file.
+	pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+		let path = path.as_ref();
+		let data = std::fs::read_to_string(path)
+			.map_err(|e| ErrorInner::ReadTopology(path.to_owned(), e))?;
+		let topology = Self::from_json(&data)
+			.map_err(|e| ErrorInner::ParseTopology(path.to_owned(), e))?;
+		Ok(topology)
+	}
+
+	/// Render the described deployment as a docker-compose file, for long-running local use of a configuration proven in tests.
+	///
+	/// All services run the given image, like `mongo:7.0`, which must include `mongosh`.
+	/// The commands set in the description are ignored.
+	/// The services are attached to a network named `temp-mongo`, and each server stores its data in a named volume.
+	/// A standalone server is published on port 27017 of the host.
+	/// The members of a replica set are published on consecutive ports starting at 27017, and so are the routers of a sharded cluster.
+	/// Replica sets are initiated and sharded clusters are set up by a one-off `init` service.
+	///
+	/// The members of a replica set know each other by their service name,
+	/// so clients on the host should connect to a single member with `directConnection=true`.
+	/// The root user of a replica set is created, but access control is not enforced, since that requires a keyfile.
+	pub fn to_compose_yaml(&self, image: &str) -> String {
+		let services = match self {
+			Self::Standalone(_) => vec![mongod_service(image, "mongodb", 0, "/data/db", &[])],
+			Self::ReplicaSet(topology) => topology.compose_services(image),
+			Self::Sharded(topology) => topology.compose_services(image),
+		};
+		render_compose(&services)
+	}
+
+	/// Spawn the described deployment.
+	pub async fn spawn(&self) -> Result<TempMongoTopology, Error> {
+		match self {
+			Self::Standalone(topology) => Ok(TempMongoTopology::Standalone(topology.builder().spawn().await?)),
+			Self::ReplicaSet(topology) => Ok(TempMongoTopology::ReplicaSet(topology.builder().spawn().await?)),
+			Self::Sharded(topology) => Ok(TempMongoTopology::Sharded(topology.builder().spawn().await?)),
+		}
+	}
+}
+
+impl StandaloneTopology {
+	/// Create a builder configured according to the description.
+	pub fn builder(&self) -> TempMongoBuilder {
+		let mut builder = TempMongoBuilder::new();
+		if let Some(command) = &self.mongod_command {
+			builder = builder.mongod_command(command);
+		}
+		builder
+	}
+}
+
+impl Default for ReplicaSetTopology {
+	fn default() -> Self {
+		Self {
+			mongod_command: None,
+			name: "rs0".into(),
+			members: vec![MemberTopology::default(); 3],
+			arbiter: false,
+			auth: None,
+		}
+	}
+}
+
+impl ReplicaSetTopology {
+	/// Create a builder configured according to the description.
+	pub fn builder(&self) -> TempMongoReplicaSetBuilder {
+		let mut builder = TempMongoReplicaSetBuilder::new()
+			.name(&self.name)
+			.members(self.members.len())
+			.arbiter(self.arbiter);
+		for (index, member) in self.members.iter().enumerate() {
+			builder = builder.member_options(index, member.to_options());
+		}
+		if let Some(command) = &self.mongod_command {
+			builder = builder.mongod_command(command);
+		}
+		if let Some(credentials) = &self.auth {
+			builder = builder.auth(&credentials.username, credentials.password.expose());
+		}
+		builder
+	}
+}
+
+impl ReplicaSetTopology {
+	/// Get the docker-compose services of the members and the service that initiates the replica set.
+	fn compose_services(&self, image: &str) -> Vec<ComposeService> {
+		let replica_set_args = ["--replSet", self.name.as_str()];
+		let mut services: Vec<_> = (0..self.members.len())
+			.map(|index| mongod_service(image, &format!("{}-{index}", self.name), Some(index), "/data/db", &replica_set_args))
+			.collect();
+		if self.arbiter {
+			services.push(mongod_service(image, &format!("{}-arbiter", self.name), None, "/data/db", &replica_set_args));
+		}
+
+		let mut members: Vec<_> = self
+			.members
+			.iter()
+			.enumerate()
+			.map(|(index, member)| member.to_member_config(index, &format!("{}-{index}:27017", self.name)))
+			.collect();
+		if self.arbiter {
+			let index = self.members.len();
+			members.push(serde_json::json!({ "_id": index, "host": format!("{}-arbiter:27017", self.name), "arbiterOnly": true }));
+		}
+		let config = serde_json::json!({ "_id": self.name, "members": members });
+
+		let primary = format!("{}-0:27017", self.name);
+		let mut script = format!("{INIT_RETRY}\nretry(() => new Mongo(\"{primary}\").getDB(\"admin\").runCommand({{ replSetInitiate: {config} }}));\n");
+		if let Some(credentials) = &self.auth {
+			let user = serde_json::json!({ "user": credentials.username, "pwd": credentials.password.expose(), "roles": ["root"] });
+			script.push_str(&format!(
+				"retry(() => {{ const admin = new Mongo(\"{primary}\").getDB(\"admin\"); if (!admin.hello().isWritablePrimary) throw new Error(\"no primary yet\"); admin.createUser({user}); }});\n"
+			));
+		}
+		let init = init_service(image, &format!("{}-init", self.name), &services, script);
+		services.push(init);
+		services
+	}
+}
+
+impl MemberTopology {
+	/// Get the configuration of the member for `replSetInitiate`.
+	fn to_member_config(&self, index: usize, host: &str) -> serde_json::Value {
+		let mut config = serde_json::json!({ "_id": index, "host": host });
+		if !self.tags.is_empty() {
+			config["tags"] = serde_json::json!(self.tags);
+		}
+		if self.hidden {
+			config["hidden"] = true.into();
+		}
+		// Hidden and delayed members must not become primary.
+		match self.priority {
+			Some(priority) => config["priority"] = priority.into(),
+			None if self.hidden || self.secondary_delay_secs.is_some() => config["priority"] = 0.into(),
+			None => (),
+		}
+		if let Some(delay) = self.secondary_delay_secs {
+			config["secondaryDelaySecs"] = delay.into();
+		}
+		config
+	}
+
+	/// Convert the description to member options.
+	pub fn to_options(&self) -> MemberOptions {
+		MemberOptions {
+			tags: self.tags.clone(),
+			hidden: self.hidden,
+			priority: self.priority,
+			secondary_delay: self.secondary_delay_secs.map(Duration::from_secs),
+		}
+	}
+}
+
+impl Default for ShardedTopology {
+	fn default() -> Self {
+		Self {
+			mongod_command: None,
+			mongos_command: None,
+			shards: 2,
+			shard_members: 1,
+			config_members: 1,
+			routers: 1,
+			sharded_databases: Vec::new(),
+		}
+	}
+}
+
+impl ShardedTopology {
+	/// Create a builder configured according to the description.
+	pub fn builder(&self) -> TempMongoShardedBuilder {
+		let mut builder = TempMongoShardedBuilder::new()
+			.shards(self.shards)
+			.shard_members(self.shard_members)
+			.config_members(self.config_members)
+			.routers(self.routers);
+		for database in &self.sharded_databases {
+			builder = builder.enable_sharding(database);
+		}
+		if let Some(command) = &self.mongod_command {
+			builder = builder.mongod_command(command);
+		}
+		if let Some(command) = &self.mongos_command {
+			builder = builder.mongos_command(command);
+		}
+		builder
+	}
+}
+
+impl ShardedTopology {
+	/// Get the docker-compose services of the config servers, the shards, the routers and the service that sets up the cluster.
+	fn compose_services(&self, image: &str) -> Vec<ComposeService> {
+		let mut services = Vec::new();
+		let mut script = format!("{INIT_RETRY}\n");
+		let mut replica_set = |name: &str, members: usize, role: &str, data_path: &str, services: &mut Vec<ComposeService>| {
+			let hosts: Vec<_> = (0..members).map(|index| format!("{name}-{index}:27017")).collect();
+			for index in 0..members {
+				let args = [role, "--replSet", name, "--port", "27017"];
+				services.push(mongod_service(image, &format!("{name}-{index}"), None, data_path, &args));
+			}
+			let members: Vec<_> = hosts
+				.iter()
+				.enumerate()
+				.map(|(index, host)| serde_json::json!({ "_id": index, "host": host }))
+				.collect();
+			let config = serde_json::json!({ "_id": name, "configsvr": role == "--configsvr", "members": members });
+			script.push_str(&format!(
+				"retry(() => new Mongo(\"{}\").getDB(\"admin\").runCommand({{ replSetInitiate: {config} }}));\n",
+				hosts[0]
+			));
+			format!("{name}/{}", hosts.join(","))
+		};
+
+		let config_servers = replica_set("config", self.config_members.max(1), "--configsvr", "/data/configdb", &mut services);
+		let shards: Vec<_> = (0..self.shards)
+			.map(|index| replica_set(&format!("shard{index}"), self.shard_members.max(1), "--shardsvr", "/data/db", &mut services))
+			.collect();
+
+		for index in 0..self.routers.max(1) {
+			services.push(ComposeService {
+				name: format!("mongos-{index}"),
+				image: image.into(),
+				command: ["mongos", "--configdb", &config_servers, "--bind_ip_all", "--port", "27017"].map(String::from).into(),
+				ports: vec![format!("{}:27017", 27017 + index)],
+				depends_on: services.iter().map(|service| service.name.clone()).collect(),
+				..Default::default()
+			});
+		}
+
+		let admin = "new Mongo(\"mongos-0:27017\").getDB(\"admin\")";
+		for shard in shards {
+			script.push_str(&format!("retry(() => {admin}.runCommand({{ addShard: \"{shard}\" }}));\n"));
+		}
+		for database in &self.sharded_databases {
+			let database = serde_json::Value::from(database.as_str());
+			script.push_str(&format!("retry(() => {admin}.runCommand({{ enableSharding: {database} }}));\n"));
+		}
+		let init = init_service(image, "init", &services, script);
+		services.push(init);
+		services
+	}
+}
+
+/// A `mongosh` helper that retries a function until it succeeds, or fails because the replica set or user already exists.
+const INIT_RETRY: &str = "function retry(f) { for (;;) { try { return f(); } catch (e) { if (e.code === 23 || e.code === 51003) return; sleep(1000); } } }";
+
+/// Create the docker-compose service of a `mongod` server.
+///
+/// If `port_offset` is set, the server is published on port `27017 + port_offset` of the host.
+fn mongod_service(image: &str, name: &str, port_offset: impl Into<Option<usize>>, data_path: &str, args: &[&str]) -> ComposeService {
+	let mut command = vec!["mongod".to_owned(), "--bind_ip_all".to_owned()];
+	command.extend(args.iter().map(|arg| arg.to_string()));
+	ComposeService {
+		name: name.into(),
+		image: image.into(),
+		command,
+		ports: port_offset.into().map(|offset| format!("{}:27017", 27017 + offset)).into_iter().collect(),
+		volumes: vec![format!("{name}-data:{data_path}")],
+		..Default::default()
+	}
+}
+
+/// Create a one-off docker-compose service that runs a `mongosh` script after the given services are started.
+fn init_service(image: &str, name: &str, services: &[ComposeService], script: String) -> ComposeService {
+	ComposeService {
+		name: name.into(),
+		image: image.into(),
+		command: vec!["mongosh".into(), "--nodb".into(), "--quiet".into(), "--eval".into(), script],
+		depends_on: services.iter().map(|service| service.name.clone()).collect(),
+		one_off: true,
+		..Default::default()
+	}
+}
+
+impl TempMongoTopology {
+	/// Get a client for the deployment.
+	///
+	/// For a replica set this is a replica set aware client, and for a sharded cluster it is connected to the routers.
+	pub fn client(&self) -> &mongodb::Client {
+		match self {
+			Self::Standalone(mongo) => mongo.client(),
+			Self::ReplicaSet(replica_set) => replica_set.client(),
+			Self::Sharded(cluster) => cluster.client(),
+		}
+	}
+
+	/// Get the path of the temporary state directory.
+	pub fn directory(&self) -> &Path {
+		match self {
+			Self::Standalone(mongo) => mongo.directory(),
+			Self::ReplicaSet(replica_set) => replica_set.directory(),
+			Self::Sharded(cluster) => cluster.directory(),
+		}
+	}
+
+	/// Kill all processes and remove the temporary state directory on the filesystem.
+	pub async fn kill_and_clean(self) -> Result<(), Error> {
+		match self {
+			Self::Standalone(mongo) => mongo.kill_and_clean().await,
+			Self::ReplicaSet(replica_set) => replica_set.kill_and_clean().await,
+			Self::Sharded(cluster) => cluster.kill_and_clean().await,
+		}
+	}
+}
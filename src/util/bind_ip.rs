@@ -0,0 +1,45 @@
+use std::ffi::OsString;
+
+/// The network interfaces a server listens on for TCP connections.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BindIp {
+	/// Only listen on the loopback adapter.
+	Loopback,
+
+	/// Listen on the given IP addresses or host names.
+	///
+	/// The client of the temporary instance connects to the first address.
+	Addresses(Vec<String>),
+
+	/// Listen on all interfaces, with `--bind_ip_all`.
+	///
+	/// This exposes the server to the network, which is only safe with authentication enabled.
+	All,
+}
+
+impl BindIp {
+	/// Get the `mongod` arguments to listen on these interfaces.
+	///
+	/// The `loopback` address is used for [`BindIp::Loopback`], and is always included for [`BindIp::Addresses`] if `include_loopback` is set.
+	pub fn args(&self, loopback: &str, include_loopback: bool) -> Vec<OsString> {
+		match self {
+			Self::Loopback => vec!["--bind_ip".into(), loopback.into()],
+			Self::Addresses(addresses) => {
+				let mut addresses = addresses.clone();
+				if include_loopback && !addresses.iter().any(|address| address == loopback) {
+					addresses.push(loopback.to_owned());
+				}
+				vec!["--bind_ip".into(), addresses.join(",").into()]
+			},
+			Self::All => vec!["--bind_ip_all".into()],
+		}
+	}
+
+	/// Get the address for clients to connect to.
+	pub fn client_address<'a>(&'a self, loopback: &'a str) -> &'a str {
+		match self {
+			Self::Addresses(addresses) => addresses.first().map(String::as_str).unwrap_or(loopback),
+			Self::Loopback | Self::All => loopback,
+		}
+	}
+}
@@ -0,0 +1,73 @@
+use mongodb::event::command::{CommandEventHandler, CommandFailedEvent, CommandStartedEvent, CommandSucceededEvent};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A command executed by a client, as recorded by command monitoring.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutedCommand {
+	/// The name of the command, like `find` or `insert`.
+	pub name: String,
+
+	/// The database the command was executed on.
+	pub database: String,
+
+	/// The time until the reply was received.
+	pub duration: Duration,
+
+	/// The error message, if the command failed.
+	pub failure: Option<String>,
+}
+
+/// A command event handler that records all finished commands.
+#[derive(Debug, Default)]
+pub struct CommandRecorder {
+	/// The database of each started command that did not finish yet, by request ID.
+	started: Mutex<HashMap<i32, String>>,
+
+	/// The finished commands, in the order they finished.
+	executed: Mutex<Vec<ExecutedCommand>>,
+}
+
+impl CommandRecorder {
+	/// Get the commands that finished so far.
+	pub fn executed(&self) -> Vec<ExecutedCommand> {
+		self.executed.lock().map(|executed| executed.clone()).unwrap_or_default()
+	}
+
+	/// Forget all commands that finished so far.
+	pub fn clear(&self) {
+		if let Ok(mut executed) = self.executed.lock() {
+			executed.clear();
+		}
+	}
+
+	/// Record a finished command.
+	fn finish(&self, request_id: i32, name: String, duration: Duration, failure: Option<String>) {
+		let database = self
+			.started
+			.lock()
+			.ok()
+			.and_then(|mut started| started.remove(&request_id))
+			.unwrap_or_default();
+		if let Ok(mut executed) = self.executed.lock() {
+			executed.push(ExecutedCommand { name, database, duration, failure });
+		}
+	}
+}
+
+impl CommandEventHandler for CommandRecorder {
+	fn handle_command_started_event(&self, event: CommandStartedEvent) {
+		if let Ok(mut started) = self.started.lock() {
+			started.insert(event.request_id, event.db);
+		}
+	}
+
+	fn handle_command_succeeded_event(&self, event: CommandSucceededEvent) {
+		self.finish(event.request_id, event.command_name, event.duration, None);
+	}
+
+	fn handle_command_failed_event(&self, event: CommandFailedEvent) {
+		self.finish(event.request_id, event.command_name, event.duration, Some(event.failure.to_string()));
+	}
+}
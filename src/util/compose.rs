@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+
+/// The name of the network that all services of a rendered compose file are attached to.
+const NETWORK: &str = "temp-mongo";
+
+/// A service of a docker-compose file.
+#[derive(Debug, Clone, Default)]
+pub struct ComposeService {
+	/// The name of the service, which is also its host name on the network.
+	pub name: String,
+
+	/// The image to run.
+	pub image: String,
+
+	/// The command and its arguments.
+	pub command: Vec<String>,
+
+	/// The published ports, as `host:container`.
+	pub ports: Vec<String>,
+
+	/// The environment variables.
+	pub environment: BTreeMap<String, String>,
+
+	/// The mounted volumes, as `volume:path`.
+	pub volumes: Vec<String>,
+
+	/// The services that must be started first.
+	pub depends_on: Vec<String>,
+
+	/// If the service is a one-off task that is not restarted when it exits.
+	pub one_off: bool,
+}
+
+/// Render services as a docker-compose file.
+///
+/// All services are attached to a single network, and all named volumes are declared.
+pub fn render_compose(services: &[ComposeService]) -> String {
+	let mut yaml = String::from("services:\n");
+	let mut volumes = Vec::new();
+	for service in services {
+		yaml.push_str(&format!("  {}:\n", service.name));
+		yaml.push_str(&format!("    image: {}\n", quote(&service.image)));
+		yaml.push_str(&format!("    hostname: {}\n", quote(&service.name)));
+		render_list(&mut yaml, "command", &service.command);
+		render_list(&mut yaml, "ports", &service.ports);
+		if !service.environment.is_empty() {
+			yaml.push_str("    environment:\n");
+			for (name, value) in &service.environment {
+				yaml.push_str(&format!("      {name}: {}\n", quote(value)));
+			}
+		}
+		render_list(&mut yaml, "volumes", &service.volumes);
+		render_list(&mut yaml, "depends_on", &service.depends_on);
+		yaml.push_str(&format!("    networks:\n      - {NETWORK}\n"));
+		if !service.one_off {
+			yaml.push_str("    restart: unless-stopped\n");
+		}
+		volumes.extend(service.volumes.iter().filter_map(|volume| volume.split_once(':')).map(|(name, _path)| name));
+	}
+
+	yaml.push_str(&format!("networks:\n  {NETWORK}: {{}}\n"));
+	if !volumes.is_empty() {
+		yaml.push_str("volumes:\n");
+		for volume in volumes {
+			yaml.push_str(&format!("  {volume}: {{}}\n"));
+		}
+	}
+	yaml
+}
+
+/// Render a list of strings, if it is not empty.
+fn render_list(yaml: &mut String, key: &str, items: &[String]) {
+	if items.is_empty() {
+		return;
+	}
+	yaml.push_str(&format!("    {key}:\n"));
+	for item in items {
+		yaml.push_str(&format!("      - {}\n", quote(item)));
+	}
+}
+
+/// Quote a string for YAML, which accepts JSON strings.
+///
+/// Dollar signs are doubled, so docker-compose does not interpolate variables in the value.
+fn quote(value: &str) -> String {
+	serde_json::Value::from(value.replace('$', "$$")).to_string()
+}
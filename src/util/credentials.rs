@@ -0,0 +1,105 @@
+use crate::error::ErrorInner;
+use crate::util::SecretString;
+use crate::Error;
+use mongodb::bson::doc;
+use mongodb::options::Credential;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::Deserialize;
+
+/// The number of random characters in a generated username.
+const RANDOM_USERNAME_LENGTH: usize = 8;
+
+/// The number of characters in a generated password.
+const RANDOM_PASSWORD_LENGTH: usize = 32;
+
+/// Username and password of a user in a temporary MongoDB instance.
+///
+/// The password is redacted in the [`Debug`] output.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Credentials {
+	/// The name of the user.
+	pub username: String,
+
+	/// The password of the user.
+	pub password: SecretString,
+}
+
+impl Credentials {
+	/// Create new credentials from a username and password.
+	pub fn new(username: impl Into<String>, password: impl Into<String>) -> Self {
+		Self {
+			username: username.into(),
+			password: SecretString::new(password),
+		}
+	}
+
+	/// Generate credentials with a random username and password.
+	///
+	/// The username starts with `user-`, followed by random alphanumeric characters.
+	/// The password consists of random alphanumeric characters.
+	pub fn random() -> Self {
+		Self {
+			username: format!("user-{}", random_string(RANDOM_USERNAME_LENGTH)),
+			password: SecretString::new(random_password()),
+		}
+	}
+
+	/// Convert the credentials to a driver [`Credential`] that authenticates against the `admin` database.
+	pub fn to_credential(&self) -> Credential {
+		Credential::builder()
+			.username(self.username.clone())
+			.password(self.password.expose().to_owned())
+			.source("admin".to_string())
+			.build()
+	}
+
+	/// Format the credentials as the user info part of a connection string, including the trailing `@`.
+	pub fn to_user_info(&self) -> String {
+		format!("{}:{}@", percent_encode(&self.username), percent_encode(self.password.expose()))
+	}
+}
+
+/// Get the default credentials for a new builder.
+///
+/// With the `auth-by-default` feature, these are random credentials, so authentication is enabled by default.
+/// Otherwise, authentication is disabled by default.
+pub fn default_credentials() -> Option<Credentials> {
+	cfg!(feature = "auth-by-default").then(Credentials::random)
+}
+
+/// Generate a random alphanumeric password.
+pub fn random_password() -> String {
+	random_string(RANDOM_PASSWORD_LENGTH)
+}
+
+/// Change the password of a user in the `admin` database.
+pub async fn update_password(client: &mongodb::Client, username: &str, password: &str) -> Result<(), Error> {
+	client
+		.database("admin")
+		.run_command(doc! { "updateUser": username, "pwd": password }, None)
+		.await
+		.map_err(|e| ErrorInner::UpdateUser(username.to_owned(), e))?;
+	Ok(())
+}
+
+/// Generate a string of random alphanumeric characters.
+fn random_string(length: usize) -> String {
+	rand::thread_rng()
+		.sample_iter(&Alphanumeric)
+		.take(length)
+		.map(char::from)
+		.collect()
+}
+
+/// Percent-encode all characters that are not unreserved in a URI.
+fn percent_encode(input: &str) -> String {
+	let mut output = String::with_capacity(input.len());
+	for byte in input.bytes() {
+		match byte {
+			b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => output.push(byte as char),
+			_ => output.push_str(&format!("%{byte:02X}")),
+		}
+	}
+	output
+}
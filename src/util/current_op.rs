@@ -0,0 +1,79 @@
+use crate::error::ErrorInner;
+use crate::Error;
+use mongodb::bson::{doc, Bson, Document};
+use std::time::Duration;
+
+/// An operation in progress, from the output of the `currentOp` command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CurrentOp {
+	/// The ID of the operation, which can be passed to `killOp`.
+	///
+	/// This is `None` for idle connections and some internal threads.
+	pub opid: Option<i64>,
+
+	/// The type of the operation, like `query`, `insert` or `command`.
+	pub operation: String,
+
+	/// The namespace of the operation, like `test.animals`.
+	pub namespace: String,
+
+	/// The command of the operation.
+	pub command: Document,
+
+	/// The operation is active, rather than idle.
+	pub active: bool,
+
+	/// The time the operation has been running, if it is active.
+	pub running: Option<Duration>,
+
+	/// The description of the client or internal thread, like `conn12`.
+	pub description: String,
+
+	/// The full `currentOp` document of the operation.
+	pub raw: Document,
+}
+
+impl CurrentOp {
+	/// Parse an entry of the `inprog` array of a `currentOp` reply.
+	pub fn from_document(raw: Document) -> Self {
+		let string = |name: &str| raw.get_str(name).unwrap_or_default().to_owned();
+		let number = |name: &str| match raw.get(name) {
+			Some(Bson::Int32(value)) => Some((*value).into()),
+			Some(Bson::Int64(value)) => Some(*value),
+			Some(Bson::Double(value)) => Some(*value as i64),
+			_ => None,
+		};
+		Self {
+			opid: number("opid"),
+			operation: string("op"),
+			namespace: string("ns"),
+			command: raw.get_document("command").cloned().unwrap_or_default(),
+			active: raw.get_bool("active").unwrap_or(false),
+			running: number("microsecs_running").and_then(|micros| Some(Duration::from_micros(micros.try_into().ok()?))),
+			description: string("desc"),
+			raw,
+		}
+	}
+}
+
+/// Get the operations in progress that match a filter.
+///
+/// The filter is added to the `currentOp` command, so it can use the fields of the output, like `{ "ns": "test.animals" }`,
+/// and the special fields `$all` and `$ownOps`.
+pub async fn current_ops(client: &mongodb::Client, filter: Document) -> Result<Vec<CurrentOp>, Error> {
+	let mut command = doc! { "currentOp": 1 };
+	command.extend(filter);
+	let reply = client
+		.database("admin")
+		.run_command(command, None)
+		.await
+		.map_err(ErrorInner::CurrentOp)?;
+	let operations = reply
+		.get_array("inprog")
+		.map_err(|e| ErrorInner::CurrentOp(mongodb::error::Error::custom(e.to_string())))?
+		.iter()
+		.filter_map(Bson::as_document)
+		.map(|operation| CurrentOp::from_document(operation.clone()))
+		.collect();
+	Ok(operations)
+}
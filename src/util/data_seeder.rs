@@ -1,10 +1,16 @@
 use serde::Deserialize;
-use mongodb::{Client, bson::Document};
+use mongodb::{Client, Collection, bson::Document};
+use mongodb::options::{Collation, CollectionOptions, CreateCollectionOptions, ReadConcern, WriteConcern};
+use std::path::Path;
+
+use crate::util::event;
+use crate::Error;
+use super::seed_file::{parse_bson_seed_file, parse_json_seed_file};
 
 /// Data seed options for mongodb instance
 /// 
 /// The database_name and collection_name are used to specify the database and collection to seed the data into
-#[derive(Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct DataSeeder {
 	/// The name of the database to seed.
 	pub database_name: String,
@@ -12,6 +18,18 @@ pub struct DataSeeder {
 	pub collection_name: String,
 	/// The documents to be seeded into the collection.
 	pub documents: Vec<Document>,
+	/// The write concern used when inserting the documents, set with [`Self::write_concern()`].
+	///
+	/// Uses the server default if set to `None`.
+	write_concern: Option<WriteConcern>,
+	/// The read concern used when verifying the seeded documents, set with [`Self::read_concern()`].
+	///
+	/// Uses the server default if set to `None`.
+	read_concern: Option<ReadConcern>,
+	/// The default collation of the collection, set with [`Self::collation()`].
+	///
+	/// If set, the collection is created with this collation before the documents are inserted.
+	collation: Option<Collation>,
 }
 
 impl DataSeeder {
@@ -25,6 +43,9 @@ impl DataSeeder {
 			database_name: String::new(),
 			collection_name: String::new(),
 			documents: Vec::new(),
+			write_concern: None,
+			read_concern: None,
+			collation: None,
 		}
 	}
 
@@ -44,6 +65,9 @@ impl DataSeeder {
 			database_name: database_name.to_string(),
 			collection_name: collection_name.to_string(),
 			documents,
+			write_concern: None,
+			read_concern: None,
+			collation: None,
 		}
 	}
 
@@ -58,14 +82,99 @@ impl DataSeeder {
 	/// # Returns
 	///
 	/// Returns an instance of `SeedData` with the specified database and collection names, and the provided documents.
+	#[allow(clippy::ptr_arg)]
 	pub fn new_in_with_string(&self, database_name: &String, collection_name: &String, documents: Vec<Document>) -> Self {
 		Self {
 			database_name: database_name.clone(),
 			collection_name: collection_name.clone(),
 			documents,
+			..Self::new()
 		}
 	}
 
+	/// Creates a new `SeedData` instance with the documents from a JSON file.
+	///
+	/// The file must contain an array of documents in (extended) JSON format,
+	/// as written by [`TempMongo::export_collection()`](crate::TempMongo::export_collection).
+	/// Files with a `.gz` extension are decompressed automatically.
+	///
+	/// # Arguments
+	///
+	/// * `database_name` - The name of the database to seed.
+	/// * `collection_name` - The name of the collection to seed.
+	/// * `path` - The path of the JSON file to read.
+	pub fn from_json_file(database_name: &str, collection_name: &str, path: impl AsRef<Path>) -> Result<Self, Error> {
+		let documents = parse_json_seed_file(path.as_ref())?;
+		Ok(Self::new().new_in(database_name, collection_name, documents))
+	}
+
+	/// Creates a new `SeedData` instance with the documents from a BSON file.
+	///
+	/// The file must contain a sequence of BSON documents, as written by `mongodump`.
+	/// Files with a `.gz` extension are decompressed automatically, so the output of `mongodump --gzip` can be used directly.
+	///
+	/// # Arguments
+	///
+	/// * `database_name` - The name of the database to seed.
+	/// * `collection_name` - The name of the collection to seed.
+	/// * `path` - The path of the BSON file to read.
+	pub fn from_bson_file(database_name: &str, collection_name: &str, path: impl AsRef<Path>) -> Result<Self, Error> {
+		let documents = parse_bson_seed_file(path.as_ref())?;
+		Ok(Self::new().new_in(database_name, collection_name, documents))
+	}
+
+	/// Set the write concern used when inserting the documents.
+	///
+	/// For example, use `w: "majority"` to wait for the documents to be replicated to a majority of a replica set.
+	pub fn write_concern(mut self, write_concern: WriteConcern) -> Self {
+		self.write_concern = Some(write_concern);
+		self
+	}
+
+	/// Set the read concern used when verifying the seeded documents.
+	pub fn read_concern(mut self, read_concern: ReadConcern) -> Self {
+		self.read_concern = Some(read_concern);
+		self
+	}
+
+	/// Set the default collation of the seeded collection.
+	///
+	/// This can be used to test locale-sensitive sorting and matching, for example with a case-insensitive collation.
+	///
+	/// The collation can only be set when a collection is created,
+	/// so seeding fails with a `NamespaceExists` error if the collection already exists.
+	pub fn collation(mut self, collation: Collation) -> Self {
+		self.collation = Some(collation);
+		self
+	}
+
+	/// Create the collection with the configured collation, if any.
+	///
+	/// Fails if the collection already exists, since its collation can not be changed.
+	async fn create_collection(&self, client: &Client) -> mongodb::error::Result<()> {
+		let Some(collation) = &self.collation else {
+			return Ok(());
+		};
+		let options = CreateCollectionOptions::builder()
+			.collation(collation.clone())
+			.write_concern(self.write_concern.clone())
+			.build();
+		client
+			.database(&self.database_name)
+			.create_collection(&self.collection_name, options)
+			.await
+	}
+
+	/// Get a handle to the collection to seed, configured with the write and read concern of the seeder.
+	fn collection(&self, client: &Client) -> Collection<Document> {
+		let options = CollectionOptions::builder()
+			.write_concern(self.write_concern.clone())
+			.read_concern(self.read_concern.clone())
+			.build();
+		client
+			.database(&self.database_name)
+			.collection_with_options(&self.collection_name, options)
+	}
 
 	/// Seeds the specified MongoDB collection with the provided documents.
 	///
@@ -76,11 +185,41 @@ impl DataSeeder {
 	/// # Errors
 	///
 	/// Returns an error if any MongoDB operation fails during the seeding process.
+	#[cfg_attr(feature = "tracing", tracing::instrument(target = "temp_mongo::seed", skip_all, fields(database = %self.database_name, collection = %self.collection_name)))]
 	pub async fn seed_document(&self, client: &Client) -> mongodb::error::Result<()> {
-		let collection = client.database(&self.database_name).collection(&self.collection_name);
+		self.create_collection(client).await?;
+		let collection = self.collection(client);
 		for document in &self.documents {
 			collection.insert_one(document.clone(), None).await?;
 		}
+		event!(debug, target: "temp_mongo::seed", "seeded {} documents into {}.{}", self.documents.len(), self.database_name, self.collection_name);
 		Ok(())
 	}
+
+	/// Verifies that all documents of the seeder are present in the collection.
+	///
+	/// The verification reads use the read concern of the seeder.
+	///
+	/// # Arguments
+	///
+	/// * `client` - A reference to the MongoDB client to use for reading documents.
+	///
+	/// # Errors
+	///
+	/// Returns an error if any MongoDB operation fails during the verification process.
+	pub async fn verify_document(&self, client: &Client) -> mongodb::error::Result<bool> {
+		let collection = self.collection(client);
+		for document in &self.documents {
+			if collection.count_documents(document.clone(), None).await? == 0 {
+				return Ok(false);
+			}
+		}
+		Ok(true)
+	}
+}
+
+impl Default for DataSeeder {
+	fn default() -> Self {
+		Self::new()
+	}
 }
@@ -0,0 +1,58 @@
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+/// The maximum length of a database name that MongoDB accepts.
+const MAX_LENGTH: usize = 63;
+
+/// The names that were handed out in this process, to detect collisions between tests.
+static ISSUED: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+/// Derive a database name for the current test from a run ID and the name of the current thread.
+///
+/// The test harness of `cargo test` names the thread of each test after the test, like `tests::insert_dog`,
+/// so each test gets a predictable database.
+/// Characters that are not allowed in database names are replaced by underscores.
+/// Names that are too long are shortened, with a hash of the full name to keep them unique.
+///
+/// If the same name was already handed out in this process, for example to another database of the same test,
+/// a numeric suffix like `_2` is added.
+pub fn isolated_database_name(run_id: &str) -> String {
+	let thread = std::thread::current();
+	let test = match thread.name() {
+		Some(name) => name.to_owned(),
+		None => format!("{:?}", thread.id()),
+	};
+	let base = shorten(&sanitize(&format!("{run_id}_{test}")), 0);
+
+	let mut issued = ISSUED.lock().unwrap_or_else(|e| e.into_inner());
+	let issued = issued.get_or_insert_with(HashSet::new);
+	let mut name = base.clone();
+	let mut count = 1;
+	while !issued.insert(name.clone()) {
+		count += 1;
+		let suffix = format!("_{count}");
+		name = shorten(&base, suffix.len()) + &suffix;
+	}
+	name
+}
+
+/// Replace all characters that are not allowed in database names, or that are awkward in shells, by underscores.
+fn sanitize(name: &str) -> String {
+	name.chars()
+		.map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+		.collect()
+}
+
+/// Shorten a name so it fits in a database name together with a suffix of the given length.
+///
+/// A shortened name ends with a hash of the full name, so different long names stay different.
+fn shorten(name: &str, suffix_length: usize) -> String {
+	let max_length = MAX_LENGTH - suffix_length;
+	if name.len() <= max_length {
+		return name.to_owned();
+	}
+	let hash = format!("{:x}", Sha256::digest(name.as_bytes()));
+	let hash = &hash[..8];
+	format!("{}_{hash}", &name[..max_length - hash.len() - 1])
+}
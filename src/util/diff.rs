@@ -0,0 +1,224 @@
+use futures_util::stream::TryStreamExt;
+use mongodb::bson::Document;
+use mongodb::Client;
+
+use super::Manifest;
+
+/// Options for comparing the contents of a database with a [`Manifest`].
+#[derive(Debug, Clone)]
+pub struct DiffOptions {
+	/// Top-level fields that are removed from all documents before they are compared.
+	///
+	/// By default only `_id` is ignored.
+	pub ignore_fields: Vec<String>,
+}
+
+impl DiffOptions {
+	/// Create new options that ignore the `_id` field.
+	pub fn new() -> Self {
+		Self {
+			ignore_fields: vec!["_id".to_string()],
+		}
+	}
+
+	/// Ignore an additional field, such as a timestamp that is generated at insertion time.
+	pub fn ignore_field(mut self, field: impl Into<String>) -> Self {
+		self.ignore_fields.push(field.into());
+		self
+	}
+
+	/// Enable or disable ignoring the `_id` field.
+	pub fn ignore_id(mut self, ignore_id: bool) -> Self {
+		self.ignore_fields.retain(|field| field != "_id");
+		if ignore_id {
+			self.ignore_fields.push("_id".to_string());
+		}
+		self
+	}
+
+	/// Remove the ignored fields from a document.
+	pub(crate) fn strip(&self, mut document: Document) -> Document {
+		for field in &self.ignore_fields {
+			document.remove(field);
+		}
+		document
+	}
+}
+
+impl Default for DiffOptions {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// The differences between the expected and actual contents of a database.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DatabaseDiff {
+	/// The name of the compared database.
+	pub database_name: String,
+	/// The differences per collection, only for collections that differ.
+	pub collections: Vec<CollectionDiff>,
+}
+
+/// The differences between the expected and actual contents of a collection.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CollectionDiff {
+	/// The name of the compared collection.
+	pub collection_name: String,
+	/// Expected documents that have no counterpart in the collection.
+	pub missing: Vec<Document>,
+	/// Documents in the collection that were not expected.
+	pub extra: Vec<Document>,
+	/// Documents that were found, but with different field values.
+	pub mismatched: Vec<FieldMismatch>,
+}
+
+/// An expected document and the closest actual document, with the fields that differ.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldMismatch {
+	/// The expected document.
+	pub expected: Document,
+	/// The actual document in the collection.
+	pub actual: Document,
+	/// The top-level fields that are missing, extra or have a different value.
+	pub fields: Vec<String>,
+}
+
+impl DatabaseDiff {
+	/// Compare the contents of a database with the seeds in a manifest.
+	///
+	/// Only collections of the manifest that are seeded into `database_name` are compared.
+	///
+	/// # Errors
+	///
+	/// Returns an error if any MongoDB operation fails while reading the collections.
+	pub async fn compute(client: &Client, database_name: &str, expected: &Manifest, options: &DiffOptions) -> mongodb::error::Result<Self> {
+		let mut collections: Vec<(&str, Vec<Document>)> = Vec::new();
+		for seed in expected.seeds.iter().filter(|seed| seed.database_name == database_name) {
+			let documents = seed.documents.iter().cloned().map(|document| options.strip(document));
+			match collections.iter_mut().find(|(name, _)| *name == seed.collection_name) {
+				Some((_, existing)) => existing.extend(documents),
+				None => collections.push((&seed.collection_name, documents.collect())),
+			}
+		}
+
+		let database = client.database(database_name);
+		let mut diff = Self {
+			database_name: database_name.to_string(),
+			collections: Vec::new(),
+		};
+		for (name, expected) in collections {
+			let actual: Vec<Document> = database
+				.collection::<Document>(name)
+				.find(None, None)
+				.await?
+				.try_collect()
+				.await?;
+			let actual = actual.into_iter().map(|document| options.strip(document)).collect();
+			let collection = CollectionDiff::compute(name, expected, actual);
+			if !collection.is_empty() {
+				diff.collections.push(collection);
+			}
+		}
+		Ok(diff)
+	}
+
+	/// Check if the database matched the expected contents.
+	pub fn is_empty(&self) -> bool {
+		self.collections.is_empty()
+	}
+}
+
+impl CollectionDiff {
+	/// Compare the expected documents of a collection with the actual documents.
+	pub fn compute(collection_name: &str, expected: Vec<Document>, mut actual: Vec<Document>) -> Self {
+		let mut diff = Self {
+			collection_name: collection_name.to_string(),
+			..Self::default()
+		};
+
+		// Remove all exact matches first, so they can not be paired with a different document.
+		let mut unmatched = Vec::new();
+		for document in expected {
+			match actual.iter().position(|other| *other == document) {
+				Some(index) => {
+					actual.remove(index);
+				},
+				None => unmatched.push(document),
+			}
+		}
+
+		// Pair the remaining documents with the actual document that shares the most field values.
+		for document in unmatched {
+			let closest = actual
+				.iter()
+				.enumerate()
+				.map(|(index, other)| (index, shared_fields(&document, other)))
+				.filter(|(_, shared)| *shared > 0)
+				.max_by_key(|(_, shared)| *shared);
+			match closest {
+				Some((index, _)) => {
+					let actual = actual.remove(index);
+					let fields = differing_fields(&document, &actual);
+					diff.mismatched.push(FieldMismatch { expected: document, actual, fields });
+				},
+				None => diff.missing.push(document),
+			}
+		}
+
+		diff.extra = actual;
+		diff
+	}
+
+	/// Check if the collection matched the expected contents.
+	pub fn is_empty(&self) -> bool {
+		self.missing.is_empty() && self.extra.is_empty() && self.mismatched.is_empty()
+	}
+}
+
+/// Count the top-level fields that have the same value in both documents.
+fn shared_fields(a: &Document, b: &Document) -> usize {
+	a.iter().filter(|(key, value)| b.get(key.as_str()) == Some(value)).count()
+}
+
+/// Get the top-level fields that are missing, extra or have a different value.
+fn differing_fields(expected: &Document, actual: &Document) -> Vec<String> {
+	let mut fields: Vec<String> = expected
+		.iter()
+		.filter(|(key, value)| actual.get(key.as_str()) != Some(value))
+		.map(|(key, _)| key.clone())
+		.collect();
+	fields.extend(actual.keys().filter(|key| !expected.contains_key(key.as_str())).cloned());
+	fields
+}
+
+impl std::fmt::Display for DatabaseDiff {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		if self.is_empty() {
+			return write!(f, "database {} matches the expected contents", self.database_name);
+		}
+		writeln!(f, "database {} differs from the expected contents:", self.database_name)?;
+		for collection in &self.collections {
+			write!(f, "{collection}")?;
+		}
+		Ok(())
+	}
+}
+
+impl std::fmt::Display for CollectionDiff {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		writeln!(f, "collection {}:", self.collection_name)?;
+		for document in &self.missing {
+			writeln!(f, "  missing: {document}")?;
+		}
+		for document in &self.extra {
+			writeln!(f, "  extra: {document}")?;
+		}
+		for mismatch in &self.mismatched {
+			writeln!(f, "  mismatch in fields {:?}:", mismatch.fields)?;
+			writeln!(f, "    expected: {}", mismatch.expected)?;
+			writeln!(f, "    actual:   {}", mismatch.actual)?;
+		}
+		Ok(())
+	}
+}
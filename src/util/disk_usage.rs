@@ -0,0 +1,22 @@
+use crate::error::ErrorInner;
+use crate::Error;
+use std::path::Path;
+
+/// Get the total size in bytes of all files in a directory and its subdirectories.
+///
+/// Symbolic links are not followed.
+pub fn disk_usage(directory: &Path) -> Result<u64, Error> {
+	let entries = std::fs::read_dir(directory).map_err(|e| ErrorInner::ReadDataDir(directory.to_owned(), e))?;
+	let mut total = 0;
+	for entry in entries {
+		let entry = entry.map_err(|e| ErrorInner::ReadDataDir(directory.to_owned(), e))?;
+		let path = entry.path();
+		let metadata = std::fs::symlink_metadata(&path).map_err(|e| ErrorInner::ReadDataDir(path.clone(), e))?;
+		if metadata.is_dir() {
+			total += disk_usage(&path)?;
+		} else if metadata.is_file() {
+			total += metadata.len();
+		}
+	}
+	Ok(total)
+}
@@ -0,0 +1,34 @@
+use futures_util::stream::TryStreamExt;
+use mongodb::bson::{Bson, Document};
+use mongodb::Client;
+use std::path::Path;
+
+use crate::error::ErrorInner;
+use crate::Error;
+
+/// Write all documents of a collection to a file as a pretty-printed JSON array.
+///
+/// The documents are written as canonical extended JSON, so that no type information is lost
+/// and the file can be loaded again with [`DataSeeder::from_json_file()`](super::DataSeeder::from_json_file).
+pub async fn export_collection(client: &Client, database_name: &str, collection_name: &str, path: &Path) -> Result<(), Error> {
+	let namespace = format!("{database_name}.{collection_name}");
+	let documents: Vec<Document> = client
+		.database(database_name)
+		.collection(collection_name)
+		.find(None, None)
+		.await
+		.map_err(|e| ErrorInner::ReadCollection(namespace.clone(), e))?
+		.try_collect()
+		.await
+		.map_err(|e| ErrorInner::ReadCollection(namespace, e))?;
+
+	let json: Vec<_> = documents
+		.into_iter()
+		.map(|document| Bson::Document(document).into_canonical_extjson())
+		.collect();
+	let data = serde_json::to_string_pretty(&json)
+		.map_err(|e| ErrorInner::WriteExport(path.to_owned(), e.into()))?;
+	std::fs::write(path, data)
+		.map_err(|e| ErrorInner::WriteExport(path.to_owned(), e))?;
+	Ok(())
+}
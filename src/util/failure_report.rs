@@ -0,0 +1,43 @@
+use crate::error::ErrorInner;
+use crate::Error;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Diagnostics of a server, gathered in one go when a test fails.
+///
+/// The report can be printed with [`Debug`], or written to a JSON file with [`Self::write_to_file()`], for example to upload it as a CI artifact.
+#[derive(Debug, Clone, Serialize)]
+pub struct FailureReport {
+	/// The connection string of the server, with the password redacted.
+	pub uri: String,
+
+	/// The PID of the server process.
+	pub process_id: u32,
+
+	/// The exit status of the server process, or `None` if it is still running.
+	pub exit_status: Option<String>,
+
+	/// The temporary state directory.
+	pub directory: PathBuf,
+
+	/// The path of the log file of the server.
+	pub log_path: PathBuf,
+
+	/// The last lines of the log of the server.
+	pub log_tail: Vec<String>,
+
+	/// The output of the `serverStatus` command as relaxed extended JSON, or `None` if it failed.
+	pub server_status: Option<serde_json::Value>,
+
+	/// The error of the `serverStatus` command, if it failed.
+	pub server_status_error: Option<String>,
+}
+
+impl FailureReport {
+	/// Write the report as pretty-printed JSON to a file.
+	pub fn write_to_file(&self, path: &Path) -> Result<(), Error> {
+		let json = serde_json::to_string_pretty(self).map_err(|e| ErrorInner::WriteFailureReport(path.to_owned(), e.into()))?;
+		std::fs::write(path, json).map_err(|e| ErrorInner::WriteFailureReport(path.to_owned(), e))?;
+		Ok(())
+	}
+}
@@ -0,0 +1,58 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::{OsStr, OsString};
+use std::process::Command;
+use std::sync::Mutex;
+
+/// The oplog size in megabytes used by the fast startup profile, unless configured explicitly.
+pub const FAST_OPLOG_SIZE_MB: u64 = 50;
+
+/// The server parameters set by the fast startup profile, with their values.
+const FAST_STARTUP_PARAMETERS: &[(&str, &str)] = &[("diagnosticDataCollectionEnabled", "false")];
+
+/// The major and minor version of a `mongod` binary.
+type MongodVersion = (u64, u64);
+
+/// The versions of the `mongod` commands that were already run with `--version` in this process.
+static VERSIONS: Mutex<Option<HashMap<OsString, Option<MongodVersion>>>> = Mutex::new(None);
+
+/// Get the extra `mongod` arguments of the fast startup profile.
+///
+/// The profile disables periodic checkpoints and diagnostic data collection.
+/// For standalone servers, journaling is disabled too on versions that still support it (before 6.1),
+/// since replica set members using WiredTiger refuse to start without a journal.
+///
+/// Server parameters that are already in `parameters` are left out, so the values set by the user take precedence.
+pub fn fast_startup_args(command: &OsStr, replica_set: bool, parameters: &BTreeMap<String, String>) -> Vec<OsString> {
+	let mut args: Vec<OsString> = vec!["--syncdelay".into(), "0".into()];
+	for (key, value) in FAST_STARTUP_PARAMETERS {
+		if !parameters.contains_key(*key) {
+			args.extend(["--setParameter".into(), format!("{key}={value}").into()]);
+		}
+	}
+	if !replica_set && cached_mongod_version(command).is_some_and(|version| version < (6, 1)) {
+		args.push("--nojournal".into());
+	}
+	args
+}
+
+/// Get the major and minor version of a `mongod` binary, running it with `--version` only once per command.
+fn cached_mongod_version(command: &OsStr) -> Option<MongodVersion> {
+	let mut versions = VERSIONS.lock().unwrap_or_else(|e| e.into_inner());
+	*versions
+		.get_or_insert_with(HashMap::new)
+		.entry(command.to_owned())
+		.or_insert_with(|| mongod_version(command))
+}
+
+/// Get the major and minor version of a `mongod` binary, by running it with `--version`.
+fn mongod_version(command: &OsStr) -> Option<MongodVersion> {
+	let output = Command::new(command).arg("--version").output().ok()?;
+	let stdout = String::from_utf8_lossy(&output.stdout);
+	let version = stdout
+		.lines()
+		.find_map(|line| line.strip_prefix("db version v"))?;
+	let mut parts = version.split('.');
+	let major = parts.next()?.parse().ok()?;
+	let minor = parts.next()?.parse().ok()?;
+	Some((major, minor))
+}
@@ -0,0 +1,39 @@
+use crate::error::ErrorInner;
+use crate::Error;
+use mongodb::bson::doc;
+use mongodb::error::ErrorKind;
+
+/// The error code for unknown fields in a command, returned by servers before 7.0 for the `confirm` field.
+const UNKNOWN_FIELD: i32 = 40415;
+
+/// Set the feature compatibility version of a deployment.
+///
+/// The command only returns once the upgrade or downgrade is complete.
+pub async fn set_feature_compatibility_version(client: &mongodb::Client, version: &str) -> Result<(), Error> {
+	let admin = client.database("admin");
+	// Since 7.0, the command must be confirmed explicitly, but older versions reject the `confirm` field.
+	let result = admin
+		.run_command(doc! { "setFeatureCompatibilityVersion": version, "confirm": true }, None)
+		.await;
+	let result = match result {
+		Err(e) if matches!(&*e.kind, ErrorKind::Command(command) if command.code == UNKNOWN_FIELD) => {
+			admin.run_command(doc! { "setFeatureCompatibilityVersion": version }, None).await
+		},
+		result => result,
+	};
+	result.map_err(|e| ErrorInner::SetFeatureCompatibilityVersion(version.to_owned(), e))?;
+	Ok(())
+}
+
+/// Get the feature compatibility version of a deployment.
+pub async fn feature_compatibility_version(client: &mongodb::Client) -> mongodb::error::Result<String> {
+	let reply = client
+		.database("admin")
+		.run_command(doc! { "getParameter": 1, "featureCompatibilityVersion": 1 }, None)
+		.await?;
+	let version = reply
+		.get_document("featureCompatibilityVersion")
+		.and_then(|fcv| fcv.get_str("version"))
+		.map_err(|e| mongodb::error::Error::custom(e.to_string()))?;
+	Ok(version.to_owned())
+}
@@ -0,0 +1,126 @@
+use super::DiffOptions;
+use crate::error::ErrorInner;
+use crate::Error;
+use futures_util::stream::TryStreamExt;
+use mongodb::bson::{Bson, Document};
+use mongodb::Database;
+use serde_json::Value;
+use std::path::Path;
+
+/// The number of unchanged lines shown around each change in a golden file diff.
+const CONTEXT_LINES: usize = 2;
+
+/// Render collections of a database as canonical JSON, for comparison with a golden file.
+///
+/// The result is a pretty-printed JSON object with a key for each collection, holding its documents as canonical extended JSON.
+/// The keys of all objects are sorted and the documents are sorted by their JSON representation,
+/// so the output does not depend on the insertion order.
+/// The ignored fields of `options` are removed first, which includes `_id` by default.
+pub async fn render_golden(database: &Database, collections: &[&str], options: &DiffOptions) -> Result<String, Error> {
+	let mut golden = serde_json::Map::new();
+	for collection_name in collections {
+		let namespace = format!("{}.{collection_name}", database.name());
+		let documents: Vec<Document> = database
+			.collection(collection_name)
+			.find(None, None)
+			.await
+			.map_err(|e| ErrorInner::ReadCollection(namespace.clone(), e))?
+			.try_collect()
+			.await
+			.map_err(|e| ErrorInner::ReadCollection(namespace, e))?;
+
+		let mut documents: Vec<_> = documents
+			.into_iter()
+			.map(|document| sort_keys(Bson::Document(options.strip(document)).into_canonical_extjson()))
+			.map(|json| (json.to_string(), json))
+			.collect();
+		documents.sort_by(|(a, _), (b, _)| a.cmp(b));
+		golden.insert(collection_name.to_string(), documents.into_iter().map(|(_, json)| json).collect());
+	}
+	let golden = sort_keys(Value::Object(golden));
+	let rendered = serde_json::to_string_pretty(&golden).unwrap_or_else(|_| golden.to_string());
+	Ok(rendered + "\n")
+}
+
+/// Write collections of a database to a golden file, see [`render_golden()`].
+pub async fn dump_golden(database: &Database, collections: &[&str], path: &Path, options: &DiffOptions) -> Result<(), Error> {
+	let golden = render_golden(database, collections, options).await?;
+	std::fs::write(path, golden).map_err(|e| ErrorInner::WriteGolden(path.to_owned(), e))?;
+	Ok(())
+}
+
+/// Sort the keys of all objects in a JSON value.
+fn sort_keys(value: Value) -> Value {
+	match value {
+		Value::Object(object) => {
+			let mut entries: Vec<_> = object.into_iter().collect();
+			entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+			Value::Object(entries.into_iter().map(|(key, value)| (key, sort_keys(value))).collect())
+		},
+		Value::Array(items) => Value::Array(items.into_iter().map(sort_keys).collect()),
+		value => value,
+	}
+}
+
+/// Render the differences between the lines of an expected and an actual golden file.
+///
+/// Removed lines are prefixed with `-` and added lines with `+`, with a few unchanged lines around each change.
+/// Returns `None` if the files are equal.
+pub fn golden_diff(expected: &str, actual: &str) -> Option<String> {
+	if expected == actual {
+		return None;
+	}
+	let expected: Vec<_> = expected.lines().collect();
+	let actual: Vec<_> = actual.lines().collect();
+
+	// The length of the longest common subsequence of the remaining lines, for each pair of positions.
+	let mut common = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+	for i in (0..expected.len()).rev() {
+		for j in (0..actual.len()).rev() {
+			common[i][j] = if expected[i] == actual[j] {
+				common[i + 1][j + 1] + 1
+			} else {
+				common[i + 1][j].max(common[i][j + 1])
+			};
+		}
+	}
+
+	let mut lines = Vec::new();
+	let (mut i, mut j) = (0, 0);
+	while i < expected.len() || j < actual.len() {
+		if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+			lines.push((' ', expected[i]));
+			i += 1;
+			j += 1;
+		} else if i < expected.len() && (j == actual.len() || common[i + 1][j] >= common[i][j + 1]) {
+			lines.push(('-', expected[i]));
+			i += 1;
+		} else {
+			lines.push(('+', actual[j]));
+			j += 1;
+		}
+	}
+
+	let is_near_change = |index: usize| {
+		let start = index.saturating_sub(CONTEXT_LINES);
+		let end = (index + CONTEXT_LINES + 1).min(lines.len());
+		lines[start..end].iter().any(|(kind, _)| *kind != ' ')
+	};
+	let mut diff = String::new();
+	let mut skipped = false;
+	for (index, (kind, line)) in lines.iter().enumerate() {
+		if is_near_change(index) {
+			if skipped {
+				diff.push_str("  ...\n");
+				skipped = false;
+			}
+			diff.push_str(&format!("{kind} {line}\n"));
+		} else {
+			skipped = true;
+		}
+	}
+	if skipped {
+		diff.push_str("  ...\n");
+	}
+	Some(diff)
+}
@@ -0,0 +1,31 @@
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use std::path::Path;
+
+/// The number of characters in a generated keyfile.
+const KEYFILE_LENGTH: usize = 756;
+
+/// Generate a random keyfile for internal authentication between replica set members.
+///
+/// The keyfile contains 756 random alphanumeric characters, the maximum `mongod` accepts.
+/// Pass it to every member of a deployment with the `--keyFile` option or the `security.keyFile` configuration file setting,
+/// for example to compose your own topology or a `docker-compose` file.
+///
+/// On Unix platforms, the file is only readable and writable by the owner (mode `600`), as required by `mongod`.
+/// An existing file is overwritten.
+pub fn generate_keyfile(path: &Path) -> std::io::Result<()> {
+	let key: String = rand::thread_rng()
+		.sample_iter(&Alphanumeric)
+		.take(KEYFILE_LENGTH)
+		.map(char::from)
+		.collect();
+	std::fs::write(path, key)?;
+
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::PermissionsExt;
+		std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+	}
+
+	Ok(())
+}
@@ -21,6 +21,16 @@ impl KillOnDrop {
 	pub fn kill(&mut self) -> std::io::Result<()> {
 		self.child.kill()
 	}
+
+	/// Check if the child process has exited, without blocking.
+	pub fn try_wait(&mut self) -> std::io::Result<Option<std::process::ExitStatus>> {
+		self.child.try_wait()
+	}
+
+	/// Wait for the child process to exit.
+	pub fn wait(&mut self) -> std::io::Result<std::process::ExitStatus> {
+		self.child.wait()
+	}
 }
 
 impl Drop for KillOnDrop {
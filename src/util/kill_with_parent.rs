@@ -0,0 +1,146 @@
+use std::process::{Child, Command};
+
+/// Spawn a child process that is terminated when the current process dies.
+///
+/// This prevents leaking servers when the test process is killed before it can clean up.
+///
+/// On Linux, the child receives `SIGKILL` when the current process dies.
+/// It is also placed in its own process group, so a Ctrl+C in the terminal only interrupts the current process,
+/// which can then shut the child down properly.
+/// On Windows, the child is assigned to a Job Object that kills all its processes when the current process exits.
+/// On other platforms, the child may outlive the current process if it is killed.
+pub fn spawn_killed_with_parent(mut command: Command) -> std::io::Result<Child> {
+	#[cfg(target_os = "linux")]
+	{
+		use std::os::unix::process::CommandExt;
+		command.process_group(0);
+		linux::set_parent_death_signal(&mut command);
+		linux::spawn_from_spawner_thread(command)
+	}
+
+	#[cfg(windows)]
+	{
+		let child = command.spawn()?;
+		windows::assign_to_job(&child);
+		Ok(child)
+	}
+
+	#[cfg(not(any(target_os = "linux", windows)))]
+	{
+		command.spawn()
+	}
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+	use std::os::unix::process::CommandExt;
+	use std::process::{Child, Command};
+	use std::sync::mpsc;
+	use std::sync::OnceLock;
+
+	/// A request to spawn a command, with a channel to send the result back on.
+	type SpawnRequest = (Command, mpsc::Sender<std::io::Result<Child>>);
+
+	/// The channel to send spawn requests to the spawner thread.
+	static SPAWNER: OnceLock<mpsc::Sender<SpawnRequest>> = OnceLock::new();
+
+	/// Make the child receive `SIGKILL` when its parent dies.
+	pub fn set_parent_death_signal(command: &mut Command) {
+		let parent = std::process::id();
+		// SAFETY: The closure only calls async-signal-safe functions and does not allocate.
+		unsafe {
+			command.pre_exec(move || {
+				if libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL) == -1 {
+					return Err(std::io::Error::last_os_error());
+				}
+				// The parent may have died before the signal was set up.
+				if libc::getppid() as u32 != parent {
+					return Err(std::io::Error::from_raw_os_error(libc::ESRCH));
+				}
+				Ok(())
+			});
+		}
+	}
+
+	/// Spawn a command from a thread that lives as long as the process.
+	///
+	/// The parent death signal is sent when the thread that spawned the child exits, not when the whole process exits.
+	/// Spawning from the current thread would kill the child as soon as the thread exits,
+	/// for example when the async runtime of a single test shuts down its worker threads.
+	pub fn spawn_from_spawner_thread(command: Command) -> std::io::Result<Child> {
+		let spawner = SPAWNER.get_or_init(|| {
+			let (sender, receiver) = mpsc::channel::<SpawnRequest>();
+			let _ = std::thread::Builder::new()
+				.name("temp-mongo-spawner".into())
+				.spawn(move || {
+					for (mut command, reply) in receiver {
+						let _ = reply.send(command.spawn());
+					}
+				});
+			sender
+		});
+
+		let spawner_gone = || std::io::Error::other("failed to start spawner thread");
+		let (reply, result) = mpsc::channel();
+		spawner.send((command, reply)).map_err(|_| spawner_gone())?;
+		result.recv().map_err(|_| spawner_gone())?
+	}
+}
+
+#[cfg(windows)]
+mod windows {
+	use std::os::windows::io::AsRawHandle;
+	use std::process::Child;
+	use std::sync::OnceLock;
+	use windows_sys::Win32::Foundation::HANDLE;
+	use windows_sys::Win32::System::JobObjects::{
+		AssignProcessToJobObject,
+		CreateJobObjectW,
+		JobObjectExtendedLimitInformation,
+		SetInformationJobObject,
+		JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+		JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+	};
+
+	/// The Job Object for all spawned children.
+	///
+	/// The handle is never closed explicitly.
+	/// Windows closes it when the current process exits, which kills all processes in the job.
+	static JOB: OnceLock<Option<HANDLE>> = OnceLock::new();
+
+	/// Assign a child process to the Job Object, creating the Job Object if needed.
+	///
+	/// Failures are ignored, since the child is still killed on drop in the normal case.
+	pub fn assign_to_job(child: &Child) {
+		let Some(job) = *JOB.get_or_init(create_job) else {
+			return;
+		};
+		// SAFETY: Both handles are valid for the duration of the call.
+		unsafe {
+			AssignProcessToJobObject(job, child.as_raw_handle() as HANDLE);
+		}
+	}
+
+	/// Create a Job Object that kills all its processes when the last handle to it is closed.
+	fn create_job() -> Option<HANDLE> {
+		// SAFETY: The limit information is plain data, for which all zeroes is a valid value.
+		unsafe {
+			let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+			if job == 0 {
+				return None;
+			}
+			let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+			info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+			let result = SetInformationJobObject(
+				job,
+				JobObjectExtendedLimitInformation,
+				&info as *const _ as *const std::ffi::c_void,
+				std::mem::size_of_val(&info) as u32,
+			);
+			if result == 0 {
+				return None;
+			}
+			Some(job)
+		}
+	}
+}
@@ -0,0 +1,113 @@
+use crate::error::ErrorInner;
+use crate::Error;
+use mongodb::bson::doc;
+use mongodb::event::sdam::{SdamEventHandler, ServerHeartbeatFailedEvent, ServerHeartbeatSucceededEvent};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The maximum number of heartbeats kept by a [`HeartbeatMonitor`], so long-lived servers do not grow it without bound.
+const MAX_HEARTBEATS: usize = 1000;
+
+/// A heartbeat of the server monitor of a client, as recorded by server discovery and monitoring.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heartbeat {
+	/// The address of the server, as `host:port`.
+	pub address: String,
+
+	/// The time until the reply to the `hello` command was received.
+	pub duration: Duration,
+
+	/// If the heartbeat waited for a change of the server state with the streaming protocol.
+	///
+	/// The duration of an awaited heartbeat includes the wait, so it is not a round trip time.
+	pub awaited: bool,
+
+	/// The error message, if the heartbeat failed.
+	pub failure: Option<String>,
+}
+
+/// A server monitoring event handler that records the most recent heartbeats.
+#[derive(Debug, Default)]
+pub struct HeartbeatMonitor {
+	heartbeats: Mutex<VecDeque<Heartbeat>>,
+}
+
+impl HeartbeatMonitor {
+	/// Get the recorded heartbeats, oldest first.
+	pub fn heartbeats(&self) -> Vec<Heartbeat> {
+		self.heartbeats.lock().map(|heartbeats| heartbeats.iter().cloned().collect()).unwrap_or_default()
+	}
+
+	/// Record a finished heartbeat, forgetting the oldest one if the limit is reached.
+	fn record(&self, heartbeat: Heartbeat) {
+		if let Ok(mut heartbeats) = self.heartbeats.lock() {
+			if heartbeats.len() == MAX_HEARTBEATS {
+				heartbeats.pop_front();
+			}
+			heartbeats.push_back(heartbeat);
+		}
+	}
+}
+
+impl SdamEventHandler for HeartbeatMonitor {
+	fn handle_server_heartbeat_succeeded_event(&self, event: ServerHeartbeatSucceededEvent) {
+		self.record(Heartbeat {
+			address: event.server_address.to_string(),
+			duration: event.duration,
+			awaited: event.awaited,
+			failure: None,
+		});
+	}
+
+	fn handle_server_heartbeat_failed_event(&self, event: ServerHeartbeatFailedEvent) {
+		self.record(Heartbeat {
+			address: event.server_address.to_string(),
+			duration: event.duration,
+			awaited: event.awaited,
+			failure: Some(event.failure.to_string()),
+		});
+	}
+}
+
+/// Statistics of the round trip times of a number of `ping` commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyStatistics {
+	/// The number of pings.
+	pub samples: usize,
+
+	/// The fastest round trip.
+	pub min: Duration,
+
+	/// The median round trip.
+	pub median: Duration,
+
+	/// The mean round trip.
+	pub mean: Duration,
+
+	/// The slowest round trip.
+	pub max: Duration,
+}
+
+/// Measure the round trip time of `samples` consecutive `ping` commands.
+///
+/// At least one ping is sent, even if `samples` is zero.
+pub async fn measure_latency(client: &mongodb::Client, samples: usize) -> Result<LatencyStatistics, Error> {
+	let database = client.database("admin");
+	let mut durations = Vec::with_capacity(samples.max(1));
+	for _ in 0..samples.max(1) {
+		let start = Instant::now();
+		database.run_command(doc! { "ping": 1 }, None).await.map_err(ErrorInner::Ping)?;
+		durations.push(start.elapsed());
+	}
+	durations.sort();
+
+	let total: Duration = durations.iter().sum();
+	Ok(LatencyStatistics {
+		samples: durations.len(),
+		min: durations[0],
+		median: durations[durations.len() / 2],
+		mean: total / durations.len() as u32,
+		max: durations[durations.len() - 1],
+	})
+}
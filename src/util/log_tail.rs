@@ -0,0 +1,124 @@
+use futures_util::stream::Stream;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// The time to wait before checking a log file for new lines again.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Follows a log file that is still being written, like `tail -f`.
+///
+/// The file is opened when it exists, so it may be created after the tail.
+#[derive(Debug)]
+struct LogTail {
+	/// The path of the log file.
+	path: PathBuf,
+
+	/// The reader of the log file, once it has been opened.
+	reader: Option<BufReader<File>>,
+
+	/// The start of a line that has not been completely written yet.
+	pending: String,
+}
+
+impl LogTail {
+	/// Create a tail of a log file, starting at the beginning of the file.
+	fn new(path: &Path) -> Self {
+		Self {
+			path: path.to_owned(),
+			reader: None,
+			pending: String::new(),
+		}
+	}
+
+	/// Read the next complete line, without the line terminator.
+	///
+	/// Returns `None` if no complete line has been written yet.
+	fn next_line(&mut self) -> std::io::Result<Option<String>> {
+		let reader = match &mut self.reader {
+			Some(reader) => reader,
+			None => match File::open(&self.path) {
+				Ok(file) => self.reader.insert(BufReader::new(file)),
+				Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+				Err(e) => return Err(e),
+			},
+		};
+		reader.read_line(&mut self.pending)?;
+		if !self.pending.ends_with('\n') {
+			return Ok(None);
+		}
+		let line = std::mem::take(&mut self.pending);
+		Ok(Some(line.trim_end_matches(['\r', '\n']).to_owned()))
+	}
+}
+
+/// Stream the lines of a log file as they are written.
+///
+/// The stream starts at the beginning of the file and never ends, unless reading the file fails.
+pub fn stream_log_lines(path: &Path) -> impl Stream<Item = std::io::Result<String>> {
+	futures_util::stream::unfold(Some(LogTail::new(path)), |tail| async move {
+		let mut tail = tail?;
+		loop {
+			match tail.next_line() {
+				Ok(Some(line)) => return Some((Ok(line), Some(tail))),
+				Ok(None) => sleep(POLL_INTERVAL).await,
+				Err(e) => return Some((Err(e), None)),
+			}
+		}
+	})
+}
+
+/// Forwards the lines of a log file to standard output in a background thread.
+///
+/// Each line is prefixed, so the log of the server can be told apart from the output of the test.
+/// Since the line is printed with [`println!()`], the test harness captures it like any other output of the test.
+///
+/// The thread is stopped when this object is dropped.
+pub struct LogForwarder {
+	stop: Arc<AtomicBool>,
+	thread: Option<JoinHandle<()>>,
+}
+
+impl std::fmt::Debug for LogForwarder {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("LogForwarder").finish_non_exhaustive()
+	}
+}
+
+impl LogForwarder {
+	/// Start forwarding the lines of a log file with the given prefix.
+	pub(crate) fn start(path: &Path, prefix: String) -> Self {
+		let stop = Arc::new(AtomicBool::new(false));
+		let mut tail = LogTail::new(path);
+		let thread = std::thread::spawn({
+			let stop = stop.clone();
+			move || {
+				while !stop.load(Ordering::Relaxed) {
+					match tail.next_line() {
+						Ok(Some(line)) => println!("{prefix}{line}"),
+						Ok(None) => std::thread::sleep(POLL_INTERVAL),
+						Err(_) => return,
+					}
+				}
+			}
+		});
+		Self {
+			stop,
+			thread: Some(thread),
+		}
+	}
+}
+
+impl Drop for LogForwarder {
+	fn drop(&mut self) {
+		self.stop.store(true, Ordering::Relaxed);
+		if let Some(thread) = self.thread.take() {
+			thread.join().ok();
+		}
+	}
+}
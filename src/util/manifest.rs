@@ -0,0 +1,106 @@
+use serde::Deserialize;
+use mongodb::Client;
+use std::path::Path;
+
+use crate::error::ErrorInner;
+use crate::Error;
+use super::{DataSeeder, ViewSeeder};
+use super::seed_file::{bson_collection_name, parse_json_seed_file};
+
+/// A description of all data to seed into a mongodb instance.
+///
+/// A manifest can be loaded from a JSON file with the following layout:
+/// ```json
+/// {
+///   "seeds": [
+///     { "database_name": "test", "collection_name": "animals", "documents": [{ "species": "dog" }] }
+///   ],
+///   "views": [
+///     { "database_name": "test", "view_name": "dogs", "source": "animals", "pipeline": [{ "$match": { "species": "dog" } }] }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct Manifest {
+	/// The documents to seed, grouped per collection.
+	#[serde(default)]
+	pub seeds: Vec<DataSeeder>,
+	/// The views to create after the documents have been seeded.
+	#[serde(default)]
+	pub views: Vec<ViewSeeder>,
+}
+
+impl Manifest {
+	/// Creates a new empty manifest.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Parse a manifest from a JSON string.
+	pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+		serde_json::from_str(json)
+	}
+
+	/// Read and parse a manifest from a JSON file.
+	///
+	/// Files with a `.gz` extension are decompressed automatically.
+	pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+		parse_json_seed_file(path.as_ref())
+	}
+
+	/// Create a manifest from the output directory of `mongodump`.
+	///
+	/// Every subdirectory is seeded as a database,
+	/// and every `.bson` or `.bson.gz` file in it is seeded as a collection.
+	/// Other files, such as the `.metadata.json` files, are ignored.
+	/// Archives created with `mongodump --archive` are not supported.
+	pub fn from_dump_dir(path: impl AsRef<Path>) -> Result<Self, Error> {
+		let path = path.as_ref();
+		let read_dir = |path: &Path| std::fs::read_dir(path)
+			.and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+			.map_err(|e| ErrorInner::ReadSeedFile(path.to_owned(), e));
+
+		let mut manifest = Self::new();
+		for database in read_dir(path)? {
+			if !database.path().is_dir() {
+				continue;
+			}
+			let database_name = database.file_name().to_string_lossy().into_owned();
+			for file in read_dir(&database.path())? {
+				let file_name = file.file_name().to_string_lossy().into_owned();
+				if let Some(collection_name) = bson_collection_name(&file_name) {
+					manifest.seeds.push(DataSeeder::from_bson_file(&database_name, collection_name, file.path())?);
+				}
+			}
+		}
+		Ok(manifest)
+	}
+
+	/// Add a collection to seed to the manifest.
+	pub fn seed(mut self, seed: DataSeeder) -> Self {
+		self.seeds.push(seed);
+		self
+	}
+
+	/// Add a view to create to the manifest.
+	pub fn view(mut self, view: ViewSeeder) -> Self {
+		self.views.push(view);
+		self
+	}
+
+	/// Seed all documents and create all views of the manifest.
+	///
+	/// # Errors
+	///
+	/// Returns an error if any MongoDB operation fails during the seeding process.
+	#[cfg_attr(feature = "tracing", tracing::instrument(target = "temp_mongo::seed", skip_all, fields(seeds = self.seeds.len(), views = self.views.len())))]
+	pub async fn load(&self, client: &Client) -> mongodb::error::Result<()> {
+		for seed in &self.seeds {
+			seed.seed_document(client).await?;
+		}
+		for view in &self.views {
+			view.seed_view(client).await?;
+		}
+		Ok(())
+	}
+}
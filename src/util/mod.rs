@@ -7,5 +7,110 @@ pub use kill_on_drop::KillOnDrop;
 mod port_finder;
 pub use port_finder::PortGenerator;
 
+mod seed_file;
+
 mod data_seeder;
 pub use data_seeder::DataSeeder;
+
+mod view_seeder;
+pub use view_seeder::ViewSeeder;
+
+mod manifest;
+pub use manifest::Manifest;
+
+mod remote_clone;
+pub use remote_clone::CloneOptions;
+
+mod diff;
+pub use diff::{CollectionDiff, DatabaseDiff, DiffOptions, FieldMismatch};
+
+mod export;
+pub use export::export_collection;
+
+mod credentials;
+pub use credentials::{default_credentials, random_password, update_password, Credentials};
+
+mod mongod_config;
+pub(crate) use mongod_config::ConfigSource;
+pub use mongod_config::{set_parameter_args, MongodConfig};
+
+mod fast_startup;
+pub use fast_startup::{fast_startup_args, FAST_OPLOG_SIZE_MB};
+
+mod kill_with_parent;
+pub use kill_with_parent::spawn_killed_with_parent;
+
+mod resource_limits;
+pub use resource_limits::ResourceLimits;
+
+mod bind_ip;
+pub use bind_ip::BindIp;
+
+mod secret;
+pub use secret::{redact_uri, SecretString};
+
+mod server_output;
+pub use server_output::{last_lines, server_output_tail, spawn_server, verbosity_args};
+
+mod port_retry;
+pub use port_retry::{bind_failed, free_port, spawn_on_free_port, DEFAULT_PORT_RETRIES};
+
+mod fcv;
+pub use fcv::{feature_compatibility_version, set_feature_compatibility_version};
+
+mod tls;
+pub use tls::{TlsCertificates, CLIENT_USERNAME};
+
+mod keyfile;
+pub use keyfile::generate_keyfile;
+
+mod trace;
+pub(crate) use trace::{event, record};
+
+mod log_tail;
+pub use log_tail::{stream_log_lines, LogForwarder};
+
+mod saved_log;
+pub use saved_log::SavedLog;
+
+mod server_status;
+pub use server_status::{Opcounters, ServerStatus};
+
+mod profiler;
+pub use profiler::{operation_summary, profiling_args, slow_query_report, OperationCounts, ProfiledOperation, ProfilingLevel};
+
+mod startup_metrics;
+pub use startup_metrics::{StartupMetrics, Stopwatch};
+
+mod current_op;
+pub use current_op::{current_ops, CurrentOp};
+
+mod command_recorder;
+pub use command_recorder::{CommandRecorder, ExecutedCommand};
+
+mod failure_report;
+pub use failure_report::FailureReport;
+
+mod disk_usage;
+pub use disk_usage::disk_usage;
+
+mod startup_warnings;
+pub use startup_warnings::startup_warnings;
+
+mod pool_monitor;
+pub use pool_monitor::{PoolMonitor, PoolStatistics};
+
+mod latency;
+pub use latency::{measure_latency, Heartbeat, HeartbeatMonitor, LatencyStatistics};
+
+mod compose;
+pub use compose::{render_compose, ComposeService};
+
+mod database_name;
+pub use database_name::isolated_database_name;
+
+mod golden;
+pub use golden::{dump_golden, golden_diff, render_golden};
+
+mod retry;
+pub use retry::RetryPolicy;
@@ -0,0 +1,121 @@
+use crate::error::ErrorInner;
+use crate::Error;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// A typed `mongod` configuration file.
+///
+/// Options are set by their dotted path in the configuration file, like `storage.wiredTiger.engineConfig.cacheSizeGB`.
+/// The configuration is rendered to YAML and passed to `mongod` with `--config`.
+/// Options that are passed on the command line, like the data directory and the port, take precedence over the configuration file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MongodConfig {
+	/// The configured options by dotted path.
+	options: BTreeMap<String, Value>,
+}
+
+/// The source of a `mongod` configuration file.
+#[derive(Debug, Clone)]
+pub(crate) enum ConfigSource {
+	/// An existing configuration file.
+	File(PathBuf),
+
+	/// A configuration that is rendered to a file in the temporary directory.
+	Inline(MongodConfig),
+}
+
+/// A node in the tree of configuration options.
+enum Node<'a> {
+	Value(&'a Value),
+	Section(BTreeMap<&'a str, Node<'a>>),
+}
+
+impl MongodConfig {
+	/// Create an empty configuration.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Set an option by its dotted path, like `setParameter.enableTestCommands`.
+	///
+	/// If a path is set as an option and is also the parent of another option, the value of the parent is ignored.
+	pub fn set(mut self, path: impl Into<String>, value: impl Into<Value>) -> Self {
+		self.options.insert(path.into(), value.into());
+		self
+	}
+
+	/// Get the value of an option by its dotted path.
+	pub fn get(&self, path: &str) -> Option<&Value> {
+		self.options.get(path)
+	}
+
+	/// Render the configuration as YAML.
+	pub fn to_yaml(&self) -> String {
+		let mut root = BTreeMap::new();
+		for (path, value) in &self.options {
+			let mut section = &mut root;
+			let mut keys = path.split('.').peekable();
+			while let Some(key) = keys.next() {
+				if keys.peek().is_none() {
+					section.insert(key, Node::Value(value));
+					break;
+				}
+				let node = section.entry(key).or_insert_with(|| Node::Section(BTreeMap::new()));
+				if let Node::Value(_) = node {
+					*node = Node::Section(BTreeMap::new());
+				}
+				let Node::Section(next) = node else {
+					unreachable!()
+				};
+				section = next;
+			}
+		}
+
+		let mut yaml = String::new();
+		render_section(&mut yaml, &root, 0);
+		yaml
+	}
+
+	/// Write the configuration as YAML file.
+	pub fn write(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+		std::fs::write(path, self.to_yaml())
+	}
+}
+
+impl ConfigSource {
+	/// Get the path of the configuration file, writing it to `directory` if needed.
+	pub(crate) fn resolve(&self, directory: &Path) -> Result<PathBuf, Error> {
+		match self {
+			Self::File(path) => Ok(path.clone()),
+			Self::Inline(config) => {
+				let path = directory.join("mongod.conf");
+				config.write(&path).map_err(|e| ErrorInner::WriteConfig(path.clone(), e))?;
+				Ok(path)
+			},
+		}
+	}
+}
+
+/// Get the `--setParameter` arguments for the given server parameters.
+pub fn set_parameter_args(parameters: &BTreeMap<String, String>) -> Vec<OsString> {
+	parameters
+		.iter()
+		.flat_map(|(key, value)| ["--setParameter".into(), format!("{key}={value}").into()])
+		.collect()
+}
+
+/// Render a section of the configuration tree as YAML with the given indentation.
+fn render_section(yaml: &mut String, section: &BTreeMap<&str, Node>, indent: usize) {
+	for (key, node) in section {
+		match node {
+			// JSON values are valid YAML flow values.
+			Node::Value(value) => yaml.push_str(&format!("{:indent$}{key}: {value}\n", "")),
+			Node::Section(section) => {
+				yaml.push_str(&format!("{:indent$}{key}:\n", ""));
+				render_section(yaml, section, indent + 2);
+			},
+		}
+	}
+}
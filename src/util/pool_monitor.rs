@@ -0,0 +1,103 @@
+use mongodb::event::cmap::{
+	CmapEventHandler,
+	ConnectionCheckedInEvent,
+	ConnectionCheckedOutEvent,
+	ConnectionCheckoutFailedEvent,
+	ConnectionCheckoutFailedReason,
+	ConnectionClosedEvent,
+	ConnectionCreatedEvent,
+	PoolClearedEvent,
+};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Statistics of the connection pool of a client, as recorded by connection monitoring.
+///
+/// All counters include the connections used while spawning the server.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStatistics {
+	/// The number of connections created.
+	pub connections_created: u64,
+
+	/// The number of connections closed.
+	pub connections_closed: u64,
+
+	/// The number of successful checkouts of a connection from the pool.
+	pub checkouts: u64,
+
+	/// The number of checkouts that failed because no connection became available in time.
+	pub checkout_timeouts: u64,
+
+	/// The number of checkouts that failed because a new connection could not be established.
+	pub checkout_errors: u64,
+
+	/// The number of connections that are currently checked out.
+	pub checked_out: u64,
+
+	/// The number of times the pool was cleared, which happens after network errors and failovers.
+	pub pool_clears: u64,
+}
+
+/// A connection pool event handler that counts the events of all pools of a client.
+#[derive(Debug, Default)]
+pub struct PoolMonitor {
+	/// The counters of [`PoolStatistics`] with the same names.
+	connections_created: AtomicU64,
+	connections_closed: AtomicU64,
+	checkouts: AtomicU64,
+	checkout_timeouts: AtomicU64,
+	checkout_errors: AtomicU64,
+	pool_clears: AtomicU64,
+
+	/// The number of connections checked back in, to compute the number of checked out connections.
+	checked_in: AtomicU64,
+}
+
+impl PoolMonitor {
+	/// Get the statistics of the pools so far.
+	pub fn statistics(&self) -> PoolStatistics {
+		let checkouts = self.checkouts.load(Ordering::Relaxed);
+		PoolStatistics {
+			connections_created: self.connections_created.load(Ordering::Relaxed),
+			connections_closed: self.connections_closed.load(Ordering::Relaxed),
+			checkouts,
+			checkout_timeouts: self.checkout_timeouts.load(Ordering::Relaxed),
+			checkout_errors: self.checkout_errors.load(Ordering::Relaxed),
+			checked_out: checkouts.saturating_sub(self.checked_in.load(Ordering::Relaxed)),
+			pool_clears: self.pool_clears.load(Ordering::Relaxed),
+		}
+	}
+}
+
+/// Increment a counter by one.
+fn increment(counter: &AtomicU64) {
+	counter.fetch_add(1, Ordering::Relaxed);
+}
+
+impl CmapEventHandler for PoolMonitor {
+	fn handle_pool_cleared_event(&self, _event: PoolClearedEvent) {
+		increment(&self.pool_clears);
+	}
+
+	fn handle_connection_created_event(&self, _event: ConnectionCreatedEvent) {
+		increment(&self.connections_created);
+	}
+
+	fn handle_connection_closed_event(&self, _event: ConnectionClosedEvent) {
+		increment(&self.connections_closed);
+	}
+
+	fn handle_connection_checkout_failed_event(&self, event: ConnectionCheckoutFailedEvent) {
+		match event.reason {
+			ConnectionCheckoutFailedReason::Timeout => increment(&self.checkout_timeouts),
+			_ => increment(&self.checkout_errors),
+		}
+	}
+
+	fn handle_connection_checked_out_event(&self, _event: ConnectionCheckedOutEvent) {
+		increment(&self.checkouts);
+	}
+
+	fn handle_connection_checked_in_event(&self, _event: ConnectionCheckedInEvent) {
+		increment(&self.checked_in);
+	}
+}
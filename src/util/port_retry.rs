@@ -0,0 +1,75 @@
+use crate::error::ErrorInner;
+use crate::util::{event, spawn_server, KillOnDrop, PortGenerator, ResourceLimits};
+use crate::Error;
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+/// The default number of times a server is restarted on a new port when its port was taken.
+pub const DEFAULT_PORT_RETRIES: u32 = 3;
+
+/// The maximum time to wait for a server to start listening before giving up on detecting bind failures.
+const LISTEN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Select a free TCP port on the loopback adapter.
+pub fn free_port() -> Result<u16, Error> {
+	Ok(PortGenerator::new()
+		.generate()
+		.selected_port()
+		.ok_or(ErrorInner::Port)?)
+}
+
+/// Spawn a server on a free TCP port, retrying on a new port if the port was taken before the server could bind to it.
+///
+/// The arguments for a port are created by `make_args`.
+/// Returns the port and the server process.
+pub async fn spawn_on_free_port(
+	command: &OsStr,
+	output_path: &Path,
+	log_path: &Path,
+	limits: &ResourceLimits,
+	retries: u32,
+	make_args: impl Fn(u16) -> Vec<OsString>,
+) -> Result<(u16, KillOnDrop), Error> {
+	let mut retries_left = retries;
+	loop {
+		let port = free_port()?;
+		let mut server = spawn_server(command, &make_args(port), output_path, limits)?;
+		if retries_left > 0 && bind_failed(&mut server, log_path).await {
+			retries_left -= 1;
+			event!(warn, target: "temp_mongo::spawn", "port {port} was taken before the server could bind to it, retrying on a new port");
+			// Remove the old log, so it is not mistaken for the log of the next attempt.
+			std::fs::remove_file(log_path).ok();
+			continue;
+		}
+		return Ok((port, server));
+	}
+}
+
+/// Wait until a server is listening for connections or has exited.
+///
+/// Returns `true` if the server exited because its address was already in use.
+/// Any other failure is left for the caller to report when connecting to the server.
+pub async fn bind_failed(server: &mut KillOnDrop, log_path: &Path) -> bool {
+	let start = Instant::now();
+	loop {
+		match server.try_wait() {
+			Ok(Some(_)) => return log_contains(log_path, &["Address already in use", "Only one usage of each socket address"]),
+			Ok(None) => (),
+			Err(_) => return false,
+		}
+		if log_contains(log_path, &["Waiting for connections", "waiting for connections"]) || start.elapsed() > LISTEN_TIMEOUT {
+			return false;
+		}
+		sleep(Duration::from_millis(50)).await;
+	}
+}
+
+/// Check if a log file contains any of the given messages.
+fn log_contains(log_path: &Path, messages: &[&str]) -> bool {
+	let Ok(log) = std::fs::read_to_string(log_path) else {
+		return false;
+	};
+	messages.iter().any(|message| log.contains(message))
+}
@@ -0,0 +1,189 @@
+use crate::error::ErrorInner;
+use crate::Error;
+use futures_util::stream::TryStreamExt;
+use mongodb::bson::{doc, Bson, DateTime, Document};
+use mongodb::options::FindOptions;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::time::Duration;
+
+/// The level of the database profiler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfilingLevel {
+	/// Do not profile any operations.
+	Off,
+
+	/// Profile operations that take longer than the slow operation threshold.
+	SlowOperations,
+
+	/// Profile all operations.
+	All,
+}
+
+impl ProfilingLevel {
+	/// Get the numeric level used by `mongod`.
+	pub fn as_number(self) -> u8 {
+		match self {
+			Self::Off => 0,
+			Self::SlowOperations => 1,
+			Self::All => 2,
+		}
+	}
+}
+
+/// Get the `mongod` arguments to enable the profiler for all databases.
+pub fn profiling_args(level: ProfilingLevel, slow_ms: u64) -> Vec<OsString> {
+	vec![
+		"--profile".into(),
+		level.as_number().to_string().into(),
+		"--slowms".into(),
+		slow_ms.to_string().into(),
+	]
+}
+
+/// An operation recorded by the database profiler.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfiledOperation {
+	/// The namespace of the operation, like `test.animals`.
+	pub namespace: String,
+
+	/// The type of the operation, like `query`, `insert` or `command`.
+	pub operation: String,
+
+	/// The time the server spent on the operation.
+	pub duration: Duration,
+
+	/// The summary of the query plan, like `COLLSCAN` or `IXSCAN { species: 1 }`, if the operation used one.
+	pub plan_summary: Option<String>,
+
+	/// The number of documents the operation examined.
+	pub docs_examined: i64,
+
+	/// The number of index keys the operation examined.
+	pub keys_examined: i64,
+
+	/// The full profiler document of the operation.
+	pub raw: Document,
+}
+
+impl ProfiledOperation {
+	/// Parse a document from a `system.profile` collection.
+	pub fn from_document(raw: Document) -> Self {
+		let string = |name: &str| raw.get_str(name).unwrap_or_default().to_owned();
+		let number = |name: &str| match raw.get(name) {
+			Some(Bson::Int32(value)) => (*value).into(),
+			Some(Bson::Int64(value)) => *value,
+			Some(Bson::Double(value)) => *value as i64,
+			_ => 0,
+		};
+		Self {
+			namespace: string("ns"),
+			operation: string("op"),
+			duration: Duration::from_millis(number("millis").try_into().unwrap_or(0)),
+			plan_summary: raw.get_str("planSummary").ok().map(str::to_owned),
+			docs_examined: number("docsExamined"),
+			keys_examined: number("keysExamined"),
+			raw,
+		}
+	}
+}
+
+/// Get the slowest operations recorded by the profiler in all databases, slowest first.
+///
+/// At most `limit` operations are returned.
+pub async fn slow_query_report(client: &mongodb::Client, limit: usize) -> Result<Vec<ProfiledOperation>, Error> {
+	let databases = client.list_database_names(None, None).await.map_err(ErrorInner::ReadProfiler)?;
+	let options = FindOptions::builder()
+		.sort(doc! { "millis": -1 })
+		.limit(i64::try_from(limit).unwrap_or(i64::MAX))
+		.build();
+
+	let mut operations = Vec::new();
+	for database in databases {
+		let collection = client.database(&database).collection::<Document>("system.profile");
+		let documents: Vec<_> = collection
+			.find(None, options.clone())
+			.await
+			.map_err(ErrorInner::ReadProfiler)?
+			.try_collect()
+			.await
+			.map_err(ErrorInner::ReadProfiler)?;
+		operations.extend(documents.into_iter().map(ProfiledOperation::from_document));
+	}
+	operations.sort_by_key(|operation| std::cmp::Reverse(operation.duration));
+	operations.truncate(limit);
+	Ok(operations)
+}
+
+/// The number of operations of each type on a single collection, as recorded by the profiler.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OperationCounts {
+	/// The number of queries, including `find`, `aggregate`, `count` and `distinct`.
+	pub queries: u64,
+
+	/// The number of `getMore` operations on cursors of earlier queries.
+	pub get_mores: u64,
+
+	/// The number of insert operations.
+	pub inserts: u64,
+
+	/// The number of update operations.
+	pub updates: u64,
+
+	/// The number of delete operations.
+	pub deletes: u64,
+
+	/// The number of other commands, like `createIndexes`.
+	pub commands: u64,
+
+	/// The number of operations that scanned the whole collection instead of using an index.
+	pub collection_scans: u64,
+}
+
+/// Count the operations recorded by the profiler in all databases since a point in time, per namespace.
+///
+/// The reads of the `system.profile` collections themselves are not counted.
+pub async fn operation_summary(client: &mongodb::Client, since: DateTime) -> Result<BTreeMap<String, OperationCounts>, Error> {
+	let databases = client.list_database_names(None, None).await.map_err(ErrorInner::ReadProfiler)?;
+	let filter = doc! { "ts": { "$gte": since } };
+
+	let mut summary = BTreeMap::<String, OperationCounts>::new();
+	for database in databases {
+		let collection = client.database(&database).collection::<Document>("system.profile");
+		let documents: Vec<_> = collection
+			.find(filter.clone(), None)
+			.await
+			.map_err(ErrorInner::ReadProfiler)?
+			.try_collect()
+			.await
+			.map_err(ErrorInner::ReadProfiler)?;
+		for operation in documents.into_iter().map(ProfiledOperation::from_document) {
+			if operation.namespace.ends_with(".system.profile") {
+				continue;
+			}
+			let counts = summary.entry(operation.namespace).or_default();
+			match operation.operation.as_str() {
+				"query" => counts.queries += 1,
+				"getmore" => counts.get_mores += 1,
+				"insert" => counts.inserts += 1,
+				"update" => counts.updates += 1,
+				"remove" => counts.deletes += 1,
+				_ if is_query_command(&operation.raw) => counts.queries += 1,
+				_ => counts.commands += 1,
+			}
+			if operation.plan_summary.is_some_and(|plan| plan.starts_with("COLLSCAN")) {
+				counts.collection_scans += 1;
+			}
+		}
+	}
+	Ok(summary)
+}
+
+/// Check if a profiled command reads documents, like `aggregate` or `count`.
+fn is_query_command(raw: &Document) -> bool {
+	let Ok(command) = raw.get_document("command") else {
+		return false;
+	};
+	let name = command.keys().next().map(String::as_str).unwrap_or_default();
+	matches!(name, "aggregate" | "count" | "distinct")
+}
@@ -0,0 +1,161 @@
+use futures_util::stream::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::options::FindOptions;
+use mongodb::{Client, Collection, Cursor, Database};
+
+/// The number of documents inserted into the target collection at once.
+const INSERT_BATCH_SIZE: usize = 1000;
+
+/// Options for cloning data from a remote MongoDB deployment into a temporary instance.
+#[derive(Debug, Clone)]
+pub struct CloneOptions {
+	/// Only clone these collections.
+	///
+	/// Clone all collections of the selected databases if set to `None`.
+	pub collections: Option<Vec<String>>,
+
+	/// Only clone documents matching this filter.
+	pub filter: Option<Document>,
+
+	/// Clone a random sample of at most this many documents per collection.
+	pub sample_size: Option<u64>,
+
+	/// Clone at most this many documents per collection.
+	///
+	/// Ignored if `sample_size` is set.
+	pub limit: Option<i64>,
+
+	/// Also clone the indexes of each collection.
+	pub copy_indexes: bool,
+}
+
+impl CloneOptions {
+	/// Create new options that clone all documents and indexes.
+	pub fn new() -> Self {
+		Self {
+			collections: None,
+			filter: None,
+			sample_size: None,
+			limit: None,
+			copy_indexes: true,
+		}
+	}
+
+	/// Only clone the given collections.
+	pub fn collections<I, S>(mut self, collections: I) -> Self
+	where
+		I: IntoIterator<Item = S>,
+		S: Into<String>,
+	{
+		self.collections = Some(collections.into_iter().map(Into::into).collect());
+		self
+	}
+
+	/// Only clone documents matching the given filter.
+	pub fn filter(mut self, filter: Document) -> Self {
+		self.filter = Some(filter);
+		self
+	}
+
+	/// Clone a random sample of at most `sample_size` documents per collection.
+	pub fn sample_size(mut self, sample_size: u64) -> Self {
+		self.sample_size = Some(sample_size);
+		self
+	}
+
+	/// Clone at most `limit` documents per collection.
+	pub fn limit(mut self, limit: i64) -> Self {
+		self.limit = Some(limit);
+		self
+	}
+
+	/// Enable or disable cloning of indexes.
+	pub fn copy_indexes(mut self, copy_indexes: bool) -> Self {
+		self.copy_indexes = copy_indexes;
+		self
+	}
+
+	/// Clone the given databases from `source` into `target`.
+	///
+	/// # Errors
+	///
+	/// Returns an error if any MongoDB operation fails on either deployment.
+	pub async fn clone_databases(&self, source: &Client, target: &Client, databases: &[&str]) -> mongodb::error::Result<()> {
+		for name in databases {
+			let source = source.database(name);
+			let target = target.database(name);
+			for collection in self.collection_names(&source).await? {
+				self.clone_collection(&source, &target, &collection).await?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Get the names of the collections to clone from a database.
+	async fn collection_names(&self, database: &Database) -> mongodb::error::Result<Vec<String>> {
+		let names = database
+			.list_collection_names(doc! { "type": "collection" })
+			.await?
+			.into_iter()
+			.filter(|name| !name.starts_with("system."))
+			.filter(|name| match &self.collections {
+				Some(collections) => collections.contains(name),
+				None => true,
+			})
+			.collect();
+		Ok(names)
+	}
+
+	/// Clone the documents and indexes of a single collection.
+	async fn clone_collection(&self, source: &Database, target: &Database, name: &str) -> mongodb::error::Result<()> {
+		let source = source.collection::<Document>(name);
+		let target = target.collection::<Document>(name);
+		let filter = self.filter.clone().unwrap_or_default();
+
+		let cursor = match self.sample_size {
+			Some(size) => {
+				let pipeline = [doc! { "$match": filter }, doc! { "$sample": { "size": size as i64 } }];
+				source.aggregate(pipeline, None).await?
+			},
+			None => {
+				let options = FindOptions::builder().limit(self.limit).build();
+				source.find(filter, options).await?
+			},
+		};
+		insert_in_batches(cursor, &target).await?;
+
+		if self.copy_indexes {
+			let indexes: Vec<_> = source
+				.list_indexes(None)
+				.await?
+				.try_filter(|index| std::future::ready(index.keys != doc! { "_id": 1 }))
+				.try_collect()
+				.await?;
+			if !indexes.is_empty() {
+				target.create_indexes(indexes, None).await?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Insert all documents of a cursor into a collection, without loading them all into memory at once.
+async fn insert_in_batches(mut cursor: Cursor<Document>, target: &Collection<Document>) -> mongodb::error::Result<()> {
+	let mut batch = Vec::with_capacity(INSERT_BATCH_SIZE);
+	while let Some(document) = cursor.try_next().await? {
+		batch.push(document);
+		if batch.len() == INSERT_BATCH_SIZE {
+			target.insert_many(std::mem::take(&mut batch), None).await?;
+		}
+	}
+	if !batch.is_empty() {
+		target.insert_many(batch, None).await?;
+	}
+	Ok(())
+}
+
+impl Default for CloneOptions {
+	fn default() -> Self {
+		Self::new()
+	}
+}
@@ -0,0 +1,87 @@
+use std::process::Command;
+
+/// Resource limits for a spawned server process.
+///
+/// The limits are applied with `setrlimit` before the server is executed.
+/// They can be used to emulate constrained environments, or to prevent a runaway server from using all resources of the machine.
+///
+/// Resource limits are only supported on Unix, they are ignored on other platforms.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+	/// The maximum number of open file descriptors (`RLIMIT_NOFILE`).
+	open_files: Option<u64>,
+
+	/// The maximum size of the virtual address space in bytes (`RLIMIT_AS`).
+	address_space: Option<u64>,
+}
+
+impl ResourceLimits {
+	/// Create resource limits that do not limit anything.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Limit the number of open file descriptors (`RLIMIT_NOFILE`).
+	///
+	/// Note that `mongod` warns at startup if the limit is below 64000, and it needs a few file descriptors per collection and index.
+	pub fn open_files(mut self, limit: u64) -> Self {
+		self.open_files = Some(limit);
+		self
+	}
+
+	/// Limit the size of the virtual address space in bytes (`RLIMIT_AS`).
+	///
+	/// Note that the virtual address space of `mongod` is much larger than its resident memory.
+	/// Allocations beyond the limit fail, which typically makes the server abort.
+	pub fn address_space(mut self, bytes: u64) -> Self {
+		self.address_space = Some(bytes);
+		self
+	}
+
+	/// Get the limit on the number of open file descriptors.
+	pub fn get_open_files(&self) -> Option<u64> {
+		self.open_files
+	}
+
+	/// Get the limit on the size of the virtual address space in bytes.
+	pub fn get_address_space(&self) -> Option<u64> {
+		self.address_space
+	}
+
+	/// Apply the limits to a command before it is spawned.
+	pub(crate) fn apply(&self, command: &mut Command) {
+		#[cfg(unix)]
+		{
+			use std::os::unix::process::CommandExt;
+			if *self == Self::default() {
+				return;
+			}
+			let limits = [
+				(libc::RLIMIT_NOFILE, self.open_files),
+				(libc::RLIMIT_AS, self.address_space),
+			];
+			// SAFETY: The closure only calls async-signal-safe functions and does not allocate.
+			unsafe {
+				command.pre_exec(move || {
+					for (resource, limit) in limits {
+						let Some(limit) = limit else {
+							continue;
+						};
+						let limit = libc::rlimit {
+							rlim_cur: limit as libc::rlim_t,
+							rlim_max: limit as libc::rlim_t,
+						};
+						if libc::setrlimit(resource, &limit) == -1 {
+							return Err(std::io::Error::last_os_error());
+						}
+					}
+					Ok(())
+				});
+			}
+		}
+		#[cfg(not(unix))]
+		{
+			let _ = command;
+		}
+	}
+}
@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+/// How often to recreate a server or to repeat a download when it fails because of the infrastructure.
+///
+/// Only failures that are likely to be transient are retried, see [`Error::is_infrastructure()`](crate::Error::is_infrastructure).
+/// Errors caused by the configuration or the test, like a missing seed profile, are returned immediately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+	/// The maximum number of attempts, including the first one.
+	attempts: u32,
+
+	/// The time to wait before the first retry.
+	delay: Duration,
+
+	/// The maximum time to wait between attempts.
+	max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		Self::new(3)
+	}
+}
+
+impl RetryPolicy {
+	/// Create a policy that makes at most `attempts` attempts, including the first one.
+	///
+	/// The delay between attempts starts at 500 milliseconds and doubles after each retry, up to 10 seconds.
+	/// A policy with zero attempts still makes a single attempt.
+	pub fn new(attempts: u32) -> Self {
+		Self {
+			attempts: attempts.max(1),
+			delay: Duration::from_millis(500),
+			max_delay: Duration::from_secs(10),
+		}
+	}
+
+	/// Set the time to wait before the first retry.
+	///
+	/// The delay doubles after each retry.
+	pub fn delay(mut self, delay: Duration) -> Self {
+		self.delay = delay;
+		self
+	}
+
+	/// Set the maximum time to wait between attempts.
+	pub fn max_delay(mut self, max_delay: Duration) -> Self {
+		self.max_delay = max_delay;
+		self
+	}
+
+	/// Get the maximum number of attempts, including the first one.
+	pub fn attempts(&self) -> u32 {
+		self.attempts
+	}
+
+	/// Get the time to wait after a failed attempt, counting from 1.
+	pub fn delay_after(&self, attempt: u32) -> Duration {
+		let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+		self.delay.saturating_mul(factor).min(self.max_delay)
+	}
+}
@@ -0,0 +1,54 @@
+use crate::error::ErrorInner;
+use crate::Error;
+use std::path::{Path, PathBuf};
+
+/// Copies the log of a server to another directory when the server is cleaned up,
+/// so it survives the removal of the temporary directory.
+///
+/// If the server is dropped while the thread is panicking, the log is copied by the [`Drop`] implementation,
+/// so the log of a failed test is kept too.
+#[derive(Debug)]
+pub struct SavedLog {
+	/// The path of the log file of the server.
+	source: PathBuf,
+
+	/// The path to copy the log file to.
+	destination: PathBuf,
+
+	/// The log has already been copied.
+	saved: bool,
+}
+
+impl SavedLog {
+	/// Prepare to copy a log file to `directory`, as `<name>.log`.
+	pub fn new(source: &Path, directory: &Path, name: &str) -> Self {
+		Self {
+			source: source.to_owned(),
+			destination: directory.join(format!("{name}.log")),
+			saved: false,
+		}
+	}
+
+	/// Get the path the log file is copied to.
+	pub fn destination(&self) -> &Path {
+		&self.destination
+	}
+
+	/// Copy the log file, creating the destination directory if needed.
+	pub fn save(&mut self) -> Result<(), Error> {
+		if let Some(parent) = self.destination.parent() {
+			std::fs::create_dir_all(parent).map_err(|e| ErrorInner::SaveLog(self.destination.clone(), e))?;
+		}
+		std::fs::copy(&self.source, &self.destination).map_err(|e| ErrorInner::SaveLog(self.destination.clone(), e))?;
+		self.saved = true;
+		Ok(())
+	}
+}
+
+impl Drop for SavedLog {
+	fn drop(&mut self) {
+		if !self.saved && std::thread::panicking() {
+			self.save().ok();
+		}
+	}
+}
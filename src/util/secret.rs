@@ -0,0 +1,77 @@
+use serde::Deserialize;
+use std::borrow::Cow;
+
+/// The text shown instead of a secret.
+const REDACTED: &str = "***";
+
+/// A string that is redacted in [`Debug`] and [`Display`][std::fmt::Display] output.
+///
+/// Use [`SecretString::expose()`] to get the actual value.
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct SecretString(String);
+
+impl SecretString {
+	/// Wrap a secret string.
+	pub fn new(secret: impl Into<String>) -> Self {
+		Self(secret.into())
+	}
+
+	/// Get the actual value of the secret.
+	pub fn expose(&self) -> &str {
+		&self.0
+	}
+}
+
+impl std::fmt::Debug for SecretString {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(REDACTED)
+	}
+}
+
+impl std::fmt::Display for SecretString {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(REDACTED)
+	}
+}
+
+impl From<String> for SecretString {
+	fn from(secret: String) -> Self {
+		Self(secret)
+	}
+}
+
+impl From<&str> for SecretString {
+	fn from(secret: &str) -> Self {
+		Self(secret.to_owned())
+	}
+}
+
+/// Redact the passwords in the user info of all connection strings in a text.
+///
+/// Other text is returned unchanged, so this can be used on anything that may contain a connection string,
+/// like error messages.
+pub fn redact_uri(text: &str) -> Cow<'_, str> {
+	let mut output = String::new();
+	let mut rest = text;
+	while let Some(scheme_end) = rest.find("://").map(|index| index + 3) {
+		let authority_end = rest[scheme_end..]
+			.find(|c: char| c == '/' || c == '?' || c.is_whitespace())
+			.map_or(rest.len(), |index| scheme_end + index);
+		let authority = &rest[scheme_end..authority_end];
+		match (authority.rfind('@'), authority.find(':')) {
+			(Some(at), Some(colon)) if colon < at => {
+				output.push_str(&rest[..scheme_end + colon + 1]);
+				output.push_str(REDACTED);
+				output.push_str(&authority[at..]);
+			},
+			_ => output.push_str(&rest[..authority_end]),
+		}
+		rest = &rest[authority_end..];
+	}
+	if output.is_empty() {
+		return Cow::Borrowed(text);
+	}
+	output.push_str(rest);
+	Cow::Owned(output)
+}
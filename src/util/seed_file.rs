@@ -0,0 +1,58 @@
+use flate2::read::GzDecoder;
+use mongodb::bson::Document;
+use std::io::Read;
+use std::path::Path;
+
+use crate::error::ErrorInner;
+use crate::Error;
+
+/// Check if a path has a `.gz` extension.
+fn is_gzip(path: &Path) -> bool {
+	path.extension().is_some_and(|extension| extension == "gz")
+}
+
+/// Read the contents of a seed file, decompressing it if it has a `.gz` extension.
+pub fn read_seed_file(path: &Path) -> Result<Vec<u8>, Error> {
+	let data = std::fs::read(path)
+		.map_err(|e| ErrorInner::ReadSeedFile(path.to_owned(), e))?;
+	if !is_gzip(path) {
+		return Ok(data);
+	}
+	let mut decompressed = Vec::new();
+	GzDecoder::new(data.as_slice())
+		.read_to_end(&mut decompressed)
+		.map_err(|e| ErrorInner::ReadSeedFile(path.to_owned(), e))?;
+	Ok(decompressed)
+}
+
+/// Parse a JSON seed file, decompressing it if it has a `.gz` extension.
+pub fn parse_json_seed_file<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, Error> {
+	let data = read_seed_file(path)?;
+	let value = serde_json::from_slice(&data)
+		.map_err(|e| ErrorInner::ParseSeedFile(path.to_owned(), e))?;
+	Ok(value)
+}
+
+/// Parse a BSON seed file as written by `mongodump`, decompressing it if it has a `.gz` extension.
+///
+/// The file must contain a sequence of BSON documents.
+pub fn parse_bson_seed_file(path: &Path) -> Result<Vec<Document>, Error> {
+	let data = read_seed_file(path)?;
+	let mut reader = data.as_slice();
+	let mut documents = Vec::new();
+	while !reader.is_empty() {
+		let document = Document::from_reader(&mut reader)
+			.map_err(|e| ErrorInner::ParseBsonSeedFile(path.to_owned(), e))?;
+		documents.push(document);
+	}
+	Ok(documents)
+}
+
+/// Strip the `.bson` or `.bson.gz` extension from a file name.
+///
+/// Returns `None` if the file name does not have one of these extensions.
+pub fn bson_collection_name(file_name: &str) -> Option<&str> {
+	file_name
+		.strip_suffix(".bson.gz")
+		.or_else(|| file_name.strip_suffix(".bson"))
+}
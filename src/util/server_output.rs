@@ -0,0 +1,76 @@
+use crate::error::ErrorInner;
+use crate::util::{event, spawn_killed_with_parent, KillOnDrop, ResourceLimits};
+use crate::Error;
+use std::ffi::{OsStr, OsString};
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::process::Command;
+
+/// The number of lines of each file to include in error messages.
+const TAIL_LINES: usize = 20;
+
+/// Spawn a server process, appending its stdout and stderr to `output_path`.
+///
+/// The server is terminated when the current process dies, see [`spawn_killed_with_parent()`].
+pub fn spawn_server(command: &OsStr, args: &[OsString], output_path: &Path, limits: &ResourceLimits) -> Result<KillOnDrop, Error> {
+	let spawn_error = |e| ErrorInner::SpawnServer(command.to_string_lossy().into(), e);
+	let output = OpenOptions::new()
+		.create(true)
+		.append(true)
+		.open(output_path)
+		.map_err(spawn_error)?;
+	let mut server = Command::new(command);
+	server
+		.args(args)
+		.stdout(output.try_clone().map_err(spawn_error)?)
+		.stderr(output);
+	limits.apply(&mut server);
+	event!(debug, target: "temp_mongo::spawn", "spawning {} {:?}", command.to_string_lossy(), args);
+	let server = spawn_killed_with_parent(server).map_err(spawn_error)?;
+	event!(debug, target: "temp_mongo::spawn", "spawned {} with PID {}", command.to_string_lossy(), server.id());
+	Ok(KillOnDrop::new(server))
+}
+
+/// Get the arguments that control the log verbosity of a server.
+///
+/// The verbosity is capped at the maximum level of 5.
+pub fn verbosity_args(quiet: bool, verbosity: u8) -> Vec<OsString> {
+	let mut args = Vec::new();
+	if quiet {
+		args.push("--quiet".into());
+	}
+	if verbosity > 0 {
+		args.push(format!("-{}", "v".repeat(verbosity.min(5).into())).into());
+	}
+	args
+}
+
+/// Get the last lines of the captured output and the log file of a server, for inclusion in error messages.
+///
+/// Files that do not exist or are empty are skipped.
+pub fn server_output_tail(output_path: &Path, log_path: &Path) -> String {
+	let mut tail = String::new();
+	for path in [output_path, log_path] {
+		let lines = last_lines(path, TAIL_LINES);
+		if lines.is_empty() {
+			continue;
+		}
+		tail.push_str(&format!("\nLast lines of {}:", path.display()));
+		for line in &lines {
+			tail.push_str("\n  ");
+			tail.push_str(line);
+		}
+	}
+	tail
+}
+
+/// Read the last `count` lines of a file.
+///
+/// Returns an empty list if the file can not be read.
+pub fn last_lines(path: &Path, count: usize) -> Vec<String> {
+	let Ok(data) = std::fs::read_to_string(path) else {
+		return Vec::new();
+	};
+	let lines: Vec<_> = data.lines().collect();
+	lines[lines.len().saturating_sub(count)..].iter().map(|line| line.to_string()).collect()
+}
@@ -0,0 +1,124 @@
+use crate::error::ErrorInner;
+use crate::Error;
+use mongodb::bson::{doc, Bson, Document};
+use std::path::Path;
+
+/// A snapshot of the output of the `serverStatus` command.
+///
+/// The most useful counters for spotting resource issues are extracted into fields.
+/// All other statistics are available in the full reply, see [`Self::raw()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerStatus {
+	/// The number of open incoming connections.
+	pub connections_current: i64,
+
+	/// The number of incoming connections that can still be opened.
+	pub connections_available: i64,
+
+	/// The number of incoming connections opened since the server started.
+	pub connections_total_created: i64,
+
+	/// The number of operations of each type since the server started.
+	pub opcounters: Opcounters,
+
+	/// The number of bytes currently in the WiredTiger cache.
+	///
+	/// This is `None` for other storage engines.
+	pub wired_tiger_cache_bytes: Option<i64>,
+
+	/// The maximum number of bytes of the WiredTiger cache.
+	///
+	/// This is `None` for other storage engines.
+	pub wired_tiger_cache_max_bytes: Option<i64>,
+
+	/// The full reply of the `serverStatus` command.
+	raw: Document,
+}
+
+/// The number of operations of each type, from the `opcounters` section of `serverStatus`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Opcounters {
+	/// The number of insert operations.
+	pub insert: i64,
+
+	/// The number of query operations.
+	pub query: i64,
+
+	/// The number of update operations.
+	pub update: i64,
+
+	/// The number of delete operations.
+	pub delete: i64,
+
+	/// The number of `getMore` operations.
+	pub getmore: i64,
+
+	/// The number of other commands.
+	pub command: i64,
+}
+
+impl ServerStatus {
+	/// Run the `serverStatus` command and parse the reply.
+	pub async fn fetch(client: &mongodb::Client) -> Result<Self, Error> {
+		let reply = client
+			.database("admin")
+			.run_command(doc! { "serverStatus": 1 }, None)
+			.await
+			.map_err(ErrorInner::ServerStatus)?;
+		Ok(Self::from_document(reply))
+	}
+
+	/// Parse a reply of the `serverStatus` command.
+	///
+	/// Missing counters are set to zero.
+	pub fn from_document(raw: Document) -> Self {
+		let counter = |section: &str, name: &str| number(&raw, section, name).unwrap_or(0);
+		let opcounters = Opcounters {
+			insert: counter("opcounters", "insert"),
+			query: counter("opcounters", "query"),
+			update: counter("opcounters", "update"),
+			delete: counter("opcounters", "delete"),
+			getmore: counter("opcounters", "getmore"),
+			command: counter("opcounters", "command"),
+		};
+		let cache = raw.get_document("wiredTiger").and_then(|wired_tiger| wired_tiger.get_document("cache")).ok();
+		let cache_number = |name: &str| cache.and_then(|cache| as_i64(cache.get(name)?));
+		Self {
+			connections_current: counter("connections", "current"),
+			connections_available: counter("connections", "available"),
+			connections_total_created: counter("connections", "totalCreated"),
+			opcounters,
+			wired_tiger_cache_bytes: cache_number("bytes currently in the cache"),
+			wired_tiger_cache_max_bytes: cache_number("maximum bytes configured"),
+			raw,
+		}
+	}
+
+	/// Get the full reply of the `serverStatus` command.
+	pub fn raw(&self) -> &Document {
+		&self.raw
+	}
+
+	/// Write the full reply as pretty-printed relaxed extended JSON to a file.
+	pub fn write_to_file(&self, path: &Path) -> Result<(), Error> {
+		let json = Bson::Document(self.raw.clone()).into_relaxed_extjson();
+		let json = serde_json::to_string_pretty(&json).map_err(|e| ErrorInner::WriteServerStatus(path.to_owned(), e.into()))?;
+		std::fs::write(path, json).map_err(|e| ErrorInner::WriteServerStatus(path.to_owned(), e))?;
+		Ok(())
+	}
+}
+
+/// Get a number from a section of a document, whatever its numeric BSON type.
+fn number(document: &Document, section: &str, name: &str) -> Option<i64> {
+	as_i64(document.get_document(section).ok()?.get(name)?)
+}
+
+/// Convert a numeric BSON value to an `i64`.
+fn as_i64(value: &Bson) -> Option<i64> {
+	match value {
+		Bson::Int32(value) => Some((*value).into()),
+		Bson::Int64(value) => Some(*value),
+		Bson::Double(value) => Some(*value as i64),
+		_ => None,
+	}
+}
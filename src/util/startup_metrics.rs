@@ -0,0 +1,58 @@
+use std::time::{Duration, Instant};
+
+/// The time spent in each phase of starting a server.
+///
+/// Use this to find out where the setup time of a test goes.
+/// Downloading binaries with [`MongoDownloader`][crate::MongoDownloader] happens before the server is spawned,
+/// so it is not included.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StartupMetrics {
+	/// The time spent creating the temporary directory, configuration files and TLS certificates.
+	pub prepare: Duration,
+
+	/// The time spent starting the server process, including retries on a new port.
+	pub spawn: Duration,
+
+	/// The time from starting the process until the server accepted commands, including creating users.
+	pub ready: Duration,
+
+	/// The time spent spawning `mongocryptd`, if requested.
+	pub cryptd: Duration,
+
+	/// The time spent loading the seed profile, if requested.
+	pub seed: Duration,
+
+	/// The total time spent starting the server.
+	pub total: Duration,
+}
+
+/// Measures the time between checkpoints of a startup sequence.
+#[derive(Debug)]
+pub struct Stopwatch {
+	/// The start of the sequence.
+	start: Instant,
+
+	/// The last checkpoint.
+	last: Instant,
+}
+
+impl Stopwatch {
+	/// Start measuring.
+	pub fn start() -> Self {
+		let now = Instant::now();
+		Self { start: now, last: now }
+	}
+
+	/// Get the time since the last checkpoint, and make now the last checkpoint.
+	pub fn lap(&mut self) -> Duration {
+		let now = Instant::now();
+		let elapsed = now - self.last;
+		self.last = now;
+		elapsed
+	}
+
+	/// Get the time since the start.
+	pub fn total(&self) -> Duration {
+		self.start.elapsed()
+	}
+}
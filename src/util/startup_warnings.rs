@@ -0,0 +1,19 @@
+use mongodb::bson::{doc, Bson};
+
+/// Get the warnings the server logged at startup, with the `getLog` command.
+///
+/// Each warning is a log line, which is a JSON document since MongoDB 4.4.
+pub async fn startup_warnings(client: &mongodb::Client) -> mongodb::error::Result<Vec<String>> {
+	let reply = client
+		.database("admin")
+		.run_command(doc! { "getLog": "startupWarnings" }, None)
+		.await?;
+	let lines = reply
+		.get_array("log")
+		.map_err(|e| mongodb::error::Error::custom(e.to_string()))?
+		.iter()
+		.filter_map(Bson::as_str)
+		.map(str::to_owned)
+		.collect();
+	Ok(lines)
+}
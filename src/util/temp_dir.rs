@@ -60,7 +60,26 @@ impl TempDir {
 	/// This ignore the value of `clean_on_drop`.
 	/// The directory will be cleaned up immediately.
 	pub fn close(self) -> std::io::Result<()> {
-		self.into_inner().close()
+		#[cfg(windows)]
+		{
+			// On Windows, files of a killed process can remain locked for a short while after it has exited.
+			let path = self.into_path();
+			let mut attempts = 0;
+			loop {
+				match std::fs::remove_dir_all(&path) {
+					// Error code 32 is `ERROR_SHARING_VIOLATION`.
+					Err(e) if (e.kind() == std::io::ErrorKind::PermissionDenied || e.raw_os_error() == Some(32)) && attempts < 20 => {
+						attempts += 1;
+						std::thread::sleep(std::time::Duration::from_millis(50));
+					},
+					result => return result,
+				}
+			}
+		}
+		#[cfg(not(windows))]
+		{
+			self.into_inner().close()
+		}
 	}
 }
 
@@ -0,0 +1,150 @@
+use crate::download::run;
+use crate::error::ErrorInner;
+use crate::Error;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The number of days the generated certificates are valid.
+const VALID_DAYS: &str = "30";
+
+/// The subject of the generated client certificate, in the order used by `openssl`.
+const CLIENT_SUBJECT: &str = "/O=temp-mongo/OU=clients/CN=client";
+
+/// The subject of the generated client certificate in RFC 2253 format, which is the name of its `$external` user.
+pub const CLIENT_USERNAME: &str = "CN=client,OU=clients,O=temp-mongo";
+
+/// Self-signed TLS certificates for a temporary server.
+///
+/// The certificates are generated with the `openssl` command, which must be available in `PATH`.
+#[derive(Debug, Clone)]
+pub struct TlsCertificates {
+	/// The directory with the generated files.
+	directory: PathBuf,
+
+	/// The certificate of the certificate authority that signed all other certificates.
+	ca_file: PathBuf,
+
+	/// The certificate and private key of the server.
+	server_pem: PathBuf,
+
+	/// The certificate and private key of a client, if generated.
+	client_pem: Option<PathBuf>,
+}
+
+impl TlsCertificates {
+	/// Generate a certificate authority and a server certificate for `localhost` and `127.0.0.1` in the given directory.
+	pub fn generate(directory: &Path) -> Result<Self, Error> {
+		let ca_file = directory.join("ca.pem");
+		let ca_key = directory.join("ca.key");
+		openssl(directory, |command| {
+			command
+				.args(["req", "-x509", "-newkey", "rsa:2048", "-nodes", "-days", VALID_DAYS])
+				.args(["-subj", "/CN=temp-mongo CA"])
+				.args(["-addext", "basicConstraints=critical,CA:TRUE"])
+				.args(["-addext", "keyUsage=critical,keyCertSign,cRLSign"])
+				.arg("-keyout")
+				.arg(&ca_key)
+				.arg("-out")
+				.arg(&ca_file);
+		})?;
+
+		let certificates = Self {
+			directory: directory.to_owned(),
+			ca_file,
+			server_pem: directory.join("server.pem"),
+			client_pem: None,
+		};
+		certificates.sign("server", "/CN=localhost", "subjectAltName=DNS:localhost,IP:127.0.0.1\nextendedKeyUsage=serverAuth\n")?;
+		Ok(certificates)
+	}
+
+	/// Get the path of the certificate of the certificate authority.
+	pub fn ca_file(&self) -> &Path {
+		&self.ca_file
+	}
+
+	/// Get the path of the PEM file with the certificate and private key of the server.
+	#[cfg(feature = "mock-kms")]
+	pub fn server_pem(&self) -> &Path {
+		&self.server_pem
+	}
+
+	/// Generate a client certificate for x.509 authentication.
+	///
+	/// The subject of the certificate is [`CLIENT_USERNAME`].
+	pub fn generate_client(&mut self) -> Result<(), Error> {
+		let pem = self.sign("client", CLIENT_SUBJECT, "extendedKeyUsage=clientAuth\n")?;
+		self.client_pem = Some(pem);
+		Ok(())
+	}
+
+	/// Get the path of the PEM file with the certificate and private key of the client, if generated.
+	pub fn client_pem(&self) -> Option<&Path> {
+		self.client_pem.as_deref()
+	}
+
+	/// Get the `mongod` arguments to require TLS with these certificates.
+	///
+	/// Clients may still connect without a client certificate.
+	pub fn server_args(&self) -> Vec<OsString> {
+		vec![
+			"--tlsMode".into(),
+			"requireTLS".into(),
+			"--tlsCertificateKeyFile".into(),
+			self.server_pem.clone().into(),
+			"--tlsCAFile".into(),
+			self.ca_file.clone().into(),
+			"--tlsAllowConnectionsWithoutCertificates".into(),
+		]
+	}
+
+	/// Generate a key and a certificate signed by the certificate authority.
+	///
+	/// The certificate and the key are written together to `<name>.pem`.
+	fn sign(&self, name: &str, subject: &str, extensions: &str) -> Result<PathBuf, Error> {
+		let key = self.directory.join(format!("{name}.key"));
+		let csr = self.directory.join(format!("{name}.csr"));
+		let certificate = self.directory.join(format!("{name}.crt"));
+		let extensions_file = self.directory.join(format!("{name}.ext"));
+		let pem = self.directory.join(format!("{name}.pem"));
+
+		std::fs::write(&extensions_file, extensions).map_err(|e| ErrorInner::GenerateCertificate(extensions_file.clone(), e))?;
+		openssl(&self.directory, |command| {
+			command
+				.args(["req", "-newkey", "rsa:2048", "-nodes", "-subj", subject])
+				.arg("-keyout")
+				.arg(&key)
+				.arg("-out")
+				.arg(&csr);
+		})?;
+		openssl(&self.directory, |command| {
+			command
+				.args(["x509", "-req", "-days", VALID_DAYS, "-CAcreateserial"])
+				.arg("-in")
+				.arg(&csr)
+				.arg("-CA")
+				.arg(&self.ca_file)
+				.arg("-CAkey")
+				.arg(self.directory.join("ca.key"))
+				.arg("-extfile")
+				.arg(&extensions_file)
+				.arg("-out")
+				.arg(&certificate);
+		})?;
+
+		let read = |path: &Path| std::fs::read_to_string(path).map_err(|e| ErrorInner::GenerateCertificate(path.to_owned(), e));
+		let contents = read(&certificate)? + &read(&key)?;
+		std::fs::write(&pem, contents).map_err(|e| ErrorInner::GenerateCertificate(pem.clone(), e))?;
+		Ok(pem)
+	}
+}
+
+/// Run an `openssl` command in the given directory.
+fn openssl(directory: &Path, configure: impl FnOnce(&mut Command)) -> Result<(), Error> {
+	let mut command = Command::new("openssl");
+	command.current_dir(directory);
+	configure(&mut command);
+	run(&mut command).map_err(|e| ErrorInner::GenerateCertificate(directory.to_owned(), e))?;
+	Ok(())
+}
@@ -0,0 +1,31 @@
+/// Emit a diagnostic event, if the `tracing` or `log` feature is enabled.
+///
+/// The arguments are passed on to the macro for the given level, like `event!(debug, target: "temp_mongo::spawn", "...")`.
+/// With both features enabled, the event is emitted through both facades.
+/// Without either feature, the event and its arguments are not evaluated at all.
+macro_rules! event {
+	($level:ident, $($arg:tt)+) => {
+		#[cfg(feature = "tracing")]
+		{
+			tracing::$level!($($arg)+);
+		}
+		#[cfg(feature = "log")]
+		{
+			log::$level!($($arg)+);
+		}
+	};
+}
+pub(crate) use event;
+
+/// Record the value of a field of the current span, if the `tracing` feature is enabled.
+///
+/// The field must be declared when the span is created, for example as `tracing::field::Empty`.
+macro_rules! record {
+	($field:literal, $value:expr) => {
+		#[cfg(feature = "tracing")]
+		{
+			tracing::Span::current().record($field, $value);
+		}
+	};
+}
+pub(crate) use record;
@@ -0,0 +1,59 @@
+use serde::Deserialize;
+use mongodb::{Client, bson::Document};
+use mongodb::options::CreateCollectionOptions;
+
+/// Read-only view to create in a mongodb instance
+///
+/// The view is created in the database `database_name` and is backed by the collection or view `source`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ViewSeeder {
+	/// The name of the database to create the view in.
+	pub database_name: String,
+	/// The name of the view.
+	pub view_name: String,
+	/// The name of the collection or view the view is defined on.
+	pub source: String,
+	/// The aggregation pipeline that defines the view.
+	#[serde(default)]
+	pub pipeline: Vec<Document>,
+}
+
+impl ViewSeeder {
+	/// Creates a new `ViewSeeder` instance with specified values.
+	///
+	/// # Arguments
+	///
+	/// * `database_name` - The name of the database to create the view in.
+	/// * `view_name` - The name of the view.
+	/// * `source` - The name of the collection or view the view is defined on.
+	/// * `pipeline` - The aggregation pipeline that defines the view.
+	pub fn new(database_name: &str, view_name: &str, source: &str, pipeline: Vec<Document>) -> Self {
+		Self {
+			database_name: database_name.to_string(),
+			view_name: view_name.to_string(),
+			source: source.to_string(),
+			pipeline,
+		}
+	}
+
+	/// Creates the view in the database.
+	///
+	/// # Arguments
+	///
+	/// * `client` - A reference to the MongoDB client to use for creating the view.
+	///
+	/// # Errors
+	///
+	/// Returns an error if the view could not be created, for example because it already exists.
+	#[cfg_attr(feature = "tracing", tracing::instrument(target = "temp_mongo::seed", skip_all, fields(database = %self.database_name, view = %self.view_name)))]
+	pub async fn seed_view(&self, client: &Client) -> mongodb::error::Result<()> {
+		let options = CreateCollectionOptions::builder()
+			.view_on(self.source.clone())
+			.pipeline(self.pipeline.clone())
+			.build();
+		client
+			.database(&self.database_name)
+			.create_collection(&self.view_name, options)
+			.await
+	}
+}
@@ -118,3 +118,1861 @@ async fn seeding_document() {
 
     assert!(let Ok(()) = mongo.kill_and_clean().await);
 }
+
+/// Seeds documents with an explicit write and read concern and
+/// verifies that the seeded documents can be read back
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn seeding_with_concerns() {
+    use mongodb::options::{Acknowledgment, ReadConcern, WriteConcern};
+
+    let_assert!(Ok(mongo) = TempMongo::new().await);
+
+    let documents = vec![doc! {"name": "Alice", "age": 30}];
+    let seed_data = mongo
+        .prepare_seed_document("test_4", "trex", documents)
+        .write_concern(WriteConcern::builder().w(Acknowledgment::Nodes(1)).build())
+        .read_concern(ReadConcern::local());
+
+    assert!(let Ok(()) = mongo.load_document(&seed_data).await);
+    assert!(let Ok(true) = mongo.verify_document(&seed_data).await);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Seeds a collection with a case-insensitive collation and
+/// refuses to change the collation of an existing collection
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn seeding_with_collation() {
+    use mongodb::options::{Collation, CollationStrength};
+
+    let_assert!(Ok(mongo) = TempMongo::new().await);
+
+    let collation = Collation::builder().locale("en").strength(CollationStrength::Secondary).build();
+    let seed_data = mongo
+        .prepare_seed_document("collation", "people", vec![doc! {"name": "Alice"}])
+        .collation(collation);
+    assert!(let Ok(()) = mongo.load_document(&seed_data).await);
+
+    let collection = mongo.client().database("collation").collection::<Document>("people");
+    let_assert!(Ok(Some(_)) = collection.find_one(doc! {"name": "ALICE"}, None).await);
+
+    assert!(let Err(_) = mongo.load_document(&seed_data).await);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Clones filtered documents and indexes from another server
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn clone_from() {
+    use mongodb::IndexModel;
+    use temp_mongo::CloneOptions;
+
+    let_assert!(Ok(source) = TempMongo::new().await);
+    let animals = source.client().database("zoo").collection::<Document>("animals");
+    let documents: Vec<_> = (0..10).map(|i| doc! { "species": if i % 2 == 0 { "dog" } else { "cat" }, "number": i }).collect();
+    assert!(let Ok(_) = animals.insert_many(documents, None).await);
+    assert!(let Ok(_) = animals.create_index(IndexModel::builder().keys(doc! { "species": 1 }).build(), None).await);
+
+    let_assert!(Ok(target) = TempMongo::new().await);
+    let options = CloneOptions::new().filter(doc! { "species": "dog" }).limit(3).copy_indexes(true);
+    assert!(let Ok(()) = target.clone_from(&source.uri(), &["zoo"], &options).await);
+
+    let cloned = target.client().database("zoo").collection::<Document>("animals");
+    assert!(let Ok(3) = cloned.count_documents(doc! { "species": "dog" }, None).await);
+    assert!(let Ok(0) = cloned.count_documents(doc! { "species": "cat" }, None).await);
+    let_assert!(Ok(indexes) = cloned.list_index_names().await);
+    assert!(indexes.contains(&"species_1".to_string()));
+
+    assert!(let Ok(()) = target.kill_and_clean().await);
+    assert!(let Ok(()) = source.kill_and_clean().await);
+}
+
+/// Loads a manifest with a seeded collection and a view on top of it,
+/// and tests if the view only returns the matching documents
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn manifest_with_view() {
+    let_assert!(Ok(mongo) = TempMongo::new().await);
+
+    let_assert!(Ok(manifest) = temp_mongo::Manifest::from_json(r#"{
+        "seeds": [{
+            "database_name": "test_5",
+            "collection_name": "animals",
+            "documents": [{ "species": "dog" }, { "species": "T-Rex" }]
+        }],
+        "views": [{
+            "database_name": "test_5",
+            "view_name": "dogs",
+            "source": "animals",
+            "pipeline": [{ "$match": { "species": "dog" } }]
+        }]
+    }"#));
+    assert!(let Ok(()) = mongo.load_manifest(&manifest).await);
+
+    let view = mongo.client().database("test_5").collection::<Document>("dogs");
+    let_assert!(Ok(count) = view.count_documents(None, None).await);
+    assert!(count == 1);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Seeds a manifest, modifies the collection and
+/// tests if the database diff reports the modifications
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn diff_database() {
+    let_assert!(Ok(mongo) = TempMongo::new().await);
+
+    let seed = mongo.prepare_seed_document(
+        "test_6",
+        "people",
+        vec![doc! {"name": "Alice", "age": 30}, doc! {"name": "Bob", "age": 25}],
+    );
+    let manifest = temp_mongo::Manifest::new().seed(seed);
+    assert!(let Ok(()) = mongo.load_manifest(&manifest).await);
+
+    let_assert!(Ok(diff) = mongo.diff_database("test_6", &manifest).await);
+    assert!(diff.is_empty());
+
+    let collection = mongo.client().database("test_6").collection::<Document>("people");
+    assert!(let Ok(_) = collection.update_one(doc! {"name": "Bob"}, doc! {"$set": {"age": 26}}, None).await);
+    assert!(let Ok(_) = collection.insert_one(doc! {"name": "Carol", "age": 41}, None).await);
+
+    let_assert!(Ok(diff) = mongo.diff_database("test_6", &manifest).await);
+    let_assert!([people] = diff.collections.as_slice());
+    assert!(people.missing.is_empty());
+    assert!(people.extra == vec![doc! {"name": "Carol", "age": 41}]);
+    let_assert!([mismatch] = people.mismatched.as_slice());
+    assert!(mismatch.fields == vec!["age".to_string()]);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Tests the assertion helpers on a seeded collection
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn assertion_helpers() {
+    use temp_mongo::assertions::{assert_collection_eq, assert_contains, assert_doc_count};
+
+    let_assert!(Ok(mongo) = TempMongo::new().await);
+
+    let documents = vec![doc! {"name": "Alice", "age": 30}, doc! {"name": "Bob", "age": 25}];
+    let seed_data = mongo.prepare_seed_document("test_7", "people", documents.clone());
+    assert!(let Ok(()) = mongo.load_document(&seed_data).await);
+
+    let collection = mongo.client().database("test_7").collection::<Document>("people");
+    assert_doc_count(&collection, None, 2).await;
+    assert_doc_count(&collection, doc! {"age": {"$gt": 26}}, 1).await;
+    assert_contains(&collection, doc! {"name": "Bob"}).await;
+    assert_collection_eq(&collection, &documents).await;
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Exports a seeded collection to a JSON file and
+/// tests if the file can be seeded into another collection
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn export_and_replay_collection() {
+    use temp_mongo::assertions::assert_collection_eq;
+
+    let_assert!(Ok(mongo) = TempMongo::new().await);
+
+    let documents = vec![doc! {"name": "Alice", "age": 30}, doc! {"name": "Bob", "age": 25}];
+    let seed_data = mongo.prepare_seed_document("test_8", "people", documents.clone());
+    assert!(let Ok(()) = mongo.load_document(&seed_data).await);
+
+    let path = mongo.directory().join("people.json");
+    assert!(let Ok(()) = mongo.export_collection("test_8", "people", &path).await);
+
+    let_assert!(Ok(replay) = temp_mongo::DataSeeder::from_json_file("test_8", "replay", &path));
+    assert!(let Ok(()) = mongo.load_document(&replay).await);
+
+    let collection = mongo.client().database("test_8").collection::<Document>("replay");
+    assert_collection_eq(&collection, &documents).await;
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Seeds documents from a gzip-compressed JSON file
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn seeding_from_gzip_file() {
+    use std::io::Write;
+    use temp_mongo::assertions::assert_collection_eq;
+
+    let_assert!(Ok(mongo) = TempMongo::new().await);
+
+    let path = mongo.directory().join("people.json.gz");
+    let_assert!(Ok(file) = std::fs::File::create(&path));
+    let mut encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+    assert!(let Ok(()) = encoder.write_all(br#"[{"name": "Alice", "age": 30}]"#));
+    assert!(let Ok(_) = encoder.finish());
+
+    let_assert!(Ok(seed_data) = temp_mongo::DataSeeder::from_json_file("test_9", "people", &path));
+    assert!(let Ok(()) = mongo.load_document(&seed_data).await);
+
+    let collection = mongo.client().database("test_9").collection::<Document>("people");
+    assert_collection_eq(&collection, &[doc! {"name": "Alice", "age": 30}]).await;
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Registers two seed profiles and tests if only the selected profile is seeded
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn seed_profiles() {
+    use temp_mongo::assertions::assert_doc_count;
+    use temp_mongo::{DataSeeder, Manifest};
+
+    let minimal = Manifest::new().seed(DataSeeder::new().new_in("test_10", "people", vec![doc! {"name": "Alice"}]));
+    let full = Manifest::new().seed(DataSeeder::new().new_in(
+        "test_10",
+        "people",
+        vec![doc! {"name": "Alice"}, doc! {"name": "Bob"}, doc! {"name": "Carol"}],
+    ));
+
+    let_assert!(Ok(mongo) = TempMongo::builder()
+        .seed_profile("minimal", minimal)
+        .seed_profile("full", full)
+        .with_profile("minimal")
+        .spawn()
+        .await);
+
+    let collection = mongo.client().database("test_10").collection::<Document>("people");
+    assert_doc_count(&collection, None, 1).await;
+    assert!(let Err(_) = mongo.load_profile("perf").await);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Spawns a replica set and tests if a document written with majority write concern
+/// can be read back
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn replica_set_insert_and_find() {
+    use mongodb::options::{Acknowledgment, InsertOneOptions, WriteConcern};
+    use temp_mongo::TempMongoReplicaSet;
+
+    let_assert!(Ok(replica_set) = TempMongoReplicaSet::new(3).await);
+    assert!(replica_set.member_count() == 3);
+
+    let collection = replica_set.client().database("test").collection::<Document>("foo");
+    let options = InsertOneOptions::builder()
+        .write_concern(WriteConcern::builder().w(Acknowledgment::Majority).build())
+        .build();
+    assert!(let Ok(_) = collection.insert_one(doc! { "hello": "world" }, options).await);
+
+    let_assert!(Ok(Some(_)) = collection.find_one(doc! { "hello": "world" }, None).await);
+
+    assert!(let Ok(()) = replica_set.kill_and_clean().await);
+}
+
+/// Spawns a replica set with keyfile authentication and tests if
+/// the authenticated client can write and the connection string contains the credentials
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn replica_set_with_auth() {
+    use temp_mongo::TempMongoReplicaSet;
+
+    let_assert!(Ok(replica_set) = TempMongoReplicaSet::builder()
+        .members(2)
+        .auth("root", "secret")
+        .spawn()
+        .await);
+
+    let collection = replica_set.client().database("test").collection::<Document>("foo");
+    assert!(let Ok(_) = collection.insert_one(doc! { "hello": "world" }, None).await);
+
+    let_assert!(Ok(uri) = mongodb::options::ClientOptions::parse(replica_set.uri()).await);
+    let_assert!(Some(credential) = uri.credential);
+    assert!(credential.username.as_deref() == Some("root"));
+
+    assert!(let Ok(()) = replica_set.kill_and_clean().await);
+}
+
+/// Spawns a sharded cluster, shards a collection and
+/// tests if documents can be written and read through the router
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn sharded_cluster_insert_and_find() {
+    use temp_mongo::TempMongoSharded;
+
+    let_assert!(Ok(cluster) = TempMongoSharded::builder()
+        .shards(2)
+        .enable_sharding("test")
+        .spawn()
+        .await);
+    assert!(cluster.shards().len() == 2);
+    assert!(let Ok(()) = cluster.shard_collection("test.users", doc! { "user_id": "hashed" }).await);
+
+    let collection = cluster.client().database("test").collection::<Document>("users");
+    let documents: Vec<_> = (0..100).map(|user_id| doc! { "user_id": user_id }).collect();
+    assert!(let Ok(_) = collection.insert_many(documents, None).await);
+    let_assert!(Ok(100) = collection.count_documents(None, None).await);
+
+    assert!(let Ok(()) = cluster.kill_and_clean().await);
+}
+
+/// Assigns a shard and a range of shard keys to a zone
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn sharded_cluster_zones() {
+    use temp_mongo::TempMongoSharded;
+
+    let_assert!(Ok(cluster) = TempMongoSharded::builder()
+        .shards(2)
+        .enable_sharding("test")
+        .spawn()
+        .await);
+    assert!(let Ok(()) = cluster.shard_collection("test.users", doc! { "region": 1 }).await);
+    assert!(let Ok(()) = cluster.add_shard_to_zone(0, "EU").await);
+    assert!(let Ok(()) = cluster.update_zone_key_range("test.users", doc! { "region": "EU" }, doc! { "region": "EV" }, "EU").await);
+
+    let config = cluster.client().database("config");
+    let shard_name = cluster.shards()[0].name();
+    let_assert!(Ok(Some(shard)) = config.collection::<Document>("shards").find_one(doc! { "_id": shard_name }, None).await);
+    let_assert!(Ok(tags) = shard.get_array("tags"));
+    assert!(tags.iter().any(|tag| tag.as_str() == Some("EU")));
+
+    let_assert!(Ok(Some(range)) = config.collection::<Document>("tags").find_one(doc! { "ns": "test.users" }, None).await);
+    assert!(let Ok("EU") = range.get_str("tag"));
+    let_assert!(Ok(min) = range.get_document("min"));
+    assert!(min == &doc! { "region": "EU" });
+
+    assert!(let Ok(()) = cluster.kill_and_clean().await);
+}
+
+/// Spawns a sharded cluster with keyfile authentication and tests if
+/// the authenticated client can write and unauthenticated clients are rejected
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn sharded_cluster_with_auth() {
+    use temp_mongo::TempMongoSharded;
+
+    let_assert!(Ok(cluster) = TempMongoSharded::builder()
+        .shards(1)
+        .auth("root", "secret")
+        .spawn()
+        .await);
+
+    let collection = cluster.client().database("test").collection::<Document>("foo");
+    assert!(let Ok(_) = collection.insert_one(doc! { "hello": "world" }, None).await);
+
+    let_assert!(Ok(options) = mongodb::options::ClientOptions::parse(cluster.uri()).await);
+    let_assert!(Some(credential) = &options.credential);
+    assert!(credential.username.as_deref() == Some("root"));
+
+    let_assert!(Ok(anonymous) = mongodb::Client::with_uri_str(format!("mongodb://127.0.0.1:{}/", cluster.router_port(0))).await);
+    assert!(let Err(_) = anonymous.database("test").collection::<Document>("foo").find_one(None, None).await);
+
+    assert!(let Ok(()) = cluster.kill_and_clean().await);
+}
+
+/// Steps down the primary of a replica set and tests if
+/// a new primary is elected and accepts writes
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn replica_set_step_down() {
+    use std::time::Duration;
+    use temp_mongo::TempMongoReplicaSet;
+
+    let_assert!(Ok(replica_set) = TempMongoReplicaSet::new(3).await);
+
+    let_assert!(Ok(previous) = replica_set.step_down_primary().await);
+    let_assert!(Ok(primary) = replica_set.await_new_primary(previous, Duration::from_secs(30)).await);
+    assert!(primary != previous);
+
+    let collection = replica_set.client().database("test").collection::<Document>("foo");
+    assert!(let Ok(_) = collection.insert_one(doc! { "hello": "world" }, None).await);
+
+    assert!(let Ok(()) = replica_set.kill_and_clean().await);
+}
+
+/// Kills the primary of a replica set and tests if a new primary is elected,
+/// and if the killed member can be restarted
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn replica_set_kill_primary() {
+    use std::time::Duration;
+    use temp_mongo::TempMongoReplicaSet;
+
+    let_assert!(Ok(mut replica_set) = TempMongoReplicaSet::new(3).await);
+
+    let_assert!(Ok(killed) = replica_set.kill_primary().await);
+    let_assert!(Ok(primary) = replica_set.await_new_primary(killed, Duration::from_secs(30)).await);
+    assert!(primary != killed);
+
+    assert!(let Ok(()) = replica_set.restart_member(killed));
+    let admin = replica_set.member_client(killed).database("admin");
+    assert!(let Ok(_) = admin.run_command(doc! { "ping": 1 }, None).await);
+    assert!(let Ok(()) = replica_set.await_ready(Duration::from_secs(30)).await);
+
+    assert!(let Ok(()) = replica_set.kill_and_clean().await);
+}
+
+/// Adds and removes members of a running replica set
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn replica_set_add_and_remove_member() {
+    use temp_mongo::TempMongoReplicaSet;
+
+    let_assert!(Ok(mut replica_set) = TempMongoReplicaSet::new(2).await);
+
+    let_assert!(Ok(index) = replica_set.add_member().await);
+    assert!(index == 2);
+    assert!(replica_set.member_count() == 3);
+
+    assert!(let Ok(()) = replica_set.remove_member(index).await);
+    assert!(replica_set.member_count() == 2);
+
+    let collection = replica_set.client().database("test").collection::<Document>("foo");
+    assert!(let Ok(_) = collection.insert_one(doc! { "hello": "world" }, None).await);
+
+    assert!(let Ok(()) = replica_set.kill_and_clean().await);
+}
+
+/// Refuses to remove the primary of a replica set
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn replica_set_remove_primary() {
+    use temp_mongo::TempMongoReplicaSet;
+
+    let_assert!(Ok(mut replica_set) = TempMongoReplicaSet::new(3).await);
+    let_assert!(Some(primary) = replica_set.primary_index().await);
+
+    let_assert!(Err(e) = replica_set.remove_member(primary).await);
+    assert!(e.to_string().contains("primary"));
+    assert!(replica_set.member_count() == 3);
+
+    assert!(let Ok(()) = replica_set.kill_and_clean().await);
+}
+
+/// Tags a replica set member and tests if a tagged read preference
+/// selects that member
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn replica_set_member_tags() {
+    use mongodb::options::{FindOneOptions, ReadPreference, ReadPreferenceOptions, SelectionCriteria};
+    use temp_mongo::{MemberOptions, TempMongoReplicaSet};
+
+    let_assert!(Ok(replica_set) = TempMongoReplicaSet::builder()
+        .members(3)
+        .member_options(0, MemberOptions::new().tag("dc", "west"))
+        .member_options(1, MemberOptions::new().tag("dc", "east"))
+        .member_options(2, MemberOptions::new().tag("dc", "east"))
+        .spawn()
+        .await);
+
+    let tag_set = [("dc".to_string(), "east".to_string())].into_iter().collect();
+    let read_preference = ReadPreference::Nearest {
+        options: ReadPreferenceOptions::builder().tag_sets(vec![tag_set]).build(),
+    };
+    let options = FindOneOptions::builder()
+        .selection_criteria(SelectionCriteria::ReadPreference(read_preference))
+        .build();
+
+    let collection = replica_set.client().database("test").collection::<Document>("foo");
+    assert!(let Ok(None) = collection.find_one(None, options).await);
+
+    assert!(let Ok(()) = replica_set.kill_and_clean().await);
+}
+
+/// Rejects replica sets without data bearing members and
+/// member options for members that do not exist, before spawning anything
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn replica_set_invalid_members() {
+    use temp_mongo::{MemberOptions, TempMongoReplicaSet};
+
+    let_assert!(Err(e) = TempMongoReplicaSet::builder().members(0).arbiter(true).spawn().await);
+    assert!(e.to_string() == "A replica set needs at least one member");
+    assert!(!e.is_infrastructure());
+
+    let_assert!(Err(e) = TempMongoReplicaSet::builder()
+        .members(2)
+        .member_options(2, MemberOptions::new().tag("dc", "west"))
+        .spawn()
+        .await);
+    assert!(e.to_string() == "Member options were set for member 2, but the replica set only has 2 data bearing members");
+    assert!(!e.is_infrastructure());
+}
+
+/// Configures a hidden and delayed replica set member
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn replica_set_hidden_delayed_member() {
+    use mongodb::bson::Bson;
+    use std::time::Duration;
+    use temp_mongo::{MemberOptions, TempMongoReplicaSet};
+
+    let_assert!(Ok(replica_set) = TempMongoReplicaSet::builder()
+        .members(2)
+        .member_options(1, MemberOptions::new().hidden(true).secondary_delay(Duration::from_secs(5)))
+        .spawn()
+        .await);
+
+    let_assert!(Ok(reply) = replica_set.client().database("admin").run_command(doc! { "replSetGetConfig": 1 }, None).await);
+    let_assert!(Ok(config) = reply.get_document("config"));
+    let_assert!(Ok(members) = config.get_array("members"));
+    let_assert!(Some(Bson::Document(member)) = members.get(1));
+    assert!(let Ok(true) = member.get_bool("hidden"));
+    assert!(member.get("priority").and_then(Bson::as_f64) == Some(0.0));
+    let delay = member.get("secondaryDelaySecs").or_else(|| member.get("slaveDelay"));
+    assert!(matches!(delay, Some(Bson::Int64(5) | Bson::Int32(5))));
+
+    assert!(let Ok(()) = replica_set.kill_and_clean().await);
+}
+
+/// Spawns a replica set from a declarative topology description
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn topology_from_json() {
+    use temp_mongo::Topology;
+
+    let_assert!(Ok(topology) = Topology::from_json(r#"{ "type": "replica_set", "members": [{}, {}] }"#));
+    let_assert!(Ok(deployment) = topology.spawn().await);
+
+    let collection = deployment.client().database("test").collection::<Document>("foo");
+    assert!(let Ok(_) = collection.insert_one(doc! { "hello": "world" }, None).await);
+
+    assert!(let Ok(()) = deployment.kill_and_clean().await);
+}
+
+/// Connects directly to a secondary using its per-member handle
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn replica_set_member_handles() {
+    use temp_mongo::TempMongoReplicaSet;
+
+    let_assert!(Ok(replica_set) = TempMongoReplicaSet::new(3).await);
+    let_assert!(Some(primary) = replica_set.primary_index().await);
+    let secondary = (primary + 1) % replica_set.member_count();
+
+    assert!(replica_set.member_uri(secondary).contains(&replica_set.member_port(secondary).to_string()));
+    assert!(replica_set.member_pid(secondary) != replica_set.member_pid(primary));
+
+    let_assert!(Ok(client) = mongodb::Client::with_uri_str(replica_set.member_uri(secondary)).await);
+    let_assert!(Ok(hello) = client.database("admin").run_command(doc! { "hello": 1 }, None).await);
+    assert!(let Ok(true) = hello.get_bool("secondary"));
+
+    assert!(let Ok(()) = replica_set.kill_and_clean().await);
+}
+
+/// Spawns a fleet of independent servers and tests that they do not share data
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn fleet_of_independent_servers() {
+    use temp_mongo::MongoFleet;
+
+    let_assert!(Ok(fleet) = MongoFleet::new(["source", "target"]).await);
+    assert!(fleet.len() == 2);
+
+    let_assert!(Some(source) = fleet.client("source"));
+    let_assert!(Some(target) = fleet.client("target"));
+    let source = source.database("test").collection::<Document>("foo");
+    let target = target.database("test").collection::<Document>("foo");
+    assert!(let Ok(_) = source.insert_one(doc! { "hello": "world" }, None).await);
+    assert!(let Ok(0) = target.count_documents(None, None).await);
+
+    assert!(let Ok(()) = fleet.kill_and_clean().await);
+}
+
+/// Spawns a replica set with custom replication settings
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn replica_set_replication_settings() {
+    use mongodb::bson::Bson;
+    use std::time::Duration;
+    use temp_mongo::TempMongoReplicaSet;
+
+    let_assert!(Ok(replica_set) = TempMongoReplicaSet::builder()
+        .members(2)
+        .oplog_size_mb(50)
+        .heartbeat_interval(Duration::from_millis(500))
+        .election_timeout(Duration::from_secs(2))
+        .spawn()
+        .await);
+
+    let admin = replica_set.client().database("admin");
+    let_assert!(Ok(reply) = admin.run_command(doc! { "replSetGetConfig": 1 }, None).await);
+    let_assert!(Ok(settings) = reply.get_document("config").and_then(|config| config.get_document("settings")));
+    assert!(let Some(Bson::Int32(2000) | Bson::Int64(2000)) = settings.get("electionTimeoutMillis"));
+    assert!(let Some(Bson::Int32(500) | Bson::Int64(500)) = settings.get("heartbeatIntervalMillis"));
+
+    assert!(let Ok(()) = replica_set.kill_and_clean().await);
+}
+
+/// Tests the download URL of official MongoDB binaries
+#[test]
+fn download_url() {
+    use temp_mongo::MongoDownloader;
+
+    let downloader = MongoDownloader::new("7.0.5").platform("linux-x86_64-ubuntu2204");
+    assert!(let Ok("https://fastdl.mongodb.org/linux/mongodb-linux-x86_64-ubuntu2204-7.0.5.tgz") = downloader.get_url().as_deref());
+
+    let downloader = MongoDownloader::new("7.0.5").platform("macos-arm64");
+    assert!(let Ok("https://fastdl.mongodb.org/osx/mongodb-macos-arm64-7.0.5.tgz") = downloader.get_url().as_deref());
+}
+
+/// Tests the download URL of MongoDB Enterprise and the license check
+#[test]
+fn download_enterprise() {
+    use temp_mongo::{Edition, MongoDownloader};
+
+    let downloader = MongoDownloader::new("7.0.5").platform("linux-x86_64-ubuntu2204").edition(Edition::Enterprise);
+    assert!(let Ok("https://downloads.mongodb.com/linux/mongodb-linux-x86_64-enterprise-ubuntu2204-7.0.5.tgz") = downloader.get_url().as_deref());
+
+    let downloader = downloader.platform("macos-arm64");
+    assert!(let Ok("https://downloads.mongodb.com/osx/mongodb-macos-arm64-enterprise-7.0.5.tgz") = downloader.get_url().as_deref());
+
+    let_assert!(Ok(cache) = tempfile::tempdir());
+    let_assert!(Err(e) = downloader.cache_dir(cache.path()).accept_enterprise_license(false).download());
+    assert!(e.to_string().contains("license"));
+}
+
+/// Emits tracing events when using cached binaries
+#[cfg(feature = "tracing")]
+#[test]
+fn tracing_download_events() {
+    use std::sync::{Arc, Mutex};
+    use temp_mongo::MongoDownloader;
+
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+    impl std::io::Write for Output {
+        fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(data);
+            Ok(data.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let_assert!(Ok(cache) = tempfile::tempdir());
+    let bin_dir = cache.path().join("7.0.5").join("linux-x86_64-ubuntu2204").join("bin");
+    assert!(let Ok(()) = std::fs::create_dir_all(&bin_dir));
+    assert!(let Ok(()) = std::fs::write(bin_dir.join(format!("mongod{}", std::env::consts::EXE_SUFFIX)), ""));
+
+    let output = Output::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .with_writer({
+            let output = output.clone();
+            move || output.clone()
+        })
+        .finish();
+    let downloader = MongoDownloader::new("7.0.5").platform("linux-x86_64-ubuntu2204").cache_dir(cache.path());
+    assert!(let Ok(_) = tracing::subscriber::with_default(subscriber, || downloader.download()));
+
+    let_assert!(Ok(output) = String::from_utf8(output.0.lock().unwrap().clone()));
+    assert!(output.contains("temp_mongo::download"));
+    assert!(output.contains("using cached binaries"));
+}
+
+/// Emits log records when using cached binaries
+#[cfg(feature = "log")]
+#[test]
+fn log_download_records() {
+    use std::sync::Mutex;
+    use temp_mongo::MongoDownloader;
+
+    static RECORDS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    struct Logger;
+    impl log::Log for Logger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+        fn log(&self, record: &log::Record) {
+            RECORDS.lock().unwrap().push(format!("{}: {}", record.target(), record.args()));
+        }
+        fn flush(&self) {}
+    }
+
+    let_assert!(Ok(cache) = tempfile::tempdir());
+    let bin_dir = cache.path().join("7.0.5").join("linux-x86_64-ubuntu2204").join("bin");
+    assert!(let Ok(()) = std::fs::create_dir_all(&bin_dir));
+    assert!(let Ok(()) = std::fs::write(bin_dir.join(format!("mongod{}", std::env::consts::EXE_SUFFIX)), ""));
+
+    assert!(let Ok(()) = log::set_logger(&Logger));
+    log::set_max_level(log::LevelFilter::Debug);
+    let downloader = MongoDownloader::new("7.0.5").platform("linux-x86_64-ubuntu2204").cache_dir(cache.path());
+    assert!(let Ok(_) = downloader.download());
+
+    let records = RECORDS.lock().unwrap();
+    assert!(records.iter().any(|record| record.starts_with("temp_mongo::download: using cached binaries")));
+}
+
+/// Lists and prunes cached binary versions
+#[test]
+fn binary_cache_prune() {
+    use temp_mongo::BinaryCache;
+
+    let_assert!(Ok(root) = tempfile::tempdir());
+    let cache = BinaryCache::in_dir(root.path());
+    assert!(let Ok([]) = cache.list_cached_versions().as_deref());
+
+    for version in ["6.0.12", "7.0.5", "7.0.14", ".download-abc"] {
+        assert!(let Ok(()) = std::fs::create_dir_all(root.path().join(version)));
+    }
+    let_assert!(Ok(versions) = cache.list_cached_versions());
+    assert!(versions == ["6.0.12", "7.0.5", "7.0.14"]);
+
+    let_assert!(Ok(removed) = cache.prune_cache(1));
+    assert!(removed == ["6.0.12", "7.0.5"]);
+    let_assert!(Ok(versions) = cache.list_cached_versions());
+    assert!(versions == ["7.0.14"]);
+}
+
+/// Leaves directories that are not named like a version in the binary cache alone
+#[test]
+fn binary_cache_prune_keeps_other_directories() {
+    use temp_mongo::BinaryCache;
+
+    let_assert!(Ok(root) = tempfile::tempdir());
+    let cache = BinaryCache::in_dir(root.path());
+    for directory in ["6.0.12", "7.0.5", "data/warm"] {
+        assert!(let Ok(()) = std::fs::create_dir_all(root.path().join(directory)));
+    }
+
+    let_assert!(Ok(removed) = cache.prune_cache(0));
+    assert!(removed == ["6.0.12", "7.0.5"]);
+    assert!(root.path().join("data/warm").is_dir());
+}
+
+/// Renders a mongod configuration and passes it to the server
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn mongod_config_file() {
+    use mongodb::bson::Bson;
+    use temp_mongo::MongodConfig;
+
+    let config = MongodConfig::new()
+        .set("setParameter.cursorTimeoutMillis", 60000)
+        .set("operationProfiling.mode", "off");
+    assert!(config.to_yaml() == "operationProfiling:\n  mode: \"off\"\nsetParameter:\n  cursorTimeoutMillis: 60000\n");
+
+    let_assert!(Ok(mongo) = TempMongo::builder().config(config).spawn().await);
+    let admin = mongo.client().database("admin");
+    let_assert!(Ok(reply) = admin.run_command(doc! { "getParameter": 1, "cursorTimeoutMillis": 1 }, None).await);
+    assert!(let Some(Bson::Int32(60000) | Bson::Int64(60000)) = reply.get("cursorTimeoutMillis"));
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Uses a caller-provided data directory and keeps the temporary directory after cleanup
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn custom_data_dir_and_keep_temp_dir() {
+    let_assert!(Ok(data_dir) = tempfile::tempdir());
+    let db_path = data_dir.path().join("db");
+
+    let_assert!(Ok(mongo) = TempMongo::builder().data_dir(&db_path).keep_temp_dir(true).spawn().await);
+    assert!(mongo.db_path() == db_path);
+    let collection = mongo.client().database("test").collection::<Document>("foo");
+    assert!(let Ok(_) = collection.insert_one(doc! { "hello": "world" }, None).await);
+
+    let directory = mongo.directory().to_owned();
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+    assert!(directory.exists());
+    assert!(db_path.join("WiredTiger").exists());
+    assert!(let Ok(()) = std::fs::remove_dir_all(&directory));
+}
+
+/// Spawns a server with the fast startup profile
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn fast_startup_profile() {
+    let_assert!(Ok(mongo) = TempMongo::builder().fast_startup(true).spawn().await);
+    let collection = mongo.client().database("test").collection::<Document>("foo");
+    assert!(let Ok(_) = collection.insert_one(doc! { "hello": "world" }, None).await);
+    assert!(let Ok(1) = collection.count_documents(None, None).await);
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Keeps a server parameter of the fast startup profile that was set explicitly
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn fast_startup_profile_keeps_parameters() {
+    let_assert!(Ok(mongo) = TempMongo::builder()
+        .fast_startup(true)
+        .set_parameter("diagnosticDataCollectionEnabled", true)
+        .spawn()
+        .await);
+    let admin = mongo.client().database("admin");
+    let_assert!(Ok(parameter) = admin.run_command(doc! { "getParameter": 1, "diagnosticDataCollectionEnabled": 1 }, None).await);
+    assert!(let Ok(true) = parameter.get_bool("diagnosticDataCollectionEnabled"));
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Limits the WiredTiger cache size of a server
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn wired_tiger_cache_size() {
+    let_assert!(Ok(mongo) = TempMongo::builder().wired_tiger_cache_size_gb(0.25).spawn().await);
+    let admin = mongo.client().database("admin");
+    let_assert!(Ok(status) = admin.run_command(doc! { "serverStatus": 1 }, None).await);
+    let_assert!(Ok(cache) = status.get_document("wiredTiger").and_then(|wired_tiger| wired_tiger.get_document("cache")));
+    let_assert!(Some(maximum) = cache.get("maximum bytes configured").and_then(|value| value.as_f64().or(value.as_i64().map(|value| value as f64))));
+    assert!(maximum == 0.25 * 1024.0 * 1024.0 * 1024.0);
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Selects the storage engine explicitly
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn storage_engine_selection() {
+    let_assert!(Ok(mongo) = TempMongo::builder().storage_engine("wiredTiger").spawn().await);
+    let admin = mongo.client().database("admin");
+    let_assert!(Ok(status) = admin.run_command(doc! { "serverStatus": 1 }, None).await);
+    let_assert!(Ok(engine) = status.get_document("storageEngine").and_then(|engine| engine.get_str("name")));
+    assert!(engine == "wiredTiger");
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Commits and aborts multi-document transactions on a replica set
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn replica_set_transactions() {
+    use temp_mongo::TempMongoReplicaSet;
+
+    let_assert!(Ok(replica_set) = TempMongoReplicaSet::new(1).await);
+    let collection = replica_set.client().database("test").collection::<Document>("foo");
+    assert!(let Ok(_) = collection.insert_one(doc! { "setup": true }, None).await);
+
+    let_assert!(Ok(mut session) = replica_set.client().start_session(None).await);
+    assert!(let Ok(()) = session.start_transaction(None).await);
+    assert!(let Ok(_) = collection.insert_one_with_session(doc! { "committed": true }, None, &mut session).await);
+    assert!(let Ok(()) = session.commit_transaction().await);
+
+    assert!(let Ok(()) = session.start_transaction(None).await);
+    assert!(let Ok(_) = collection.insert_one_with_session(doc! { "aborted": true }, None, &mut session).await);
+    assert!(let Ok(()) = session.abort_transaction().await);
+
+    assert!(let Ok(1) = collection.count_documents(doc! { "committed": true }, None).await);
+    assert!(let Ok(0) = collection.count_documents(doc! { "aborted": true }, None).await);
+
+    assert!(let Ok(()) = replica_set.kill_and_clean().await);
+}
+
+/// Spawns a quiet server and a verbose server
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn log_verbosity() {
+    let_assert!(Ok(quiet) = TempMongo::builder().quiet(true).spawn().await);
+    let_assert!(Ok(verbose) = TempMongo::builder().verbosity(2).spawn().await);
+
+    let admin = verbose.client().database("admin");
+    let_assert!(Ok(reply) = admin.run_command(doc! { "getParameter": 1, "logComponentVerbosity": 1 }, None).await);
+    let_assert!(Ok(verbosity) = reply.get_document("logComponentVerbosity"));
+    assert!(let Ok(2) = verbosity.get_i32("verbosity"));
+
+    assert!(let Ok(()) = quiet.kill_and_clean().await);
+    assert!(let Ok(()) = verbose.kill_and_clean().await);
+}
+
+/// Spawns a mongocryptd process and runs a command against it
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn spawn_mongocryptd() {
+    use temp_mongo::TempMongoCryptd;
+
+    let_assert!(Ok(cryptd) = TempMongoCryptd::new().await);
+    assert!(cryptd.uri() == format!("mongodb://127.0.0.1:{}", cryptd.port()));
+
+    let admin = cryptd.client().database("admin");
+    let_assert!(Ok(reply) = admin.run_command(doc! { "hello": 1 }, None).await);
+    assert!(let Ok(true) = reply.get_bool("iscryptd"));
+
+    let directory = cryptd.directory().to_owned();
+    assert!(let Ok(()) = cryptd.kill_and_clean().await);
+    assert!(!directory.exists());
+}
+
+/// Downloads a MongoDB version and runs a test against it
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn for_each_version() {
+    let versions = std::cell::RefCell::new(Vec::new());
+    let result = TempMongo::for_each_version(&["8.0.4"], |mongo| {
+        let versions = &versions;
+        async move {
+            let admin = mongo.client().database("admin");
+            let_assert!(Ok(info) = admin.run_command(doc! { "buildInfo": 1 }, None).await);
+            let_assert!(Ok(version) = info.get_str("version"));
+            versions.borrow_mut().push(version.to_string());
+        }
+    })
+    .await;
+    assert!(let Ok(()) = result);
+    assert!(versions.into_inner() == ["8.0.4"]);
+}
+
+/// Reuses a named persistent data directory across runs
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn persistent_data_dir() {
+    let name = format!("test-{}", std::process::id());
+
+    let_assert!(Ok(mongo) = TempMongo::builder().persistent(&name).spawn().await);
+    assert!(!mongo.reused_data());
+    let db_path = mongo.db_path().to_owned();
+    let collection = mongo.client().database("test").collection::<Document>("foo");
+    assert!(let Ok(_) = collection.insert_one(doc! { "hello": "world" }, None).await);
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+
+    let_assert!(Ok(mongo) = TempMongo::builder().persistent(&name).spawn().await);
+    assert!(mongo.reused_data());
+    assert!(mongo.db_path() == db_path);
+    let collection = mongo.client().database("test").collection::<Document>("foo");
+    assert!(let Ok(1) = collection.count_documents(doc! { "hello": "world" }, None).await);
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+
+    assert!(let Ok(()) = std::fs::remove_dir_all(&db_path));
+}
+
+/// Shuts down a server gracefully with the shutdown command
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn graceful_shutdown() {
+    let_assert!(Ok(mongo) = TempMongo::builder().keep_temp_dir(true).spawn().await);
+    let directory = mongo.directory().to_owned();
+    let log_path = mongo.log_path().to_owned();
+    assert!(let Ok(()) = mongo.shutdown_and_clean(std::time::Duration::from_secs(30)).await);
+
+    let_assert!(Ok(log) = std::fs::read_to_string(&log_path));
+    assert!(log.contains("shutdown command"));
+    assert!(let Ok(()) = std::fs::remove_dir_all(&directory));
+}
+
+/// Passes server parameters to mongod with --setParameter
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn set_server_parameters() {
+    use mongodb::bson::Bson;
+
+    let_assert!(Ok(mongo) = TempMongo::builder()
+        .set_parameter("enableTestCommands", 1)
+        .set_parameter("ttlMonitorSleepSecs", 1)
+        .spawn()
+        .await);
+
+    let admin = mongo.client().database("admin");
+    let_assert!(Ok(reply) = admin.run_command(doc! { "getParameter": 1, "ttlMonitorSleepSecs": 1 }, None).await);
+    assert!(let Some(Bson::Int32(1) | Bson::Int64(1)) = reply.get("ttlMonitorSleepSecs"));
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Downgrades and upgrades the feature compatibility version of a server
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn feature_compatibility_version() {
+    let_assert!(Ok(mongo) = TempMongo::new().await);
+    let_assert!(Ok(current) = mongo.fcv().await);
+    let_assert!(Some((major, _)) = current.split_once('.'));
+    let_assert!(Ok(major) = major.parse::<u32>());
+
+    // Every release supports downgrading to the previous major release.
+    let previous = format!("{}.0", major - 1);
+    assert!(let Ok(()) = mongo.set_fcv(&previous).await);
+    let_assert!(Ok(version) = mongo.fcv().await);
+    assert!(version == previous);
+
+    assert!(let Ok(()) = mongo.set_fcv(&current).await);
+    let_assert!(Ok(version) = mongo.fcv().await);
+    assert!(version == current);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Limits the number of open files of the spawned server
+#[cfg(target_os = "linux")]
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn resource_limits() {
+    use mongodb::bson::Bson;
+    use temp_mongo::ResourceLimits;
+
+    let limits = ResourceLimits::new().open_files(10_000);
+    let_assert!(Ok(mongo) = TempMongo::builder().resource_limits(limits).spawn().await);
+
+    let admin = mongo.client().database("admin");
+    let_assert!(Ok(info) = admin.run_command(doc! { "hostInfo": 1 }, None).await);
+    let_assert!(Ok(extra) = info.get_document("extra"));
+    assert!(let Some(Bson::Int32(10_000) | Bson::Int64(10_000)) = extra.get("maxOpenFiles"));
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Downloads through an explicitly configured proxy
+#[test]
+fn download_through_proxy() {
+    use temp_mongo::MongoDownloader;
+
+    let_assert!(Ok(cache) = tempfile::tempdir());
+    let downloader = MongoDownloader::new("7.0.5")
+        .platform("linux-x86_64-ubuntu2204")
+        .cache_dir(cache.path())
+        .proxy("http://127.0.0.1:1");
+    let_assert!(Err(e) = downloader.download());
+    assert!(e.to_string().starts_with("Failed to download https://fastdl.mongodb.org/"));
+    assert!(e.is_infrastructure());
+}
+
+/// Does not retry downloading a version that does not exist
+#[test]
+fn download_with_retries() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use temp_mongo::{MongoDownloader, RetryPolicy};
+
+    // A proxy that rejects every request, like a server that does not have the requested version.
+    let_assert!(Ok(proxy) = std::net::TcpListener::bind("127.0.0.1:0"));
+    let_assert!(Ok(address) = proxy.local_addr());
+    let requests = Arc::new(AtomicUsize::new(0));
+    std::thread::spawn({
+        let requests = requests.clone();
+        move || {
+            for mut stream in proxy.incoming().flatten() {
+                requests.fetch_add(1, Ordering::SeqCst);
+                BufReader::new(&stream).read_line(&mut String::new()).ok();
+                stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n").ok();
+            }
+        }
+    });
+
+    let_assert!(Ok(cache) = tempfile::tempdir());
+    let downloader = MongoDownloader::new("0.0.0")
+        .platform("linux-x86_64-ubuntu2204")
+        .cache_dir(cache.path())
+        .proxy(format!("http://{address}"));
+    let start = Instant::now();
+    let_assert!(Err(e) = downloader.download_with_retries(RetryPolicy::new(3).delay(Duration::from_secs(10))));
+    assert!(!e.is_infrastructure());
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert!(requests.load(Ordering::SeqCst) == 1);
+}
+
+/// Reports the progress of a download to a callback
+#[test]
+fn download_progress() {
+    use std::sync::{Arc, Mutex};
+    use temp_mongo::MongoDownloader;
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let_assert!(Ok(cache) = tempfile::tempdir());
+    let downloader = MongoDownloader::new("7.0.5")
+        .platform("linux-x86_64-ubuntu2204")
+        .cache_dir(cache.path())
+        .proxy("http://127.0.0.1:1")
+        .progress({
+            let reports = reports.clone();
+            move |progress| reports.lock().unwrap().push(progress.clone())
+        });
+    let_assert!(Err(_) = downloader.download());
+
+    let reports = reports.lock().unwrap();
+    let_assert!([progress] = reports.as_slice());
+    assert!(progress.url.ends_with("mongodb-linux-x86_64-ubuntu2204-7.0.5.tgz"));
+    assert!(progress.downloaded == 0);
+    assert!(progress.total == None);
+}
+
+/// Requires TLS with generated certificates
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn tls_with_generated_certificates() {
+    use mongodb::options::ClientOptions;
+
+    let_assert!(Ok(mongo) = TempMongo::builder().tls(true).spawn().await);
+    let_assert!(Some(port) = mongo.port());
+    let_assert!(Some(ca_file) = mongo.tls_ca_file());
+    assert!(ca_file.is_file());
+    assert!(let Ok(_) = mongo.client().list_database_names(None, None).await);
+
+    // A client without TLS can not connect.
+    let_assert!(Ok(options) = ClientOptions::parse(format!("mongodb://127.0.0.1:{port}/?directConnection=true&serverSelectionTimeoutMS=1000")).await);
+    let_assert!(Ok(plain) = mongodb::Client::with_options(options));
+    assert!(let Err(_) = plain.list_database_names(None, None).await);
+
+    // A client that trusts the generated certificate authority can.
+    let uri = format!("mongodb://localhost:{port}/?directConnection=true&tls=true&tlsCAFile={}", ca_file.display());
+    let_assert!(Ok(options) = ClientOptions::parse(uri).await);
+    let_assert!(Ok(client) = mongodb::Client::with_options(options));
+    assert!(let Ok(_) = client.list_database_names(None, None).await);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Authenticate with a generated x.509 client certificate.
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn x509_client_certificate_auth() {
+    use mongodb::options::ClientOptions;
+
+    let_assert!(Ok(mongo) = TempMongo::builder().x509_auth(true).spawn().await);
+    let_assert!(Some(port) = mongo.port());
+    let_assert!(Some(ca_file) = mongo.tls_ca_file());
+    let_assert!(Some(client_pem) = mongo.tls_client_pem());
+    assert!(client_pem.is_file());
+    assert!(mongo.x509_username() == Some("CN=client,OU=clients,O=temp-mongo"));
+    assert!(let Ok(_) = mongo.client().list_database_names(None, None).await);
+
+    // A client without a certificate is not authorized.
+    let uri = format!("mongodb://localhost:{port}/?directConnection=true&tls=true&tlsCAFile={}", ca_file.display());
+    let_assert!(Ok(options) = ClientOptions::parse(uri).await);
+    let_assert!(Ok(anonymous) = mongodb::Client::with_options(options));
+    assert!(let Err(_) = anonymous.list_database_names(None, None).await);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Enable authentication with a generated root user.
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn random_credentials() {
+    let_assert!(Ok(mongo) = TempMongo::builder().with_random_credentials().tls(true).spawn().await);
+    let_assert!(Some(credentials) = mongo.credentials());
+    assert!(credentials.username.starts_with("user-"));
+    assert!(credentials.password.expose().len() == 32);
+    assert!(let Ok(_) = mongo.client().list_database_names(None, None).await);
+
+    // Use TLS to get a TCP port that other clients can connect to.
+    let_assert!(Some(port) = mongo.port());
+    let_assert!(Some(ca_file) = mongo.tls_ca_file());
+    let options = format!("localhost:{port}/?directConnection=true&tls=true&tlsCAFile={}&authSource=admin", ca_file.display());
+
+    // A client without credentials is not authorized.
+    let_assert!(Ok(anonymous) = mongodb::Client::with_uri_str(format!("mongodb://{options}")).await);
+    assert!(let Err(_) = anonymous.list_database_names(None, None).await);
+
+    // A client with the credentials is.
+    let_assert!(Ok(client) = mongodb::Client::with_uri_str(format!("mongodb://{}{options}", credentials.to_user_info())).await);
+    assert!(let Ok(_) = client.list_database_names(None, None).await);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Provision mongocryptd for automatic encryption next to the server.
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn csfle_with_mongocryptd() {
+    use temp_mongo::QueryAnalysis;
+
+    let_assert!(Ok(mongo) = TempMongo::builder().with_csfle(QueryAnalysis::Mongocryptd).spawn().await);
+    let_assert!(Some(cryptd) = mongo.cryptd());
+    let_assert!(Some(options) = mongo.auto_encryption_extra_options());
+    let_assert!(Ok(uri) = options.get_str("mongocryptdURI"));
+    assert!(uri == cryptd.uri());
+    assert!(let Ok(true) = options.get_bool("mongocryptdBypassSpawn"));
+
+    let cryptd_directory = cryptd.directory().to_owned();
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+    assert!(!cryptd_directory.exists());
+}
+
+/// Create a local KMS provider with data keys in the key vault.
+#[cfg(feature = "csfle")]
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn local_kms_data_keys() {
+    let_assert!(Ok(mongo) = TempMongo::new().await);
+    let_assert!(Ok(kms) = mongo.local_kms(2).await);
+    assert!(kms.master_key().len() == 96);
+    assert!(kms.data_keys().len() == 2);
+
+    let namespace = kms.key_vault_namespace();
+    let key_vault = mongo.client().database(&namespace.db).collection::<Document>(&namespace.coll);
+    assert!(let Ok(2) = key_vault.count_documents(None, None).await);
+
+    let fields = kms.encrypted_fields(&[("ssn", "string")]);
+    let_assert!(Ok(fields) = fields.get_array("fields"));
+    assert!(fields.len() == 1);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Serve AWS KMS requests from the mock KMS server over TLS.
+#[cfg(feature = "mock-kms")]
+#[test]
+fn mock_kms_endpoint() {
+    use temp_mongo::MockKms;
+
+    let_assert!(Ok(kms) = MockKms::new());
+    let_assert!(Ok(output) = std::process::Command::new("curl")
+        .args(["--silent", "--fail", "--cacert"])
+        .arg(kms.ca_file())
+        .args(["--header", "X-Amz-Target: TrentService.Encrypt"])
+        .args(["--data", r#"{"KeyId": "arn:mock", "Plaintext": "c2VjcmV0"}"#])
+        .arg(format!("https://{}/", kms.endpoint()))
+        .output());
+    assert!(output.status.success());
+    let_assert!(Ok(response) = serde_json::from_slice::<serde_json::Value>(&output.stdout));
+    assert!(response["CiphertextBlob"] == "c2VjcmV0");
+    assert!(kms.operations() == ["aws:Encrypt"]);
+}
+
+/// Enable authentication by default with the auth-by-default feature.
+#[cfg(feature = "auth-by-default")]
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn auth_by_default() {
+    let_assert!(Ok(mongo) = TempMongo::new().await);
+    assert!(mongo.credentials().is_some());
+    assert!(let Ok(_) = mongo.client().list_database_names(None, None).await);
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+
+    let_assert!(Ok(mongo) = TempMongo::builder().without_auth().spawn().await);
+    assert!(mongo.credentials().is_none());
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Generate a keyfile that mongod accepts.
+#[test]
+fn generate_keyfile() {
+    let_assert!(Ok(dir) = tempfile::tempdir());
+    let path = dir.path().join("keyfile");
+    assert!(let Ok(()) = temp_mongo::generate_keyfile(&path));
+    let_assert!(Ok(key) = std::fs::read_to_string(&path));
+    assert!(key.len() == 756);
+    assert!(key.chars().all(|c| c.is_ascii_alphanumeric()));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let_assert!(Ok(metadata) = std::fs::metadata(&path));
+        assert!(metadata.permissions().mode() & 0o777 == 0o600);
+    }
+}
+
+/// Rotate the password of the root user.
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn rotate_password() {
+    let_assert!(Ok(mut mongo) = TempMongo::builder().with_random_credentials().tls(true).spawn().await);
+    let_assert!(Some(old) = mongo.credentials().cloned());
+    let_assert!(Ok(password) = mongo.rotate_password(&old.username).await);
+    let_assert!(Some(new) = mongo.credentials());
+    assert!(new.password.expose() == password);
+    assert!(new.password != old.password);
+    assert!(let Ok(_) = mongo.client().list_database_names(None, None).await);
+
+    // Use TLS to get a TCP port that other clients can connect to.
+    let_assert!(Some(port) = mongo.port());
+    let_assert!(Some(ca_file) = mongo.tls_ca_file());
+    let options = format!("localhost:{port}/?directConnection=true&tls=true&tlsCAFile={}&authSource=admin", ca_file.display());
+    let_assert!(Ok(stale) = mongodb::Client::with_uri_str(format!("mongodb://{}{options}", old.to_user_info())).await);
+    assert!(let Err(_) = stale.list_database_names(None, None).await);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Read audit events from the audit log.
+#[ignore = "requires MongoDB Enterprise"]
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn audit_log() {
+    let_assert!(Ok(mongo) = TempMongo::builder().audit_log(true).spawn().await);
+    assert!(let Some(_) = mongo.audit_log_path());
+    let_assert!(Ok(()) = mongo.client().database("test").create_collection("audited", None).await);
+    std::thread::sleep(std::time::Duration::from_millis(500));
+
+    let_assert!(Ok(events) = mongo.audit_events());
+    assert!(events.iter().any(|event| event["atype"] == "createCollection"));
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Listen on TCP on explicitly configured interfaces.
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn bind_ip() {
+    use temp_mongo::BindIp;
+
+    let_assert!(Ok(mongo) = TempMongo::builder().bind_ip(BindIp::Addresses(vec!["127.0.0.1".into()])).spawn().await);
+    let_assert!(Some(port) = mongo.port());
+    assert!(let Ok(_) = mongo.client().list_database_names(None, None).await);
+
+    let_assert!(Ok(client) = mongodb::Client::with_uri_str(format!("mongodb://127.0.0.1:{port}/?directConnection=true")).await);
+    assert!(let Ok(_) = client.list_database_names(None, None).await);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Disable server-side JavaScript.
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn no_scripting() {
+    let_assert!(Ok(mongo) = TempMongo::builder().no_scripting(true).spawn().await);
+    let collection = mongo.client().database("test").collection::<Document>("scripts");
+    assert!(let Ok(_) = collection.insert_one(doc! { "x": 1 }, None).await);
+    assert!(let Err(_) = collection.find_one(doc! { "$where": "this.x == 1" }, None).await);
+    assert!(let Ok(Some(_)) = collection.find_one(doc! { "x": 1 }, None).await);
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Spawn a server with the production-like security profile.
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn production_like() {
+    let_assert!(Ok(mongo) = TempMongo::builder().production_like().spawn().await);
+    assert!(let Some(_) = mongo.credentials());
+    assert!(let Some(_) = mongo.tls_ca_file());
+    assert!(let Some(_) = mongo.port());
+
+    let collection = mongo.client().database("test").collection::<Document>("scripts");
+    assert!(let Ok(_) = collection.insert_one(doc! { "x": 1 }, None).await);
+    assert!(let Err(_) = collection.find_one(doc! { "$where": "this.x == 1" }, None).await);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Redact passwords in Debug output.
+#[test]
+fn redact_passwords() {
+    use temp_mongo::{Credentials, SecretString};
+
+    let credentials = Credentials::new("admin", "hunter2");
+    assert!(!format!("{credentials:?}").contains("hunter2"));
+    assert!(credentials.password.expose() == "hunter2");
+    assert!(SecretString::new("hunter2").to_string() == "***");
+}
+
+/// Streams and forwards the log of the server
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn stream_logs() {
+    use futures_util::stream::StreamExt;
+
+    let_assert!(Ok(mongo) = TempMongo::new().await);
+    let logs = mongo.stream_logs();
+    futures_util::pin_mut!(logs);
+    let_assert!(Some(Ok(line)) = logs.next().await);
+    assert!(line.starts_with('{'));
+
+    let forwarder = mongo.forward_logs("[mongod] ");
+    drop(forwarder);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Saves the log of the server to a directory on clean-up
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn save_logs_to() {
+    let_assert!(Ok(logs) = tempfile::tempdir());
+    let_assert!(Ok(mongo) = TempMongo::builder().save_logs_to(logs.path()).spawn().await);
+    let_assert!(Some(saved_log_path) = mongo.saved_log_path().map(|path| path.to_owned()));
+    assert!(saved_log_path.starts_with(logs.path()));
+    assert!(!saved_log_path.exists());
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+    let_assert!(Ok(log) = std::fs::read_to_string(&saved_log_path));
+    assert!(log.contains("Waiting for connections") || log.contains("waiting for connections"));
+}
+
+/// Takes a serverStatus snapshot and writes it to a file on clean-up
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn server_status() {
+    let_assert!(Ok(output) = tempfile::tempdir());
+    let_assert!(Ok(mongo) = TempMongo::builder().save_server_status_to(output.path()).spawn().await);
+    let collection = mongo.client().database("test").collection::<Document>("animals");
+    assert!(let Ok(_) = collection.insert_one(doc! { "species": "dog" }, None).await);
+
+    let_assert!(Ok(status) = mongo.server_status().await);
+    assert!(status.connections_current > 0);
+    assert!(status.opcounters.insert >= 1);
+    assert!(status.raw().contains_key("uptime"));
+
+    let_assert!(Some(path) = mongo.server_status_path().map(|path| path.to_owned()));
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+    let_assert!(Ok(json) = std::fs::read_to_string(&path));
+    assert!(json.contains("opcounters"));
+}
+
+/// Reports the slowest operations recorded by the profiler
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn slow_query_report() {
+    use temp_mongo::ProfilingLevel;
+
+    let_assert!(Ok(mongo) = TempMongo::builder().enable_profiling(ProfilingLevel::All, 0).spawn().await);
+    let collection = mongo.client().database("test").collection::<Document>("animals");
+    assert!(let Ok(_) = collection.insert_one(doc! { "species": "dog" }, None).await);
+    assert!(let Ok(Some(_)) = collection.find_one(doc! { "species": "dog" }, None).await);
+
+    let_assert!(Ok(report) = mongo.slow_query_report(10).await);
+    assert!(report.iter().any(|operation| operation.namespace == "test.animals" && operation.operation == "query"));
+    assert!(report.windows(2).all(|pair| pair[0].duration >= pair[1].duration));
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Records the time spent in each startup phase
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn startup_metrics() {
+    let_assert!(Ok(mongo) = TempMongo::new().await);
+    let metrics = mongo.startup_metrics();
+    assert!(metrics.ready > std::time::Duration::ZERO);
+    assert!(metrics.total >= metrics.prepare + metrics.spawn + metrics.ready + metrics.cryptd + metrics.seed);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Calls the lifecycle hooks in order
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn lifecycle_hooks() {
+    use std::sync::{Arc, Mutex};
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let (created, ready, before_cleanup) = (events.clone(), events.clone(), events.clone());
+    let_assert!(Ok(mongo) = TempMongo::builder()
+        .on_created(move |mongo| {
+            let events = created.clone();
+            Box::pin(async move {
+                assert!(let Ok(_) = mongo.client().list_database_names(None, None).await);
+                events.lock().unwrap().push("created");
+            })
+        })
+        .on_ready(move |_| {
+            let events = ready.clone();
+            Box::pin(async move { events.lock().unwrap().push("ready") })
+        })
+        .on_before_cleanup(move |_| {
+            let events = before_cleanup.clone();
+            Box::pin(async move { events.lock().unwrap().push("before_cleanup") })
+        })
+        .spawn()
+        .await);
+    assert!(*events.lock().unwrap() == ["created", "ready"]);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+    assert!(*events.lock().unwrap() == ["created", "ready", "before_cleanup"]);
+}
+
+/// Finds an operation in progress with currentOp
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn current_ops() {
+    let_assert!(Ok(mongo) = TempMongo::new().await);
+    let_assert!(Ok(operations) = mongo.current_ops(doc! { "command.currentOp": { "$exists": true } }).await);
+    let_assert!([operation] = operations.as_slice());
+    assert!(operation.active);
+    assert!(let Some(_) = operation.opid);
+    assert!(operation.command.contains_key("currentOp"));
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Records the commands executed by the client
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn record_commands() {
+    let_assert!(Ok(mongo) = TempMongo::builder().record_commands(true).spawn().await);
+    assert!(mongo.commands_executed().is_empty());
+
+    let collection = mongo.client().database("test").collection::<Document>("animals");
+    assert!(let Ok(_) = collection.insert_one(doc! { "species": "dog" }, None).await);
+    assert!(let Ok(Some(_)) = collection.find_one(doc! { "species": "dog" }, None).await);
+    let commands = mongo.commands_executed();
+    let names: Vec<_> = commands.iter().map(|command| command.name.as_str()).collect();
+    assert!(names == ["insert", "find"]);
+    assert!(commands.iter().all(|command| command.database == "test" && command.failure.is_none()));
+
+    mongo.clear_commands_executed();
+    assert!(mongo.commands_executed().is_empty());
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Gathers a failure report with the log, server status and redacted connection string
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn failure_report() {
+    let_assert!(Ok(mut mongo) = TempMongo::builder().with_random_credentials().spawn().await);
+    let_assert!(Some(password) = mongo.credentials().map(|credentials| credentials.password.expose().to_owned()));
+    let report = mongo.failure_report(5).await;
+    assert!(report.exit_status.is_none());
+    assert!(report.log_tail.len() == 5);
+    assert!(let Some(_) = report.server_status);
+    assert!(!report.uri.contains(&password));
+
+    let_assert!(Ok(output) = tempfile::tempdir());
+    let path = output.path().join("report.json");
+    assert!(let Ok(()) = report.write_to_file(&path));
+    let_assert!(Ok(json) = std::fs::read_to_string(&path));
+    assert!(json.contains("\"log_tail\""));
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Reports the exit status of the server process
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn exit_status() {
+    let_assert!(Ok(mut mongo) = TempMongo::new().await);
+    assert!(let Ok(None) = mongo.exit_status());
+
+    // The server closes the connection while shutting down, so the command is expected to fail.
+    mongo.client().database("admin").run_command(doc! { "shutdown": 1, "force": true }, None).await.ok();
+    let mut status = mongo.exit_status();
+    for _ in 0..100 {
+        if let Ok(Some(_)) = status {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        status = mongo.exit_status();
+    }
+    assert!(let Ok(Some(_)) = status);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Cleans up a crashed server even if a serverStatus snapshot was requested
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn server_status_after_crash() {
+    let_assert!(Ok(output) = tempfile::tempdir());
+    let_assert!(Ok(mut mongo) = TempMongo::builder().save_server_status_to(output.path()).spawn().await);
+    let directory = mongo.directory().to_owned();
+
+    // The server closes the connection while shutting down, so the command is expected to fail.
+    mongo.client().database("admin").run_command(doc! { "shutdown": 1, "force": true }, None).await.ok();
+    for _ in 0..100 {
+        if let Ok(Some(_)) = mongo.exit_status() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+
+    let_assert!(Some(path) = mongo.server_status_path().map(|path| path.to_owned()));
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+    assert!(!directory.exists());
+    assert!(!path.exists());
+}
+
+/// Reports the disk usage of the data directory
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn data_size() {
+    let_assert!(Ok(mongo) = TempMongo::new().await);
+    let_assert!(Ok(size) = mongo.data_size());
+    assert!(size > 0);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Reads the startup warnings of the server
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn startup_warnings() {
+    let_assert!(Ok(mongo) = TempMongo::new().await);
+    assert!(mongo.startup_warnings().iter().all(|warning| warning.starts_with('{')));
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Counts the operations on each collection with the profiler
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn operation_summary() {
+    let_assert!(Ok(mongo) = TempMongo::builder().count_operations().spawn().await);
+    let collection = mongo.client().database("test").collection::<Document>("animals");
+    assert!(let Ok(_) = collection.insert_one(doc! { "species": "dog" }, None).await);
+    assert!(let Ok(Some(_)) = collection.find_one(doc! { "species": "dog" }, None).await);
+
+    let_assert!(Ok(summary) = mongo.operation_summary().await);
+    let_assert!(Some(counts) = summary.get("test.animals"));
+    assert!(counts.inserts == 1);
+    assert!(counts.queries == 1);
+    assert!(counts.collection_scans == 1);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Records the statistics of the connection pool of the client
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn pool_statistics() {
+    let_assert!(Ok(mongo) = TempMongo::new().await);
+    let before = mongo.pool_statistics();
+    let collection = mongo.client().database("test").collection::<Document>("animals");
+    assert!(let Ok(_) = collection.insert_one(doc! { "species": "dog" }, None).await);
+
+    let after = mongo.pool_statistics();
+    assert!(after.connections_created >= 1);
+    assert!(after.checkouts > before.checkouts);
+    assert!(after.checked_out == 0);
+    assert!(after.checkout_timeouts == 0);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Records heartbeats and measures the latency to the server
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn latency() {
+    use std::time::Duration;
+
+    let_assert!(Ok(mongo) = TempMongo::builder().heartbeat_frequency(Duration::from_millis(500)).spawn().await);
+    let_assert!(Ok(latency) = mongo.measure_latency(5).await);
+    assert!(latency.samples == 5);
+    assert!(latency.min <= latency.median && latency.median <= latency.max);
+
+    // The first heartbeat discovers the server, so it is recorded before the client can run any command.
+    let heartbeats = mongo.heartbeats();
+    assert!(!heartbeats.is_empty());
+    assert!(heartbeats.iter().all(|heartbeat| heartbeat.failure.is_none()));
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Runs a closure with a new instance and cleans it up afterwards
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn with() {
+    let mut directory = None;
+    let_assert!(Ok(Ok(databases)) = TempMongo::with(|mongo| {
+        directory = Some(mongo.directory().to_owned());
+        Box::pin(async move { mongo.client().list_database_names(None, None).await })
+    }).await);
+    assert!(databases.contains(&"admin".to_string()));
+
+    let_assert!(Some(directory) = directory);
+    assert!(!directory.exists());
+}
+
+/// Shares a single server between handles, with a unique database per handle
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn shared() {
+    let_assert!(Ok(first) = TempMongo::shared().await);
+    let_assert!(Ok(second) = TempMongo::shared().await);
+    assert!(first.uri() == second.uri());
+    assert!(first.database().name() != second.database().name());
+
+    let collection = first.database().collection::<Document>("animals");
+    assert!(let Ok(_) = collection.insert_one(doc! { "species": "dog" }, None).await);
+    let_assert!(Ok(names) = second.client().list_database_names(None, None).await);
+    assert!(names.contains(&first.database().name().to_string()));
+
+    let name = first.database().name().to_string();
+    assert!(let Ok(()) = first.into_database().drop_database().await);
+    let_assert!(Ok(names) = second.client().list_database_names(None, None).await);
+    assert!(!names.contains(&name));
+}
+
+/// Hands out pre-started instances and resets released ones
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn instance_pool() {
+    use temp_mongo::InstancePool;
+
+    let_assert!(Ok(pool) = InstancePool::new(1));
+    let_assert!(Ok(mongo) = pool.acquire().await);
+    let collection = mongo.client().database("test").collection::<Document>("animals");
+    assert!(let Ok(_) = collection.insert_one(doc! { "species": "dog" }, None).await);
+
+    assert!(let Ok(()) = mongo.reset().await);
+    let_assert!(Ok(names) = mongo.client().list_database_names(None, None).await);
+    assert!(!names.contains(&"test".to_string()));
+
+    let_assert!(Ok(replacement) = pool.acquire().await);
+    assert!(replacement.directory() != mongo.directory());
+    assert!(let Ok(()) = pool.release(mongo).await);
+    assert!(let Ok(()) = replacement.kill_and_clean().await);
+}
+
+/// Writes the connection string for nextest setup scripts
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn serve_for_nextest() {
+    let_assert!(Ok(directory) = tempfile::tempdir());
+    let env_file = directory.path().join("nextest-env");
+    std::env::set_var("NEXTEST_ENV", &env_file);
+    assert!(let Ok(()) = temp_mongo::serve_for_nextest(&TempMongo::builder(), async {}).await);
+    std::env::remove_var("NEXTEST_ENV");
+
+    let_assert!(Ok(variables) = std::fs::read_to_string(&env_file));
+    assert!(variables.lines().any(|line| line.starts_with("MONGODB_URI=mongodb://")));
+    assert!(variables.contains(&format!("TEMP_MONGO_PID={}", std::process::id())));
+}
+
+/// Renders a topology as a docker-compose file
+#[test]
+fn topology_to_compose_yaml() {
+    use temp_mongo::Topology;
+
+    let_assert!(Ok(topology) = Topology::from_json(r#"{ "type": "replica_set", "name": "rs0", "members": [{}, { "hidden": true }] }"#));
+    let yaml = topology.to_compose_yaml("mongo:7.0");
+    assert!(yaml.starts_with("services:\n"));
+    assert!(yaml.contains("  rs0-0:\n"));
+    assert!(yaml.contains("  rs0-1:\n"));
+    assert!(yaml.contains("      - \"27018:27017\"\n"));
+    assert!(yaml.contains("  rs0-init:\n"));
+    assert!(yaml.contains("\\\"hidden\\\":true"));
+    assert!(yaml.contains("volumes:\n  rs0-0-data: {}\n  rs0-1-data: {}\n"));
+
+    let_assert!(Ok(topology) = Topology::from_json(r#"{ "type": "sharded", "shards": 2, "sharded_databases": ["test"] }"#));
+    let yaml = topology.to_compose_yaml("mongo:7.0");
+    assert!(yaml.contains("  config-0:\n"));
+    assert!(yaml.contains("  shard1-0:\n"));
+    assert!(yaml.contains("      - \"config/config-0:27017\"\n"));
+    assert!(yaml.contains("addShard: \\\"shard1/shard1-0:27017\\\""));
+}
+
+/// Gives each scenario its own database on the shared server
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn scenario_mongo() {
+    use temp_mongo::ScenarioMongo;
+
+    let mut first = ScenarioMongo::default();
+    let_assert!(Ok(mut second) = ScenarioMongo::new().await);
+    let_assert!(Ok(first_name) = first.database().await.map(|database| database.name().to_string()));
+    let_assert!(Ok(second_name) = second.database().await.map(|database| database.name().to_string()));
+    assert!(first_name != second_name);
+
+    let_assert!(Ok(database) = first.database().await);
+    assert!(let Ok(_) = database.collection::<Document>("animals").insert_one(doc! { "species": "dog" }, None).await);
+}
+
+/// Creates an instance for benchmarks synchronously
+#[test]
+fn for_benchmarks() {
+    let_assert!(Ok(mongo) = TempMongo::for_benchmarks());
+    let collection = mongo.client().database("test").collection::<Document>("animals");
+    assert!(let Ok(_) = mongo.block_on(collection.insert_one(doc! { "species": "dog" }, None)));
+    assert!(let Ok(()) = mongo.reset_between_iterations());
+    let_assert!(Ok(names) = mongo.run(|client| async move { client.list_database_names(None, None).await }));
+    assert!(!names.contains(&"test".to_string()));
+}
+
+/// Runs each generated case against clean data
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn case_runner() {
+    let_assert!(Ok(mongo) = TempMongo::new().await);
+    let mut runner = mongo.case_runner();
+    for count in 0..3 {
+        let_assert!(Ok(Ok(documents)) = runner.run_case(|mongo| async move {
+            let collection = mongo.client().database("test").collection::<Document>("counts");
+            collection.insert_one(doc! { "count": count }, None).await?;
+            collection.count_documents(None, None).await
+        }).await);
+        assert!(documents == 1);
+    }
+    assert!(runner.cases_run() == 3);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Derives a unique database name from the test name and a run ID
+#[test]
+fn isolated_database_name() {
+    let first = temp_mongo::isolated_database_name("run-1");
+    let second = temp_mongo::isolated_database_name("run-1");
+    assert!(first == "run-1_isolated_database_name");
+    assert!(second == "run-1_isolated_database_name_2");
+
+    let long = temp_mongo::isolated_database_name(&"x".repeat(100));
+    assert!(long.len() == 63);
+    assert!(long != temp_mongo::isolated_database_name(&"x".repeat(101)));
+}
+
+/// Refuses a test database that already exists on the server
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn isolated_database() {
+    let_assert!(Ok(mongo) = TempMongo::new().await);
+    let_assert!(Ok(database) = mongo.isolated_database("run-1").await);
+    assert!(database.name() == "run-1_isolated_database");
+    assert!(let Ok(_) = database.collection::<Document>("animals").insert_one(doc! { "species": "dog" }, None).await);
+
+    // Another run with the same run ID already created the database.
+    let other_run = mongo.client().database("run-2_isolated_database");
+    assert!(let Ok(_) = other_run.collection::<Document>("animals").insert_one(doc! { "species": "cat" }, None).await);
+    assert!(let Err(_) = mongo.isolated_database("run-2").await);
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Creates the state of a web application and cleans up after it
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn app_state() {
+    let_assert!(Ok(mongo) = TempMongo::new().await);
+    let uri: String = mongo.app_state();
+    assert!(uri == mongo.uri());
+    let database: mongodb::Database = mongo.app_state();
+    assert!(database.name() == "test");
+
+    let_assert!(Ok(Ok(_)) = mongo.run_app(|client: mongodb::Client| async move {
+        client.database("test").collection::<Document>("animals").insert_one(doc! { "species": "dog" }, None).await
+    }).await);
+}
+
+/// Compares collections with a golden file, whatever the insertion order
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn golden_file() {
+    let_assert!(Ok(mongo) = TempMongo::new().await);
+    let collection = mongo.client().database("test").collection::<Document>("animals");
+    assert!(let Ok(_) = collection.insert_many([doc! { "species": "dog", "legs": 4 }, doc! { "legs": 2, "species": "bird" }], None).await);
+
+    let path = mongo.directory().join("animals.golden.json");
+    assert!(let Ok(()) = mongo.dump_golden("test", &["animals"], &path).await);
+    let_assert!(Ok(golden) = std::fs::read_to_string(&path));
+    assert!(!golden.contains("_id"));
+
+    let reordered = mongo.client().database("reordered").collection::<Document>("animals");
+    assert!(let Ok(_) = reordered.insert_many([doc! { "species": "bird", "legs": 2 }, doc! { "species": "dog", "legs": 4 }], None).await);
+    mongo.assert_matches_golden("reordered", &["animals"], &path).await;
+
+    let_assert!(Some(diff) = temp_mongo::golden_diff(&golden, &golden.replace("dog", "cat")));
+    assert!(diff.contains("-       \"species\": \"dog\""));
+    assert!(diff.contains("+       \"species\": \"cat\""));
+
+    assert!(let Ok(()) = mongo.kill_and_clean().await);
+}
+
+/// Does not retry spawning a server that can not be found
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn spawn_with_retries() {
+    use std::time::{Duration, Instant};
+
+    let policy = temp_mongo::RetryPolicy::new(5).delay(Duration::from_secs(10));
+    assert!(policy.delay_after(1) == Duration::from_secs(10));
+
+    let start = Instant::now();
+    let_assert!(Err(e) = TempMongo::builder().mongod_command("temp-mongo-does-not-exist").spawn_with_retries(policy).await);
+    assert!(!e.is_infrastructure());
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
+
+/// Does not retry spawning a server that exits on an invalid argument
+#[cfg_attr(feature = "tokio-runtime", tokio::test)]
+#[cfg_attr(feature = "async-std-runtime", async_std::test)]
+async fn spawn_with_retries_invalid_argument() {
+    use std::time::{Duration, Instant};
+
+    // The delay is longer than the server selection timeout of the client, so a retry would be noticed.
+    let policy = temp_mongo::RetryPolicy::new(2).delay(Duration::from_secs(60));
+
+    let start = Instant::now();
+    let_assert!(Err(e) = TempMongo::builder().set_parameter("temp-mongo-does-not-exist", 1).spawn_with_retries(policy).await);
+    assert!(!e.is_infrastructure());
+    assert!(e.to_string().contains("The server has exited"));
+    assert!(start.elapsed() < Duration::from_secs(60));
+}